@@ -0,0 +1,62 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use anyhow::{Context, Result};
+use std::{fs, path::Path};
+
+use crate::system_packages::SystemPackage;
+
+/// Database of the installed packages of Alpine Linux, relative to the root directory.
+pub const INSTALLED_DB_FILE: &str = "lib/apk/db/installed";
+
+/// Parses the apk database. Packages are separated by empty lines, each line is a single letter key
+/// and its value: `P` name, `V` version, `L` license, `U` url, `F` directory and `R` file of the
+/// preceding directory.
+pub fn parse_installed(content: &str) -> Vec<SystemPackage> {
+    let mut packages = vec![];
+    let mut package: Option<SystemPackage> = None;
+    let mut dir = String::new();
+    for line in content.lines().chain(std::iter::once("")) {
+        let Some((key, value)) = line.split_once(':') else {
+            if line.is_empty() {
+                packages.extend(package.take());
+                dir.clear();
+            }
+            continue;
+        };
+        let package = package.get_or_insert_with(|| SystemPackage {
+            name: String::new(),
+            version: None,
+            license: None,
+            url: None,
+            files: vec![],
+        });
+        let value = value.trim();
+        match key {
+            "P" => package.name = value.to_owned(),
+            "V" => package.version = Some(value.to_owned()),
+            "L" if !value.is_empty() => package.license = Some(value.to_owned()),
+            "U" if !value.is_empty() => package.url = Some(value.to_owned()),
+            "F" => dir = value.to_owned(),
+            "R" if dir.is_empty() => package.files.push(value.to_owned()),
+            "R" => package.files.push(format!("{dir}/{value}")),
+            _ => {}
+        }
+    }
+    packages.retain(|package| !package.name.is_empty());
+    packages
+}
+
+/// Reads the installed packages of the apk database in `root`.
+pub fn read_installed_packages(root: &Path) -> Result<Vec<SystemPackage>> {
+    let db_file = root.join(INSTALLED_DB_FILE);
+    let content = fs::read_to_string(&db_file).with_context(|| format!("Cannot read apk database {db_file:?}"))?;
+    Ok(parse_installed(&content))
+}
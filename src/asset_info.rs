@@ -0,0 +1,359 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use anyhow::Result;
+use log::*;
+use std::{fs, path::PathBuf};
+
+use crate::{
+    file_info::{self, FileInfo},
+    license_detector::LicenseDetector,
+    license_info::{self, LicenseInfo},
+    settings,
+    system_packages::SystemPackages,
+    vcpkg_status::VcpkgStatus,
+};
+
+const FONT_EXTENSIONS: [&str; 3] = ["ttf", "otf", "ttc"];
+const ICON_THEME_FILE: &str = "index.theme";
+const ICONS_DIR: &str = "icons";
+const ICU_DATA_PREFIX: &str = "icudt";
+const ICU_DATA_EXTENSION: &str = "dat";
+
+const TTC_TAG: &[u8] = b"ttcf";
+const NAME_TABLE_TAG: &[u8] = b"name";
+const NAME_ID_COPYRIGHT: u16 = 0;
+const NAME_ID_FAMILY: u16 = 1;
+const NAME_ID_VERSION: u16 = 5;
+const NAME_ID_LICENSE_DESCRIPTION: u16 = 13;
+const NAME_ID_LICENSE_URL: u16 = 14;
+const PLATFORM_UNICODE: u16 = 0;
+const PLATFORM_MACINTOSH: u16 = 1;
+const PLATFORM_WINDOWS: u16 = 3;
+
+/// SPDX ids of the license descriptions and URLs found in font name tables.
+/// The patterns are matched case insensitive against a part of the text, the first match wins.
+const FONT_LICENSE_NAMES: [(&str, &str); 9] = [
+    ("openfontlicense.org", "OFL-1.1"),
+    ("scripts.sil.org/ofl", "OFL-1.1"),
+    ("sil open font license", "OFL-1.1"),
+    ("apache.org/licenses/license-2.0", "Apache-2.0"),
+    ("apache license, version 2.0", "Apache-2.0"),
+    ("ubuntu font licen", "Ubuntu-font-1.0"),
+    ("bitstream vera", "Bitstream-Vera"),
+    ("gust font license", "LPPL-1.3c"),
+    ("creativecommons.org/licenses/by/4.0", "CC-BY-4.0"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetKind {
+    Font,
+    IconTheme,
+    Data,
+}
+
+impl AssetKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AssetKind::Font => "font",
+            AssetKind::IconTheme => "icon theme",
+            AssetKind::Data => "data file",
+        }
+    }
+}
+
+/// License relevant strings of a font name table.
+#[derive(Debug, Clone, Default)]
+pub struct FontNames {
+    pub family: Option<String>,
+    pub version: Option<String>,
+    pub copyright: Option<String>,
+    pub license_description: Option<String>,
+    pub license_url: Option<String>,
+}
+
+/// A non-code file of the package that carries a license, e.g. a font or an icon theme.
+#[derive(Debug, Clone)]
+pub struct Asset {
+    pub kind: AssetKind,
+    /// Font family, icon theme or file name
+    pub name: String,
+    pub file: FileInfo,
+    /// Path of the file relative to the package directory
+    pub relative_path: String,
+    pub font_names: Option<FontNames>,
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn decode_name(platform: u16, bytes: &[u8]) -> String {
+    match platform {
+        PLATFORM_UNICODE | PLATFORM_WINDOWS => {
+            let units = bytes
+                .chunks_exact(2)
+                .map(|x| u16::from_be_bytes([x[0], x[1]]))
+                .collect::<Vec<_>>();
+            String::from_utf16_lossy(&units)
+        }
+        // Mac Roman, the license relevant strings are ASCII
+        _ => bytes.iter().map(|&x| x as char).collect(),
+    }
+}
+
+/// Reads the name table of a TrueType or OpenType font, for collections the first font is read.
+pub fn read_font_names(data: &[u8]) -> Option<FontNames> {
+    let font_offset = if data.starts_with(TTC_TAG) {
+        read_u32(data, 12)? as usize
+    } else {
+        0
+    };
+    let num_tables = read_u16(data, font_offset + 4)? as usize;
+    let name_table = (0..num_tables)
+        .map(|index| font_offset + 12 + index * 16)
+        .find(|&record| data.get(record..record + 4) == Some(NAME_TABLE_TAG))
+        .and_then(|record| read_u32(data, record + 8))? as usize;
+
+    let count = read_u16(data, name_table + 2)? as usize;
+    let string_offset = name_table + read_u16(data, name_table + 4)? as usize;
+    // The english Windows names are preferred, other platforms are used as fallback:
+    let mut names: Vec<(u16, String, bool)> = vec![];
+    for index in 0..count {
+        let record = name_table + 6 + index * 12;
+        let (Some(platform), Some(language), Some(name_id), Some(length), Some(offset)) = (
+            read_u16(data, record),
+            read_u16(data, record + 4),
+            read_u16(data, record + 6),
+            read_u16(data, record + 8),
+            read_u16(data, record + 10),
+        ) else {
+            break;
+        };
+        if ![PLATFORM_UNICODE, PLATFORM_MACINTOSH, PLATFORM_WINDOWS].contains(&platform) {
+            continue;
+        }
+        let start = string_offset + offset as usize;
+        let Some(bytes) = data.get(start..start + length as usize) else {
+            continue;
+        };
+        let preferred = platform == PLATFORM_WINDOWS && language == 0x409;
+        let value = decode_name(platform, bytes).trim().to_owned();
+        if value.is_empty() {
+            continue;
+        }
+        match names.iter_mut().find(|(id, _, _)| *id == name_id) {
+            Some(name) if preferred && !name.2 => *name = (name_id, value, preferred),
+            Some(_) => {}
+            None => names.push((name_id, value, preferred)),
+        }
+    }
+
+    let name = |name_id: u16| {
+        names
+            .iter()
+            .find(|(id, _, _)| *id == name_id)
+            .map(|(_, x, _)| x.clone())
+    };
+    Some(FontNames {
+        family: name(NAME_ID_FAMILY),
+        version: name(NAME_ID_VERSION),
+        copyright: name(NAME_ID_COPYRIGHT),
+        license_description: name(NAME_ID_LICENSE_DESCRIPTION),
+        license_url: name(NAME_ID_LICENSE_URL),
+    })
+}
+
+impl FontNames {
+    /// Maps the license URL or the license description to an SPDX id.
+    pub fn license(&self) -> Option<String> {
+        [&self.license_url, &self.license_description]
+            .into_iter()
+            .flatten()
+            .find_map(|text| {
+                let text = text.to_lowercase();
+                FONT_LICENSE_NAMES
+                    .iter()
+                    .find(|(pattern, _)| text.contains(pattern))
+                    .map(|(_, id)| (*id).to_owned())
+            })
+    }
+}
+
+impl Asset {}
+
+fn classify(file: &FileInfo, package_path: &PathBuf) -> Option<Asset> {
+    let relative_path = file
+        .path
+        .strip_prefix(package_path)
+        .unwrap_or(&file.path)
+        .to_string_lossy()
+        .into_owned();
+    let extension = file.extension.as_deref().map(str::to_lowercase).unwrap_or_default();
+
+    if FONT_EXTENSIONS.contains(&extension.as_str()) {
+        let font_names = fs::read(&file.path).ok().and_then(|data| read_font_names(&data));
+        if font_names.is_none() {
+            warn!("Cannot read the name table of font {:?}", file.path);
+        }
+        return Some(Asset {
+            kind: AssetKind::Font,
+            name: font_names
+                .as_ref()
+                .and_then(|x| x.family.clone())
+                .unwrap_or_else(|| file.name.clone()),
+            file: file.clone(),
+            relative_path,
+            font_names,
+        });
+    }
+    if file.name == ICON_THEME_FILE {
+        let theme_dir = file.path.parent()?;
+        if theme_dir.parent()?.file_name()? != ICONS_DIR {
+            return None;
+        }
+        return Some(Asset {
+            kind: AssetKind::IconTheme,
+            name: theme_dir.file_name()?.to_string_lossy().into_owned(),
+            file: file.clone(),
+            relative_path,
+            font_names: None,
+        });
+    }
+    if file.name.starts_with(ICU_DATA_PREFIX) && extension == ICU_DATA_EXTENSION {
+        return Some(Asset {
+            kind: AssetKind::Data,
+            name: file.name.clone(),
+            file: file.clone(),
+            relative_path,
+            font_names: None,
+        });
+    }
+    None
+}
+
+/// Finds fonts, icon themes and ICU data files in a directory tree.
+pub fn find_assets(package_path: &PathBuf, ignore_list: &[String]) -> std::io::Result<Vec<Asset>> {
+    Ok(file_info::find_files_recurse(package_path, "", ignore_list)?
+        .iter()
+        .filter_map(|file| classify(file, package_path))
+        .collect())
+}
+
+fn add_asset(licenses: &mut Vec<LicenseInfo>, info: LicenseInfo, asset: &Asset) {
+    if let Some(existing) = licenses.iter_mut().find(|x| x.package_name == info.package_name) {
+        if !existing.binaries.contains(&asset.relative_path) {
+            existing.binaries.push(asset.relative_path.clone());
+        }
+    } else {
+        let mut info = info;
+        info.binaries = vec![asset.relative_path.clone()];
+        licenses.push(info);
+    }
+}
+
+/// License of an asset that is not owned by a package, taken from the font name table or an override.
+fn unowned_license_info(
+    asset: &Asset,
+    overrides: &settings::Overrides,
+    detector: &LicenseDetector,
+) -> Result<LicenseInfo> {
+    let font_names = asset.font_names.clone().unwrap_or_default();
+    let override_info =
+        settings::Override::find_override("assets", &asset.name, font_names.version.as_deref(), &[], overrides);
+    let (license, license_files) = license_info::apply_override(override_info, font_names.license(), vec![]);
+
+    let license_expression = license
+        .as_deref()
+        .and_then(|license| license_info::parse_license_expression(&asset.name, license));
+    let license_ids = license_info::license_ids(license_expression.as_ref());
+    let license_texts = detector.detect_licenses(&asset.name, license_ids.as_slice(), license_files.as_slice())?;
+
+    match (&license, override_info) {
+        (_, Some(_)) => {}
+        (Some(license), None) => warn!(
+            "The {} '{}' is not owned by a vcpkg or system package, please review its license '{license}'",
+            asset.kind.name(),
+            asset.relative_path
+        ),
+        (None, None) => warn!(
+            "The {} '{}' is not owned by a vcpkg or system package and has no license, please add an override",
+            asset.kind.name(),
+            asset.relative_path
+        ),
+    }
+
+    Ok(LicenseInfo {
+        lib_info: asset.file.clone(),
+        package_name: asset.name.clone(),
+        license: license.unwrap_or_else(|| "NOASSERTION".to_owned()),
+        license_expression,
+        license_texts,
+        version: font_names.version,
+        url: font_names.license_url,
+        features: vec![],
+        dependencies: vec![],
+        binaries: vec![],
+    })
+}
+
+/// Attributes the assets to the vcpkg port or the system package that installed them.
+/// The licenses of vcpkg ports are taken from `vcpkg_licenses`, assets without owner are
+/// reported with the license of their metadata and flagged for review.
+pub fn get_license_info(
+    assets: &[Asset],
+    vcpkg_status: &VcpkgStatus,
+    vcpkg_licenses: &[LicenseInfo],
+    system_packages: Option<&SystemPackages>,
+    overrides: &settings::Overrides,
+    detector: &LicenseDetector,
+) -> Result<Vec<LicenseInfo>> {
+    let mut licenses: Vec<LicenseInfo> = vec![];
+    for asset in assets {
+        trace!("Query owner of {} '{}'", asset.kind.name(), asset.relative_path);
+
+        if let Some(port) = vcpkg_status.find_port_of_triplet_file(&asset.relative_path) {
+            match vcpkg_licenses.iter().find(|x| x.package_name == port.name) {
+                Some(info) => {
+                    let mut info = info.clone();
+                    info.lib_info = asset.file.clone();
+                    add_asset(&mut licenses, info, asset);
+                    continue;
+                }
+                None => warn!(
+                    "The {} '{}' is owned by vcpkg port '{}' without license information",
+                    asset.kind.name(),
+                    asset.relative_path,
+                    port.name
+                ),
+            }
+        }
+
+        if let Some(system_packages) = system_packages {
+            match system_packages.package_name_of_file(&asset.relative_path) {
+                Ok(Some(package_name)) => {
+                    let info = system_packages.license_info(&package_name, asset.file.clone(), overrides, detector)?;
+                    add_asset(&mut licenses, info, asset);
+                    continue;
+                }
+                Ok(None) => {}
+                Err(error) => warn!("{error:?}"),
+            }
+        }
+
+        let info = unowned_license_info(asset, overrides, detector)?;
+        add_asset(&mut licenses, info, asset);
+    }
+    Ok(licenses)
+}
@@ -0,0 +1,237 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use anyhow::{Context, Result};
+use object::{
+    FileKind, LittleEndian, Object, ObjectSection, ReadRef,
+    elf::{DT_NEEDED, DT_RPATH, DT_RUNPATH, DT_SONAME},
+    macho::{FatArch32, FatArch64},
+    read::{
+        elf::{ElfFile, FileHeader},
+        macho::{FatArch, LoadCommandVariant, MachHeader, MachOFatFile, MachOFile},
+        pe::{ImageNtHeaders, PeFile},
+    },
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::file_info::{self, FileInfo};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryFormat {
+    Elf,
+    Pe,
+    MachO,
+}
+
+/// Dynamic linking information of an executable or shared library.
+#[derive(Debug, Clone)]
+pub struct BinaryInfo {
+    pub format: BinaryFormat,
+    pub is_64: bool,
+    /// SONAME of an ELF library or the install name of a Mach-O dylib
+    pub name: Option<String>,
+    /// Libraries the binary is linked against (ELF `DT_NEEDED`, PE import table, Mach-O `LC_LOAD_DYLIB`)
+    pub needed: Vec<String>,
+    /// Library search paths (ELF `DT_RUNPATH`/`DT_RPATH`, Mach-O `LC_RPATH`)
+    pub search_paths: Vec<String>,
+}
+
+fn to_string(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+fn push_unique(list: &mut Vec<String>, value: String) {
+    if !list.contains(&value) {
+        list.push(value);
+    }
+}
+
+fn parse_elf<'data, Elf: FileHeader, R: ReadRef<'data>>(data: R) -> Result<BinaryInfo> {
+    let file = ElfFile::<Elf, R>::parse(data)?;
+    let endian = file.endian();
+    let dynamic_table = file.elf_section_table().dynamic_table(endian, data)?;
+
+    let mut info = BinaryInfo {
+        format: BinaryFormat::Elf,
+        is_64: file.elf_header().is_type_64(),
+        name: None,
+        needed: vec![],
+        search_paths: vec![],
+    };
+    for entry in dynamic_table.iter() {
+        if !entry.is_string() {
+            continue;
+        }
+        let value = to_string(dynamic_table.string(entry)?);
+        if entry.tag == DT_NEEDED {
+            push_unique(&mut info.needed, value);
+        } else if entry.tag == DT_SONAME {
+            info.name = Some(value);
+        } else if entry.tag == DT_RUNPATH || entry.tag == DT_RPATH {
+            for path in value.split(':').filter(|x| !x.is_empty()) {
+                push_unique(&mut info.search_paths, path.to_owned());
+            }
+        }
+    }
+    Ok(info)
+}
+
+fn parse_pe<'data, Pe: ImageNtHeaders, R: ReadRef<'data>>(data: R) -> Result<BinaryInfo> {
+    let file = PeFile::<Pe, R>::parse(data)?;
+
+    let mut info = BinaryInfo {
+        format: BinaryFormat::Pe,
+        is_64: file.is_64(),
+        name: None,
+        needed: vec![],
+        search_paths: vec![],
+    };
+    if let Some(import_table) = file.import_table()? {
+        for descriptor in import_table.descriptors()? {
+            let descriptor = descriptor?;
+            let name = import_table.name(descriptor.name.get(LittleEndian))?;
+            push_unique(&mut info.needed, to_string(name));
+        }
+    }
+    if let Some(delay_load_table) = file.delay_load_import_table()? {
+        for descriptor in delay_load_table.descriptors()? {
+            let descriptor = descriptor?;
+            let name = delay_load_table.name(descriptor.dll_name_rva.get(LittleEndian))?;
+            push_unique(&mut info.needed, to_string(name));
+        }
+    }
+    Ok(info)
+}
+
+fn parse_macho<'data, Mach: MachHeader, R: ReadRef<'data>>(data: R) -> Result<BinaryInfo> {
+    let file = MachOFile::<Mach, R>::parse(data)?;
+    let endian = file.endian();
+
+    let mut info = BinaryInfo {
+        format: BinaryFormat::MachO,
+        is_64: file.macho_header().is_type_64(),
+        name: None,
+        needed: vec![],
+        search_paths: vec![],
+    };
+    let mut commands = file.macho_load_commands()?;
+    while let Some(command) = commands.next()? {
+        match command.variant()? {
+            LoadCommandVariant::Dylib(dylib) => {
+                push_unique(&mut info.needed, to_string(command.string(endian, dylib.dylib.name)?));
+            }
+            LoadCommandVariant::IdDylib(dylib) => {
+                info.name = Some(to_string(command.string(endian, dylib.dylib.name)?));
+            }
+            LoadCommandVariant::Rpath(rpath) => {
+                push_unique(&mut info.search_paths, to_string(command.string(endian, rpath.path)?));
+            }
+            _ => {}
+        }
+    }
+    Ok(info)
+}
+
+fn parse_macho_fat<Fat: FatArch>(data: &[u8]) -> Result<Option<BinaryInfo>> {
+    let file = MachOFatFile::<Fat>::parse(data)?;
+    // All architectures of a universal binary link the same libraries, so the first one is sufficient:
+    let Some(arch) = file.arches().first() else {
+        return Ok(None);
+    };
+    let data = arch.data(data)?;
+    match FileKind::parse(data)? {
+        FileKind::MachO32 => parse_macho::<object::macho::MachHeader32<object::Endianness>, _>(data).map(Some),
+        FileKind::MachO64 => parse_macho::<object::macho::MachHeader64<object::Endianness>, _>(data).map(Some),
+        _ => Ok(None),
+    }
+}
+
+impl BinaryInfo {
+    /// Parses the dynamic linking information, returns `None` if the data is no ELF, PE or Mach-O binary.
+    pub fn parse(data: &[u8]) -> Result<Option<Self>> {
+        let Ok(kind) = FileKind::parse(data) else {
+            return Ok(None);
+        };
+        let info = match kind {
+            FileKind::Elf32 => parse_elf::<object::elf::FileHeader32<object::Endianness>, _>(data)?,
+            FileKind::Elf64 => parse_elf::<object::elf::FileHeader64<object::Endianness>, _>(data)?,
+            FileKind::Pe32 => parse_pe::<object::pe::ImageNtHeaders32, _>(data)?,
+            FileKind::Pe64 => parse_pe::<object::pe::ImageNtHeaders64, _>(data)?,
+            FileKind::MachO32 => parse_macho::<object::macho::MachHeader32<object::Endianness>, _>(data)?,
+            FileKind::MachO64 => parse_macho::<object::macho::MachHeader64<object::Endianness>, _>(data)?,
+            FileKind::MachOFat32 => return parse_macho_fat::<FatArch32>(data),
+            FileKind::MachOFat64 => return parse_macho_fat::<FatArch64>(data),
+            _ => return Ok(None),
+        };
+        Ok(Some(info))
+    }
+
+    pub fn read(file: &Path) -> Result<Option<Self>> {
+        let data = fs::read(file).with_context(|| format!("Cannot read binary {file:?}"))?;
+        Self::parse(data.as_slice()).with_context(|| format!("Cannot parse binary {file:?}"))
+    }
+
+    /// File name of a needed library, Mach-O libraries are referenced by path (e.g. `@rpath/libz.1.dylib`).
+    pub fn needed_file_names(&self) -> Vec<String> {
+        self.needed
+            .iter()
+            .map(|needed| match self.format {
+                BinaryFormat::MachO => needed.rsplit('/').next().unwrap_or(needed).to_owned(),
+                _ => needed.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Returns the uncompressed content of a section, e.g. `.dep-v0` or `.go.buildinfo`.
+pub fn section_data(data: &[u8], name: &str) -> Result<Option<Vec<u8>>> {
+    let file = object::File::parse(data)?;
+    match file.section_by_name(name) {
+        Some(section) => Ok(Some(section.uncompressed_data()?.into_owned())),
+        None => Ok(None),
+    }
+}
+
+/// Checks if a binary has section headers, stripped binaries or unknown formats may have none.
+pub fn has_sections(data: &[u8]) -> bool {
+    object::File::parse(data).is_ok_and(|file| file.sections().next().is_some())
+}
+
+/// Finds all ELF, PE and Mach-O binaries in a directory tree.
+pub fn find_binaries_recurse(path: &PathBuf, ignore_list: &[String]) -> std::io::Result<Vec<FileInfo>> {
+    Ok(file_info::find_files_recurse(path, "", ignore_list)?
+        .into_iter()
+        .filter(|file| is_binary(&file.path))
+        .collect())
+}
+
+/// Checks the magic bytes of a file to find out if it is an ELF, PE or Mach-O binary.
+pub fn is_binary(file: &Path) -> bool {
+    use std::io::Read;
+
+    let mut magic = [0u8; 4];
+    let Ok(mut f) = fs::File::open(file) else {
+        return false;
+    };
+    if f.read_exact(&mut magic).is_err() {
+        return false;
+    }
+    matches!(
+        magic,
+        [0x7f, b'E', b'L', b'F']
+            | [b'M', b'Z', _, _]
+            | [0xfe, 0xed, 0xfa, 0xce | 0xcf]
+            | [0xce | 0xcf, 0xfa, 0xed, 0xfe]
+            | [0xca, 0xfe, 0xba, 0xbe | 0xbf]
+    )
+}
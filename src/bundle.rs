@@ -0,0 +1,460 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use anyhow::{Context, Result};
+use log::*;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{
+    binary_info::BinaryInfo,
+    file_info::FileInfo,
+    license_detector::{LicenseDetector, LicenseFile},
+    license_info::{self, LicenseInfo},
+    settings,
+};
+
+const FLATPAK_METADATA_FILE: &str = "metadata";
+const FLATPAK_FILES_DIR: &str = "files";
+const SNAP_YAML_FILE: &str = "meta/snap.yaml";
+const SNAP_MANIFEST_FILE: &str = "snap/manifest.yaml";
+const SNAP_DEFAULT_BASE: &str = "core";
+const DEBIAN_DOC_DIR: &str = "usr/share/doc";
+const DEBIAN_COPYRIGHT_FILE: &str = "copyright";
+/// Suffix of Debian library packages rebuilt for the 64 bit `time_t` transition.
+const DEBIAN_T64_SUFFIX: &str = "t64";
+
+/// SPDX ids of the short license names used in Debian copyright files, other names are
+/// looked up in the SPDX license list.
+const DEBIAN_LICENSE_NAMES: [(&str, &str); 16] = [
+    ("expat", "MIT"),
+    ("gpl-2", "GPL-2.0-only"),
+    ("gpl-2+", "GPL-2.0-or-later"),
+    ("gpl-3", "GPL-3.0-only"),
+    ("gpl-3+", "GPL-3.0-or-later"),
+    ("lgpl-2", "LGPL-2.0-only"),
+    ("lgpl-2+", "LGPL-2.0-or-later"),
+    ("lgpl-2.1", "LGPL-2.1-only"),
+    ("lgpl-2.1+", "LGPL-2.1-or-later"),
+    ("lgpl-3", "LGPL-3.0-only"),
+    ("lgpl-3+", "LGPL-3.0-or-later"),
+    ("bsd-2-clause", "BSD-2-Clause"),
+    ("bsd-3-clause", "BSD-3-Clause"),
+    ("bsd-4-clause", "BSD-4-Clause"),
+    ("mpl-2.0", "MPL-2.0"),
+    ("zlib", "Zlib"),
+];
+
+/// Layout of the package directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundleKind {
+    /// AppDir or any other directory containing the installed files
+    Directory,
+    /// Flatpak build directory or OSTree checkout with `metadata` and the `files/` tree
+    Flatpak,
+    /// Unpacked snap with `meta/snap.yaml`
+    Snap,
+}
+
+impl BundleKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Directory => "directory",
+            Self::Flatpak => "flatpak",
+            Self::Snap => "snap",
+        }
+    }
+
+    /// Detects the layout of a package directory.
+    pub fn detect(package_path: &Path) -> Self {
+        if package_path.join(FLATPAK_METADATA_FILE).is_file() && package_path.join(FLATPAK_FILES_DIR).is_dir() {
+            Self::Flatpak
+        } else if package_path.join(SNAP_YAML_FILE).is_file() {
+            Self::Snap
+        } else {
+            Self::Directory
+        }
+    }
+}
+
+impl FromStr for BundleKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "directory" | "appdir" => Ok(Self::Directory),
+            "flatpak" => Ok(Self::Flatpak),
+            "snap" => Ok(Self::Snap),
+            _ => Err(anyhow::anyhow!(
+                "Unknown bundle '{s}', supported are 'directory', 'flatpak' and 'snap'"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for BundleKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A Debian package staged into a snap.
+#[derive(Debug, Clone)]
+pub struct StagedPackage {
+    pub name: String,
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SnapYaml {
+    name: Option<String>,
+    version: Option<String>,
+    base: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct SnapPart {
+    #[serde(default)]
+    stage_packages: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct SnapManifest {
+    #[serde(default)]
+    parts: BTreeMap<String, SnapPart>,
+    #[serde(default)]
+    primed_stage_packages: Vec<String>,
+}
+
+/// The package to analyze, the libraries of Flatpak and Snap bundles not shipped with the bundle
+/// are provided by their runtime.
+#[derive(Debug, Clone)]
+pub struct Bundle {
+    pub kind: BundleKind,
+    /// Directory containing the installed files of the bundle
+    pub root: PathBuf,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    /// Flatpak runtime reference (e.g. `org.freedesktop.Platform/x86_64/23.08`) or snap base
+    pub runtime: Option<String>,
+    pub staged_packages: Vec<StagedPackage>,
+}
+
+/// Reads a key of a section of the Flatpak `metadata` key file.
+fn key_file_value(content: &str, section: &str, key: &str) -> Option<String> {
+    let mut in_section = false;
+    for line in content.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+            in_section = name == section;
+        } else if in_section
+            && let Some((k, v)) = line.split_once('=')
+            && k.trim() == key
+        {
+            return Some(v.trim().to_owned());
+        }
+    }
+    None
+}
+
+fn parse_staged_package(entry: &str) -> StagedPackage {
+    match entry.split_once('=') {
+        Some((name, version)) => StagedPackage {
+            name: name.trim().to_owned(),
+            version: Some(version.trim().to_owned()),
+        },
+        None => StagedPackage {
+            name: entry.trim().to_owned(),
+            version: None,
+        },
+    }
+}
+
+impl Bundle {
+    /// Opens a package directory, the layout is detected if `kind` is not given.
+    pub fn open(package_path: &Path, kind: Option<BundleKind>) -> Result<Self> {
+        let kind = kind.unwrap_or_else(|| BundleKind::detect(package_path));
+        let mut bundle = Self {
+            kind,
+            root: package_path.to_path_buf(),
+            name: None,
+            version: None,
+            runtime: None,
+            staged_packages: vec![],
+        };
+        match kind {
+            BundleKind::Directory => {}
+            BundleKind::Flatpak => {
+                let metadata_file = package_path.join(FLATPAK_METADATA_FILE);
+                let metadata = fs::read_to_string(&metadata_file)
+                    .with_context(|| format!("Cannot read Flatpak metadata {metadata_file:?}"))?;
+                // Applications are described by the `[Application]` section, runtimes by the `[Runtime]` section:
+                let section = if metadata.contains("[Application]") {
+                    "Application"
+                } else {
+                    "Runtime"
+                };
+                bundle.name = key_file_value(&metadata, section, "name");
+                bundle.runtime = key_file_value(&metadata, section, "runtime");
+                bundle.root = package_path.join(FLATPAK_FILES_DIR);
+            }
+            BundleKind::Snap => {
+                let snap_file = package_path.join(SNAP_YAML_FILE);
+                let content = fs::read_to_string(&snap_file)
+                    .with_context(|| format!("Cannot read snap metadata {snap_file:?}"))?;
+                let snap = serde_yaml_bw::from_str::<SnapYaml>(&content)
+                    .with_context(|| format!("Cannot parse snap metadata {snap_file:?}"))?;
+                bundle.name = snap.name;
+                bundle.version = snap.version;
+                bundle.runtime = Some(snap.base.unwrap_or_else(|| SNAP_DEFAULT_BASE.to_owned()));
+
+                let manifest_file = package_path.join(SNAP_MANIFEST_FILE);
+                if manifest_file.is_file() {
+                    let content = fs::read_to_string(&manifest_file)
+                        .with_context(|| format!("Cannot read snap manifest {manifest_file:?}"))?;
+                    let manifest = serde_yaml_bw::from_str::<SnapManifest>(&content)
+                        .with_context(|| format!("Cannot parse snap manifest {manifest_file:?}"))?;
+                    let entries = manifest
+                        .parts
+                        .values()
+                        .flat_map(|part| part.stage_packages.iter())
+                        .chain(manifest.primed_stage_packages.iter());
+                    for package in entries.map(|entry| parse_staged_package(entry)) {
+                        if !bundle.staged_packages.iter().any(|x| x.name == package.name) {
+                            bundle.staged_packages.push(package);
+                        }
+                    }
+                } else {
+                    warn!("Cannot find snap manifest {manifest_file:?}, staged packages are not reported");
+                }
+            }
+        }
+        info!(
+            "Analyze {kind} bundle{} at: {:?}",
+            bundle.name.as_ref().map(|x| format!(" '{x}'")).unwrap_or_default(),
+            bundle.root
+        );
+        Ok(bundle)
+    }
+
+    /// Reports the libraries needed by the bundle but not shipped with it as provided by the runtime.
+    pub fn runtime_license_info(&self, provided_libs: &[String]) -> Option<LicenseInfo> {
+        let runtime = self.runtime.as_ref()?;
+        for lib in provided_libs {
+            info!("Library '{lib}' is provided by runtime '{runtime}'");
+        }
+        Some(LicenseInfo {
+            lib_info: FileInfo::new(runtime.clone(), &self.root),
+            package_name: runtime.clone(),
+            license: "NOASSERTION".to_owned(),
+            license_expression: None,
+            license_texts: vec![],
+            version: None,
+            url: None,
+            features: vec![],
+            dependencies: vec![],
+            binaries: provided_libs.to_vec(),
+        })
+    }
+
+    /// Attributes the bundled libraries that are not from vcpkg to the packages staged into a snap.
+    /// All staged packages are reported since they ship more than libraries, libraries without
+    /// staged package are reported for review.
+    pub fn license_info(
+        &self,
+        libs: &[FileInfo],
+        overrides: &settings::Overrides,
+        detector: &LicenseDetector,
+    ) -> Result<Vec<LicenseInfo>> {
+        let mut licenses = vec![];
+        for package in &self.staged_packages {
+            licenses.push(debian_package_license_info(
+                &self.root,
+                &package.name,
+                package.version.clone(),
+                None,
+                overrides,
+                detector,
+            )?);
+        }
+        for lib in libs {
+            let soname = BinaryInfo::read(&lib.path)
+                .ok()
+                .flatten()
+                .and_then(|x| x.name)
+                .unwrap_or_else(|| lib.name.clone());
+            let candidates = debian_package_names(&soname);
+            match licenses
+                .iter_mut()
+                .take(self.staged_packages.len())
+                .find(|info| candidates.contains(&info.package_name))
+            {
+                Some(info) => info.binaries.push(lib.name.clone()),
+                None => licenses.push(unattributed_license_info(lib, self.kind, overrides, detector)?),
+            }
+        }
+        Ok(licenses)
+    }
+}
+
+/// License information of a Debian package installed into `root`, the license is read from the machine
+/// readable copyright file in `usr/share/doc/<package>`.
+pub fn debian_package_license_info(
+    root: &Path,
+    name: &str,
+    version: Option<String>,
+    url: Option<String>,
+    overrides: &settings::Overrides,
+    detector: &LicenseDetector,
+) -> Result<LicenseInfo> {
+    let copyright_file = root.join(DEBIAN_DOC_DIR).join(name).join(DEBIAN_COPYRIGHT_FILE);
+    let (license, license_files) = if copyright_file.is_file() {
+        let content = fs::read_to_string(&copyright_file)
+            .with_context(|| format!("Cannot read Debian copyright file {copyright_file:?}"))?;
+        let license_file = LicenseFile {
+            id: None,
+            file: copyright_file.to_string_lossy().into_owned(),
+        };
+        (copyright_license(&content), vec![license_file])
+    } else {
+        (None, vec![])
+    };
+
+    let override_info = settings::Override::find_override("debian", name, version.as_deref(), &[], overrides);
+    let (license, license_files) = license_info::apply_override(override_info, license, license_files);
+    let license_expression = license
+        .as_deref()
+        .and_then(|license| license_info::parse_license_expression(name, license));
+    let license_ids = license_info::license_ids(license_expression.as_ref());
+    let license_texts = detector.detect_licenses(name, license_ids.as_slice(), license_files.as_slice())?;
+
+    let license = match license {
+        Some(license) => license,
+        None => {
+            warn!("Missing license identifier for Debian package '{name}', please add an override");
+            "NOASSERTION".to_owned()
+        }
+    };
+
+    Ok(LicenseInfo {
+        lib_info: FileInfo::new(DEBIAN_COPYRIGHT_FILE.to_owned(), &copyright_file),
+        package_name: name.to_owned(),
+        license,
+        license_expression,
+        license_texts,
+        version,
+        url,
+        features: vec![],
+        dependencies: vec![],
+        binaries: vec![],
+    })
+}
+
+/// Names of the Debian package shipping a library according to the Debian library packaging policy,
+/// e.g. `libfoo.so.1` is shipped by `libfoo1` and `libfoo-2.0.so.0` by `libfoo-2.0-0`.
+pub fn debian_package_names(soname: &str) -> Vec<String> {
+    let Some((stem, version)) = soname.split_once(".so.") else {
+        return vec![];
+    };
+    let major = version.split('.').next().unwrap_or(version);
+    let name = if stem.ends_with(|c: char| c.is_ascii_digit()) {
+        format!("{stem}-{major}")
+    } else {
+        format!("{stem}{major}")
+    }
+    .to_lowercase()
+    .replace('_', "-");
+    vec![format!("{name}{DEBIAN_T64_SUFFIX}"), name]
+}
+
+/// Maps a Debian license short name to an SPDX id.
+pub fn debian_license_id(name: &str) -> Option<String> {
+    let lower_name = name.trim().to_lowercase();
+    if let Some((_, id)) = DEBIAN_LICENSE_NAMES.iter().find(|(x, _)| *x == lower_name) {
+        return Some((*id).to_owned());
+    }
+    spdx::license_id(name.trim()).map(|id| id.name.to_owned())
+}
+
+/// Collects the licenses of a machine readable Debian copyright file (DEP-5).
+/// Returns `None` if the file is not machine readable or a license cannot be mapped to SPDX.
+pub fn copyright_license(content: &str) -> Option<String> {
+    let mut ids: Vec<String> = vec![];
+    for line in content.lines() {
+        let Some(value) = line.strip_prefix("License:") else {
+            continue;
+        };
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        // Short names are combined with `or`, `and` and `with`, e.g. `GPL-2+ or Artistic`
+        let mut expression = vec![];
+        for token in value.split_whitespace() {
+            match token.to_lowercase().as_str() {
+                "or" | "|" => expression.push("OR".to_owned()),
+                "and" | "," => expression.push("AND".to_owned()),
+                "with" => expression.push("WITH".to_owned()),
+                _ if expression.last().is_some_and(|x| x == "WITH") => expression.push(token.to_owned()),
+                _ => expression.push(debian_license_id(token.trim_end_matches(','))?),
+            }
+        }
+        let id = expression.join(" ");
+        let id = if expression.len() > 1 { format!("({id})") } else { id };
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    match ids.len() {
+        0 => None,
+        1 => Some(ids[0].trim_start_matches('(').trim_end_matches(')').to_owned()),
+        _ => Some(ids.join(" AND ")),
+    }
+}
+
+/// Reports a library that cannot be attributed to a package, its license can be set by an override.
+pub fn unattributed_license_info(
+    lib: &FileInfo,
+    kind: BundleKind,
+    overrides: &settings::Overrides,
+    detector: &LicenseDetector,
+) -> Result<LicenseInfo> {
+    let override_info = settings::Override::find_override(kind.name(), &lib.name, None, &[], overrides);
+    let (license, license_files) = license_info::apply_override(override_info, None, vec![]);
+    let license_expression = license
+        .as_deref()
+        .and_then(|license| license_info::parse_license_expression(&lib.name, license));
+    let license_ids = license_info::license_ids(license_expression.as_ref());
+    let license_texts = detector.detect_licenses(&lib.name, license_ids.as_slice(), license_files.as_slice())?;
+    if license.is_none() {
+        warn!(
+            "Cannot attribute library '{}' of the {kind} bundle to a package, please add an override",
+            lib.name
+        );
+    }
+    Ok(LicenseInfo {
+        lib_info: lib.clone(),
+        package_name: lib.name.clone(),
+        license: license.unwrap_or_else(|| "NOASSERTION".to_owned()),
+        license_expression,
+        license_texts,
+        version: None,
+        url: None,
+        features: vec![],
+        dependencies: vec![],
+        binaries: vec![lib.name.clone()],
+    })
+}
@@ -0,0 +1,155 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use anyhow::{Context, Result, bail};
+use log::*;
+use serde::Deserialize;
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    binary_info, crates_info, file_info::FileInfo, license_detector::LicenseDetector, license_info::LicenseInfo,
+    settings,
+};
+
+/// Section `cargo auditable` embeds the zlib compressed dependency list into.
+const AUDITABLE_SECTION: &str = ".dep-v0";
+const LOCAL_SOURCE: &str = "local";
+/// Limit of the decompressed dependency list to protect against decompression bombs.
+const MAX_DEPENDENCY_LIST_SIZE: u64 = 8 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyKind {
+    Build,
+    #[default]
+    Runtime,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuditablePackage {
+    pub name: String,
+    pub version: String,
+    pub source: String,
+    #[serde(default)]
+    pub kind: DependencyKind,
+    #[serde(default)]
+    pub dependencies: Vec<usize>,
+    #[serde(default)]
+    pub root: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct VersionInfo {
+    packages: Vec<AuditablePackage>,
+}
+
+/// Reads the dependency list `cargo auditable` embeds into a Rust binary.
+/// Returns `None` for binaries without dependency list.
+pub fn read_dependencies(file: &Path) -> Result<Option<Vec<AuditablePackage>>> {
+    let data = fs::read(file).with_context(|| format!("Cannot read binary {file:?}"))?;
+    let Some(compressed) = binary_info::section_data(&data, AUDITABLE_SECTION)
+        .with_context(|| format!("Cannot read section '{AUDITABLE_SECTION}' of binary {file:?}"))?
+    else {
+        return Ok(None);
+    };
+
+    // One byte more than the limit is read to tell a list of the maximum size from a truncated one:
+    let mut json = vec![];
+    flate2::read::ZlibDecoder::new(compressed.as_slice())
+        .take(MAX_DEPENDENCY_LIST_SIZE + 1)
+        .read_to_end(&mut json)
+        .with_context(|| format!("Cannot decompress the dependency list of binary {file:?}"))?;
+    if json.len() as u64 > MAX_DEPENDENCY_LIST_SIZE {
+        bail!("The dependency list of binary {file:?} is too large, it exceeds {MAX_DEPENDENCY_LIST_SIZE} bytes");
+    }
+    let info = serde_json::from_slice::<VersionInfo>(&json)
+        .with_context(|| format!("Cannot parse the dependency list of binary {file:?}"))?;
+    Ok(Some(info.packages))
+}
+
+/// Collects the license information of the crates compiled into the given Rust binaries.
+/// The licenses are taken from the crate sources in `search_dirs`, crates without sources
+/// are reported without license.
+pub fn get_license_info(
+    binaries: &[FileInfo],
+    search_dirs: &[PathBuf],
+    overrides: &settings::Overrides,
+    detector: &LicenseDetector,
+) -> Result<Vec<LicenseInfo>> {
+    let mut licenses: Vec<LicenseInfo> = vec![];
+
+    for binary in binaries {
+        let packages = match read_dependencies(&binary.path) {
+            Ok(Some(packages)) => packages,
+            Ok(None) => continue,
+            Err(error) => {
+                warn!("{error:?}");
+                continue;
+            }
+        };
+        info!(
+            "Found {} crates embedded by cargo auditable in binary '{}'",
+            packages.len(),
+            binary.name
+        );
+
+        for package in &packages {
+            // Build dependencies are not part of the binary and local crates are first party code:
+            if package.kind == DependencyKind::Build || package.root || package.source == LOCAL_SOURCE {
+                continue;
+            }
+            if let Some(info) = licenses
+                .iter_mut()
+                .find(|x| x.package_name == package.name && x.version.as_deref() == Some(package.version.as_str()))
+            {
+                if !info.binaries.contains(&binary.name) {
+                    info.binaries.push(binary.name.clone());
+                }
+                continue;
+            }
+
+            let crate_dir = crates_info::find_crate_dir(&package.name, &package.version, search_dirs);
+            if crate_dir.is_none() {
+                warn!(
+                    "Cannot find the sources of crate '{}@{}' of binary '{}' in the local registry cache",
+                    package.name, package.version, binary.name
+                );
+            }
+            let (license, mut info) = crates_info::crate_license_info(
+                &package.name,
+                &package.version,
+                crate_dir.as_deref(),
+                binary.clone(),
+                overrides,
+                detector,
+            )?;
+            if license.is_none() {
+                warn!(
+                    "Missing license identifier for crate '{}@{}' of binary '{}'",
+                    package.name, package.version, binary.name
+                );
+            }
+            info.dependencies = package
+                .dependencies
+                .iter()
+                .filter_map(|&index| packages.get(index))
+                .filter(|dependency| dependency.kind == DependencyKind::Runtime)
+                .map(|dependency| dependency.name.clone())
+                .collect();
+            info.binaries.push(binary.name.clone());
+            licenses.push(info);
+        }
+    }
+    Ok(licenses)
+}
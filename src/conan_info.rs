@@ -0,0 +1,378 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use anyhow::{Context, Result};
+use log::*;
+use serde_json::Value;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    file_info::FileInfo,
+    license_detector::{LicenseDetector, LicenseFile},
+    license_info::{self, LicenseInfo},
+    settings,
+    target_os::TargetOs,
+};
+
+const CONANINFO_FILE: &str = "conaninfo.txt";
+const CONANMANIFEST_FILE: &str = "conanmanifest.txt";
+const LICENSES_DIR: &str = "licenses";
+/// Directory of the Conan 2 cache containing the recipe and package folders.
+const CACHE_PACKAGES_DIR: &str = "p";
+/// Package folders of packages built from source are in a subdirectory of the cache.
+const CACHE_BUILD_DIR: &str = "b";
+const CACHE_PACKAGE_DIR: &str = "p";
+/// Conan 2 names the cache folders by the first characters of the package name and a hash.
+const CACHE_FOLDER_NAME_PREFIX: usize = 5;
+
+/// A package of the Conan dependency graph.
+#[derive(Debug, Clone)]
+pub struct ConanPackage {
+    pub name: String,
+    pub version: Option<String>,
+    pub license: Option<String>,
+    pub url: Option<String>,
+    /// Package folder in the Conan cache, missing for packages that were not installed
+    pub package_folder: Option<PathBuf>,
+    /// Options of the package, e.g. `shared=True`
+    pub options: Vec<String>,
+    pub dependencies: Vec<String>,
+    /// Build requirements are tools, they are not shipped with the product
+    pub is_build_context: bool,
+}
+
+/// Returns the name and version of a reference like `zlib/1.3.1#revision%timestamp` or `zlib/1.3.1@user/channel`.
+fn parse_reference(reference: &str) -> (String, Option<String>) {
+    let reference = reference.split(['#', '@']).next().unwrap_or(reference);
+    match reference.split_once('/') {
+        Some((name, version)) => (name.to_owned(), Some(version.to_owned())),
+        None => (reference.to_owned(), None),
+    }
+}
+
+/// Joins the licenses of the `license` attribute of a recipe, a string or a list of licenses that all apply.
+fn recipe_license(license: &Value) -> Option<String> {
+    match license {
+        Value::String(license) => Some(license.clone()),
+        Value::Array(licenses) => {
+            let licenses = licenses.iter().filter_map(Value::as_str).collect::<Vec<_>>();
+            (!licenses.is_empty()).then(|| licenses.join(" AND "))
+        }
+        _ => None,
+    }
+}
+
+/// Parses the output of `conan graph info --format=json` or `conan install --format=json`.
+fn parse_graph_info(graph: &Value) -> Vec<ConanPackage> {
+    let Some(nodes) = graph.pointer("/graph/nodes").and_then(Value::as_object) else {
+        return vec![];
+    };
+    let node_name = |id: &str| {
+        nodes
+            .get(id)
+            .and_then(|node| node.get("ref"))
+            .and_then(Value::as_str)
+            .map(|reference| parse_reference(reference).0)
+    };
+    let mut packages = vec![];
+    for node in nodes.values() {
+        // The root node is the consumer conanfile, it has no reference:
+        let Some((name, version)) = node
+            .get("ref")
+            .and_then(Value::as_str)
+            .filter(|reference| reference.contains('/'))
+            .map(parse_reference)
+        else {
+            continue;
+        };
+        let options = node
+            .get("options")
+            .and_then(Value::as_object)
+            .map(|options| {
+                options
+                    .iter()
+                    .map(|(key, value)| format!("{key}={}", value.as_str().unwrap_or(&value.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let dependencies = node
+            .get("dependencies")
+            .and_then(Value::as_object)
+            .map(|dependencies| dependencies.keys().filter_map(|id| node_name(id)).collect())
+            .unwrap_or_default();
+        packages.push(ConanPackage {
+            name,
+            version,
+            license: node.get("license").and_then(recipe_license),
+            url: node
+                .get("homepage")
+                .or_else(|| node.get("url"))
+                .and_then(Value::as_str)
+                .map(str::to_owned),
+            package_folder: node.get("package_folder").and_then(Value::as_str).map(PathBuf::from),
+            options,
+            dependencies,
+            is_build_context: node.get("context").and_then(Value::as_str) == Some("build"),
+        });
+    }
+    packages
+}
+
+/// Parses a `conan.lock` file, it only contains the references of the packages.
+fn parse_lock(lock: &Value) -> Vec<ConanPackage> {
+    let references = |key: &str, is_build_context: bool| {
+        lock.get(key)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .map(move |reference| {
+                let (name, version) = parse_reference(reference);
+                ConanPackage {
+                    name,
+                    version,
+                    license: None,
+                    url: None,
+                    package_folder: None,
+                    options: vec![],
+                    dependencies: vec![],
+                    is_build_context,
+                }
+            })
+    };
+    references("requires", false)
+        .chain(references("build_requires", true))
+        .collect()
+}
+
+/// Returns the Conan home directory: `CONAN_HOME` or `~/.conan2`.
+pub fn default_conan_home() -> Option<PathBuf> {
+    if let Some(home) = std::env::var_os("CONAN_HOME").filter(|x| !x.is_empty()) {
+        return Some(PathBuf::from(home));
+    }
+    std::env::var_os("HOME").map(|home| Path::new(&home).join(".conan2"))
+}
+
+/// Finds the package folders of the cache named after the package, the folder is only used if it is unique.
+fn find_cache_package_folder(name: &str, conan_home: &Path) -> Option<PathBuf> {
+    let prefix = name.chars().take(CACHE_FOLDER_NAME_PREFIX).collect::<String>();
+    let cache_dir = conan_home.join(CACHE_PACKAGES_DIR);
+    let mut folders = vec![];
+    for dir in [cache_dir.clone(), cache_dir.join(CACHE_BUILD_DIR)] {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let package_folder = entry.path().join(CACHE_PACKAGE_DIR);
+            if entry.file_name().to_string_lossy().starts_with(&prefix) && package_folder.join(CONANINFO_FILE).is_file()
+            {
+                folders.push(package_folder);
+            }
+        }
+    }
+    if folders.len() > 1 {
+        warn!("Cannot find unique package folder of Conan package '{name}' in the cache, found: {folders:?}");
+        return None;
+    }
+    folders.pop()
+}
+
+/// Reads the Conan dependency graph of a graph info JSON file or a `conan.lock` file. Package folders that
+/// are not part of the graph are looked up in the cache of `conan_home`.
+pub fn read_packages(file: &Path, conan_home: Option<&Path>) -> Result<Vec<ConanPackage>> {
+    let content = fs::read_to_string(file).with_context(|| format!("Cannot read Conan graph {file:?}"))?;
+    let json: Value = serde_json::from_str(&content).with_context(|| format!("Cannot parse Conan graph {file:?}"))?;
+    let mut packages = if json.get("graph").is_some() {
+        parse_graph_info(&json)
+    } else {
+        parse_lock(&json)
+    };
+    for package in packages.iter_mut().filter(|x| x.package_folder.is_none()) {
+        package.package_folder = conan_home.and_then(|home| find_cache_package_folder(&package.name, home));
+    }
+    Ok(packages)
+}
+
+/// Reads the options and requirements of the `conaninfo.txt` of a package folder.
+fn parse_conaninfo(content: &str) -> (Vec<String>, Vec<String>) {
+    let mut options = vec![];
+    let mut requires = vec![];
+    let mut section = "";
+    for line in content.lines().map(str::trim).filter(|x| !x.is_empty()) {
+        if let Some(name) = line.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+            section = name;
+        } else if section == "options" {
+            options.push(line.to_owned());
+        } else if section == "requires" {
+            requires.push(parse_reference(line).0);
+        }
+    }
+    (options, requires)
+}
+
+/// Lists the files of a package folder by its `conanmanifest.txt`, the first line is a timestamp
+/// followed by `<path>: <md5>` lines.
+fn manifest_files(package_folder: &Path) -> Vec<String> {
+    fs::read_to_string(package_folder.join(CONANMANIFEST_FILE))
+        .unwrap_or_default()
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.rsplit_once(": ").map(|(path, _)| path.to_owned()))
+        .collect()
+}
+
+fn find_license_files(package_folder: &Path) -> Vec<PathBuf> {
+    fn collect(dir: &Path, files: &mut Vec<PathBuf>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for path in entries.flatten().map(|x| x.path()) {
+            if path.is_dir() {
+                collect(&path, files);
+            } else if path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+    let mut files = vec![];
+    collect(&package_folder.join(LICENSES_DIR), &mut files);
+    files.sort();
+    files
+}
+
+impl ConanPackage {
+    /// File names of the libraries of the target operating system in the package folder, e.g. `libz.so.1`.
+    pub fn libraries(&self, target_os: TargetOs) -> Vec<FileInfo> {
+        let Some(package_folder) = &self.package_folder else {
+            return vec![];
+        };
+        manifest_files(package_folder)
+            .iter()
+            .filter_map(|file| {
+                let name = Path::new(file).file_name()?.to_string_lossy().into_owned();
+                target_os
+                    .is_library(&name)
+                    .then(|| FileInfo::new(name, &package_folder.join(file)))
+            })
+            .collect()
+    }
+
+    pub fn license_info(
+        &self,
+        binaries: Vec<String>,
+        overrides: &settings::Overrides,
+        detector: &LicenseDetector,
+    ) -> Result<LicenseInfo> {
+        let (options, requires) = match &self.package_folder {
+            Some(package_folder) => {
+                parse_conaninfo(&fs::read_to_string(package_folder.join(CONANINFO_FILE)).unwrap_or_default())
+            }
+            None => (vec![], vec![]),
+        };
+        let features = if self.options.is_empty() {
+            options
+        } else {
+            self.options.clone()
+        };
+        let dependencies = if self.dependencies.is_empty() {
+            requires
+        } else {
+            self.dependencies.clone()
+        };
+        let license_files = match &self.package_folder {
+            Some(package_folder) => find_license_files(package_folder)
+                .iter()
+                .map(|file| LicenseFile {
+                    id: None,
+                    file: file.to_string_lossy().into_owned(),
+                })
+                .collect(),
+            None => {
+                warn!("Cannot find the package folder of Conan package '{}'", self.name);
+                vec![]
+            }
+        };
+
+        let override_info =
+            settings::Override::find_override("conan", &self.name, self.version.as_deref(), &features, overrides);
+        let (license, license_files) = license_info::apply_override(override_info, self.license.clone(), license_files);
+
+        let license_expression = license
+            .as_deref()
+            .and_then(|license| license_info::parse_license_expression(&self.name, license));
+        let license_ids = license_info::license_ids(license_expression.as_ref());
+
+        let license_texts = detector.detect_licenses(&self.name, license_ids.as_slice(), license_files.as_slice())?;
+
+        // `conan.lock` files do not contain the license, it is detected from the packaged license files:
+        let license = license.or_else(|| {
+            let mut ids = license_texts.iter().map(|x| x.id.clone()).collect::<Vec<_>>();
+            ids.dedup();
+            if ids.is_empty() { None } else { Some(ids.join(" AND ")) }
+        });
+        let license = match license {
+            Some(license) => license,
+            None => {
+                warn!(
+                    "Missing license identifier for Conan package '{}', please add an override",
+                    self.name
+                );
+                "NOASSERTION".to_owned()
+            }
+        };
+
+        let lib_info = match &self.package_folder {
+            Some(package_folder) => FileInfo::new(CONANINFO_FILE.to_owned(), &package_folder.join(CONANINFO_FILE)),
+            None => FileInfo::new(self.name.clone(), Path::new(&self.name)),
+        };
+
+        Ok(LicenseInfo {
+            lib_info,
+            package_name: self.name.clone(),
+            license,
+            license_expression,
+            license_texts,
+            version: self.version.clone(),
+            url: self.url.clone(),
+            features,
+            dependencies,
+            binaries,
+        })
+    }
+}
+
+/// Collects the license information of the Conan packages of the host context, the libraries of the
+/// package are attributed to the Conan package providing them. Build requirements are not shipped and skipped.
+pub fn get_license_info(
+    packages: &[ConanPackage],
+    package_libs: &[FileInfo],
+    target_os: TargetOs,
+    overrides: &settings::Overrides,
+    detector: &LicenseDetector,
+) -> Result<Vec<LicenseInfo>> {
+    let mut licenses = vec![];
+    for package in packages {
+        if package.is_build_context {
+            debug!("Skip Conan build requirement '{}', it is not shipped", package.name);
+            continue;
+        }
+        let libraries = package.libraries(target_os);
+        let binaries = package_libs
+            .iter()
+            .filter(|lib| libraries.iter().any(|x| x.name == lib.name))
+            .map(|lib| lib.name.clone())
+            .collect();
+        licenses.push(package.license_info(binaries, overrides, detector)?);
+    }
+    Ok(licenses)
+}
@@ -0,0 +1,536 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use anyhow::{Context, Result};
+use log::*;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    file_info::{self, FileInfo},
+    license_detector::{LicenseDetector, LicenseFile},
+    license_info::{self, LicenseInfo},
+    settings,
+    target_os::TargetOs,
+};
+
+const CARGO_MANIFEST_FILE: &str = "Cargo.toml";
+const VENDOR_DIR: &str = "vendor";
+const DEPENDENCY_TABLES: [&str; 2] = ["dependencies", "build-dependencies"];
+const DEV_DEPENDENCY_TABLES: [&str; 2] = ["dev-dependencies", "dev_dependencies"];
+const MANIFEST_SEARCH_IGNORES: [&str; 3] = ["target", VENDOR_DIR, ".git"];
+
+/// Platform the crates are compiled for, used to evaluate `[target.'cfg(...)'.dependencies]` tables.
+#[derive(Debug, Clone)]
+pub struct CargoTarget {
+    pub os: TargetOs,
+    /// Target triple like `x86_64-unknown-linux-gnu`, if known
+    pub triple: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct LockPackage {
+    name: String,
+    version: String,
+    source: Option<String>,
+    dependencies: Vec<String>,
+}
+
+/// A dependency declaration of a crate manifest.
+#[derive(Debug, Clone)]
+struct DependencyDeclaration {
+    name: String,
+    dev: bool,
+    target: Option<String>,
+}
+
+impl CargoTarget {
+    pub fn new(os: TargetOs, triple: Option<String>) -> Self {
+        let os = triple.as_deref().and_then(Self::os_of_triple).unwrap_or(os);
+        Self { os, triple }
+    }
+
+    fn os_of_triple(triple: &str) -> Option<TargetOs> {
+        if triple.contains("-linux") {
+            Some(TargetOs::Linux)
+        } else if triple.contains("-windows") {
+            Some(TargetOs::Windows)
+        } else if triple.contains("-apple-darwin") {
+            Some(TargetOs::Macos)
+        } else {
+            None
+        }
+    }
+
+    fn arch(&self) -> Option<&str> {
+        self.triple.as_deref().and_then(|triple| triple.split('-').next())
+    }
+
+    /// Evaluates a `cfg` key value pair, `None` if the value cannot be determined.
+    fn eval_cfg_value(&self, key: &str, value: Option<&str>) -> Option<bool> {
+        let family = match self.os {
+            TargetOs::Linux | TargetOs::Macos => "unix",
+            TargetOs::Windows => "windows",
+        };
+        match (key, value) {
+            ("unix" | "windows", None) => Some(key == family),
+            ("target_family", Some(value)) => Some(value == family),
+            ("target_os", Some(value)) => Some(value == self.os.name()),
+            ("target_vendor", Some(value)) => match &self.triple {
+                Some(triple) => Some(triple.split('-').nth(1) == Some(value)),
+                None if value == "apple" => Some(self.os == TargetOs::Macos),
+                None => None,
+            },
+            ("target_arch", Some(value)) => self.arch().map(|arch| arch == value),
+            ("target_env", Some(value)) => self
+                .triple
+                .as_deref()
+                .map(|triple| triple.split('-').nth(3).unwrap_or_default() == value),
+            ("target_pointer_width", Some(value)) => self.arch().map(|arch| {
+                let width = if arch.contains("64") || arch == "s390x" {
+                    "64"
+                } else {
+                    "32"
+                };
+                width == value
+            }),
+            _ => None,
+        }
+    }
+
+    /// Checks if a target specification like `cfg(windows)` or `x86_64-pc-windows-msvc` applies to the target.
+    /// Unknown predicates are treated as matching, so that no dependency is missed.
+    fn matches(&self, spec: &str) -> bool {
+        let spec = spec.trim();
+        if let Some(cfg) = spec.strip_prefix("cfg(").and_then(|x| x.strip_suffix(')')) {
+            let tokens = tokenize_cfg(cfg);
+            let mut pos = 0;
+            return self.eval_cfg(&tokens, &mut pos).unwrap_or(true);
+        }
+        match &self.triple {
+            Some(triple) => triple == spec,
+            None => Self::os_of_triple(spec).is_none_or(|os| os == self.os),
+        }
+    }
+
+    fn eval_cfg(&self, tokens: &[String], pos: &mut usize) -> Option<bool> {
+        let ident = tokens.get(*pos).cloned()?;
+        *pos += 1;
+        match tokens.get(*pos).map(String::as_str) {
+            Some("(") => {
+                *pos += 1;
+                let mut values = vec![];
+                while tokens.get(*pos).is_some_and(|t| t != ")") {
+                    values.push(self.eval_cfg(tokens, pos));
+                    if tokens.get(*pos).is_some_and(|t| t == ",") {
+                        *pos += 1;
+                    }
+                }
+                *pos += 1;
+                match ident.as_str() {
+                    "all" if values.contains(&Some(false)) => Some(false),
+                    "all" if values.contains(&None) => None,
+                    "all" => Some(true),
+                    "any" if values.contains(&Some(true)) => Some(true),
+                    "any" if values.contains(&None) => None,
+                    "any" => Some(false),
+                    "not" => values.first().copied().flatten().map(|value| !value),
+                    _ => None,
+                }
+            }
+            Some("=") => {
+                *pos += 1;
+                let value = tokens.get(*pos).map(|x| x.trim_matches('"').to_owned());
+                *pos += 1;
+                self.eval_cfg_value(&ident, value.as_deref())
+            }
+            _ => self.eval_cfg_value(&ident, None),
+        }
+    }
+}
+
+fn tokenize_cfg(cfg: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut chars = cfg.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' | ')' | ',' | '=' => tokens.push(c.to_string()),
+            '"' => {
+                let mut value = String::from('"');
+                for c in chars.by_ref() {
+                    value.push(c);
+                    if c == '"' {
+                        break;
+                    }
+                }
+                tokens.push(value);
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut ident = String::from(c);
+                while let Some(&c) = chars.peek() {
+                    if c.is_alphanumeric() || c == '_' {
+                        ident.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(ident);
+            }
+        }
+    }
+    tokens
+}
+
+fn parse_toml_file(file: &Path) -> Result<toml::Table> {
+    let content = fs::read_to_string(file).with_context(|| format!("Cannot read file {file:?}"))?;
+    toml::from_str::<toml::Table>(content.as_str()).with_context(|| format!("Cannot parse TOML file {file:?}"))
+}
+
+fn parse_lock_file(lock_file: &Path) -> Result<Vec<LockPackage>> {
+    let lock = parse_toml_file(lock_file)?;
+    let mut packages = vec![];
+    for package in lock.get("package").and_then(|x| x.as_array()).into_iter().flatten() {
+        let field = |key: &str| package.get(key).and_then(|x| x.as_str()).map(str::to_owned);
+        let (Some(name), Some(version)) = (field("name"), field("version")) else {
+            continue;
+        };
+        packages.push(LockPackage {
+            name,
+            version,
+            source: field("source"),
+            dependencies: package
+                .get("dependencies")
+                .and_then(|x| x.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|x| x.as_str().map(str::to_owned))
+                .collect(),
+        });
+    }
+    Ok(packages)
+}
+
+/// Resolves a dependency of `Cargo.lock` (`name`, `name version` or `name version (source)`) to a package.
+fn resolve_lock_dependency(dependency: &str, packages: &[LockPackage]) -> Option<usize> {
+    let mut parts = dependency.splitn(3, ' ');
+    let name = parts.next()?;
+    let version = parts.next();
+    let source = parts
+        .next()
+        .map(|source| source.trim_start_matches('(').trim_end_matches(')'));
+    packages.iter().position(|pkg| {
+        pkg.name == name
+            && version.is_none_or(|version| pkg.version == version)
+            && source.is_none_or(|source| pkg.source.as_deref() == Some(source))
+    })
+}
+
+fn collect_declarations(table: &toml::Table, target: Option<&str>, declarations: &mut Vec<DependencyDeclaration>) {
+    let tables = DEPENDENCY_TABLES
+        .iter()
+        .map(|x| (x, false))
+        .chain(DEV_DEPENDENCY_TABLES.iter().map(|x| (x, true)));
+    for (table_name, dev) in tables {
+        for (key, value) in table.get(*table_name).and_then(|x| x.as_table()).into_iter().flatten() {
+            let name = value
+                .get("package")
+                .and_then(|x| x.as_str())
+                .unwrap_or(key.as_str())
+                .to_owned();
+            declarations.push(DependencyDeclaration {
+                name,
+                dev,
+                target: target.map(str::to_owned),
+            });
+        }
+    }
+}
+
+fn manifest_declarations(manifest: &toml::Table) -> Vec<DependencyDeclaration> {
+    let mut declarations = vec![];
+    collect_declarations(manifest, None, &mut declarations);
+    for (target, table) in manifest.get("target").and_then(|x| x.as_table()).into_iter().flatten() {
+        if let Some(table) = table.as_table() {
+            collect_declarations(table, Some(target), &mut declarations);
+        }
+    }
+    declarations
+}
+
+/// Finds the manifests of the local crates (workspace members and path dependencies) by their package name.
+fn find_local_manifests(dir: &Path, manifests: &mut HashMap<String, PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir).with_context(|| format!("Cannot read directory {dir:?}"))? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if path.is_dir() && !MANIFEST_SEARCH_IGNORES.contains(&name.as_str()) && !name.starts_with('.') {
+            find_local_manifests(&path, manifests)?;
+        } else if path.is_file() && name == CARGO_MANIFEST_FILE {
+            let manifest = parse_toml_file(&path)?;
+            if let Some(package_name) = manifest
+                .get("package")
+                .and_then(|x| x.get("name"))
+                .and_then(|x| x.as_str())
+            {
+                manifests.insert(package_name.to_owned(), path);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Source directories of the crates in the local registry cache (`$CARGO_HOME/registry/src/*`).
+pub fn registry_src_dirs() -> Vec<PathBuf> {
+    let cargo_home = std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cargo")));
+    let Some(registry_src) = cargo_home.map(|x| x.join("registry").join("src")) else {
+        return vec![];
+    };
+    let Ok(entries) = fs::read_dir(&registry_src) else {
+        return vec![];
+    };
+    let mut dirs = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir())
+        .collect::<Vec<_>>();
+    dirs.sort();
+    dirs
+}
+
+fn manifest_version(manifest: &toml::Table) -> Option<&str> {
+    manifest
+        .get("package")
+        .and_then(|x| x.get("version"))
+        .and_then(|x| x.as_str())
+}
+
+/// Finds the source directory of a crate in vendor directories (`<name>` or `<name>-<version>`)
+/// or registry source directories.
+pub fn find_crate_dir(name: &str, version: &str, search_dirs: &[PathBuf]) -> Option<PathBuf> {
+    for search_dir in search_dirs {
+        let versioned_dir = search_dir.join(format!("{name}-{version}"));
+        if versioned_dir.join(CARGO_MANIFEST_FILE).is_file() {
+            return Some(versioned_dir);
+        }
+        let dir = search_dir.join(name);
+        let manifest_file = dir.join(CARGO_MANIFEST_FILE);
+        if manifest_file.is_file()
+            && parse_toml_file(&manifest_file).is_ok_and(|manifest| manifest_version(&manifest) == Some(version))
+        {
+            return Some(dir);
+        }
+    }
+    None
+}
+
+/// Collects the license information of a crate from its manifest and license files.
+/// Returns a license of `None` if neither the crate nor an override declares a license.
+pub fn crate_license_info(
+    name: &str,
+    version: &str,
+    crate_dir: Option<&Path>,
+    lib_info: FileInfo,
+    overrides: &settings::Overrides,
+    detector: &LicenseDetector,
+) -> Result<(Option<String>, LicenseInfo)> {
+    let mut license = None;
+    let mut license_files = vec![];
+    let mut url = None;
+    if let Some(crate_dir) = crate_dir {
+        let manifest = parse_toml_file(&crate_dir.join(CARGO_MANIFEST_FILE))?;
+        let package = manifest.get("package");
+        let field = |key: &str| package.and_then(|x| x.get(key)).and_then(|x| x.as_str());
+
+        license = field("license").map(str::to_owned);
+        url = field("repository").or(field("homepage")).map(str::to_owned);
+        license_files = match field("license-file") {
+            Some(license_file) => vec![crate_dir.join(license_file)],
+            None => file_info::find_license_files(crate_dir)?,
+        }
+        .iter()
+        .map(|file| LicenseFile {
+            id: None,
+            file: file.to_string_lossy().into_owned(),
+        })
+        .collect();
+    }
+
+    let override_info = settings::Override::find_override("crates", name, Some(version), &[], overrides);
+    let (license, license_files) = license_info::apply_override(override_info, license, license_files);
+
+    let license_expression = license
+        .as_deref()
+        .and_then(|license| license_info::parse_license_expression(name, license));
+    let license_ids = license_info::license_ids(license_expression.as_ref());
+
+    // Detect license ids of license texts:
+    let license_texts = detector.detect_licenses(name, license_ids.as_slice(), license_files.as_slice())?;
+
+    // A crate with only a license file is licensed under the detected license:
+    let license = license.or_else(|| {
+        let mut ids = license_texts.iter().map(|x| x.id.clone()).collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+        if ids.is_empty() { None } else { Some(ids.join(" AND ")) }
+    });
+
+    let info = LicenseInfo {
+        lib_info,
+        package_name: name.to_owned(),
+        license: license.clone().unwrap_or_else(|| "NOASSERTION".to_owned()),
+        license_expression,
+        license_texts,
+        version: Some(version.to_owned()),
+        url,
+        features: vec![],
+        dependencies: vec![],
+        binaries: vec![],
+    };
+    Ok((license, info))
+}
+
+/// Collects the license information of all crates in `Cargo.lock` that are compiled for the target.
+/// Local crates (workspace members and path dependencies) are not reported and dev-dependencies
+/// of the local crates are excluded.
+pub fn get_license_info(
+    lock_file: &Path,
+    search_dirs: &[PathBuf],
+    target: &CargoTarget,
+    overrides: &settings::Overrides,
+    detector: &LicenseDetector,
+) -> Result<Vec<LicenseInfo>> {
+    let packages = parse_lock_file(lock_file)?;
+    let project_dir = lock_file.parent().unwrap_or(Path::new("."));
+
+    let mut local_manifests = HashMap::new();
+    find_local_manifests(project_dir, &mut local_manifests)?;
+
+    let mut crate_dirs: Vec<Option<PathBuf>> = vec![];
+    for package in &packages {
+        let crate_dir = match &package.source {
+            None => local_manifests
+                .get(&package.name)
+                .and_then(|x| x.parent())
+                .map(Path::to_path_buf),
+            Some(_) => find_crate_dir(&package.name, &package.version, search_dirs),
+        };
+        crate_dirs.push(crate_dir);
+    }
+
+    // Walk the dependency graph starting at the local crates:
+    let mut used = vec![false; packages.len()];
+    let mut dependencies: Vec<Vec<String>> = vec![vec![]; packages.len()];
+    let mut queue = (0..packages.len())
+        .filter(|&index| packages[index].source.is_none())
+        .collect::<Vec<_>>();
+    queue.iter().for_each(|&index| used[index] = true);
+    while let Some(index) = queue.pop() {
+        let package = &packages[index];
+        let declarations = match &crate_dirs[index] {
+            Some(crate_dir) => Some(manifest_declarations(&parse_toml_file(
+                &crate_dir.join(CARGO_MANIFEST_FILE),
+            )?)),
+            None => None,
+        };
+
+        for dependency in &package.dependencies {
+            let Some(dep_index) = resolve_lock_dependency(dependency, &packages) else {
+                warn!(
+                    "Cannot resolve dependency '{dependency}' of crate '{}' in {lock_file:?}",
+                    package.name
+                );
+                continue;
+            };
+            let dep_name = &packages[dep_index].name;
+            let is_required = declarations.as_ref().is_none_or(|declarations| {
+                let mut matching = declarations.iter().filter(|x| &x.name == dep_name).peekable();
+                matching.peek().is_none()
+                    || matching.any(|x| !x.dev && x.target.as_deref().is_none_or(|spec| target.matches(spec)))
+            });
+            if !is_required {
+                trace!(
+                    "Skip crate '{dep_name}', it is a dev-dependency or not compiled for the target of '{}'",
+                    package.name
+                );
+                continue;
+            }
+            if !dependencies[index].contains(dep_name) {
+                dependencies[index].push(dep_name.clone());
+            }
+            if !used[dep_index] {
+                used[dep_index] = true;
+                queue.push(dep_index);
+            }
+        }
+    }
+
+    let mut licenses = vec![];
+    for (index, package) in packages.iter().enumerate() {
+        if !used[index] || package.source.is_none() {
+            continue;
+        }
+        let crate_dir = crate_dirs[index].as_deref();
+        // The crate is described by its manifest, or by the lock file if its sources are missing:
+        let lib_info = match crate_dir {
+            Some(crate_dir) => FileInfo::new(CARGO_MANIFEST_FILE.to_owned(), &crate_dir.join(CARGO_MANIFEST_FILE)),
+            None => {
+                warn!(
+                    "Cannot find the sources of crate '{}@{}', run 'cargo vendor' or 'cargo fetch' to provide them",
+                    package.name, package.version
+                );
+                let lock_file_name = lock_file.file_name().unwrap_or_default().to_string_lossy();
+                FileInfo::new(lock_file_name.into_owned(), lock_file)
+            }
+        };
+        let (license, mut info) = crate_license_info(
+            &package.name,
+            &package.version,
+            crate_dir,
+            lib_info,
+            overrides,
+            detector,
+        )?;
+        if license.is_none() {
+            if crate_dir.is_some() {
+                return Err(anyhow::anyhow!(
+                    "Missing license identifier for crate '{}@{}'",
+                    package.name,
+                    package.version
+                ));
+            }
+            warn!(
+                "Missing license identifier for crate '{}@{}', please add an override",
+                package.name, package.version
+            );
+        }
+        info.dependencies = std::mem::take(&mut dependencies[index]);
+        licenses.push(info);
+    }
+    Ok(licenses)
+}
+
+/// Default directories to search for crate sources: the `vendor` directory next to `Cargo.lock`
+/// and the local registry cache.
+pub fn default_search_dirs(lock_file: &Path) -> Vec<PathBuf> {
+    let mut search_dirs = vec![];
+    if let Some(vendor_dir) = lock_file.parent().map(|x| x.join(VENDOR_DIR))
+        && vendor_dir.is_dir()
+    {
+        search_dirs.push(vendor_dir);
+    }
+    search_dirs.extend(registry_src_dirs());
+    search_dirs
+}
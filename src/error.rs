@@ -0,0 +1,107 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use std::path::PathBuf;
+
+/// Error of the library functions, the variants allow callers to react to e.g. a missing or ambiguous package.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// No installed package contains the library or file
+    #[error("Cannot find any package containing {0}")]
+    PackageNotFound(String),
+    /// Several installed packages contain the library or file
+    #[error(
+        "Cannot find unique package containing {query}, packages found: {}",
+        quoted(packages)
+    )]
+    AmbiguousPackage { query: String, packages: Vec<String> },
+    /// No installed package has the name
+    #[error("Cannot find installed {kind} package '{package}'")]
+    UnknownPackage { kind: String, package: String },
+    /// The package declares no license and no override sets one
+    #[error("Missing license identifier for {kind} package '{package}'")]
+    MissingLicense { kind: String, package: String },
+    /// The license of a license text cannot be detected, no license templates are loaded
+    #[error("Cannot detect the license of a license text of package '{0}', no license templates are loaded")]
+    NoLicenseTemplates(String),
+    /// The settings file is not valid YAML, TOML or JSON or does not match the settings
+    #[error("Cannot parse settings {}", path.display())]
+    SettingsParse {
+        path: PathBuf,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// The settings are inconsistent, e.g. an included settings file does not exist
+    #[error("Invalid settings {}: {message}", path.display())]
+    Settings { path: PathBuf, message: String },
+    /// A license is not a valid SPDX license expression
+    #[error("Cannot parse license expression '{expression}' for package '{package}'")]
+    SpdxParse {
+        package: String,
+        expression: String,
+        source: spdx::ParseError,
+    },
+    /// A description of packages, e.g. an rpm database fixture, is not valid YAML, TOML or JSON
+    #[error("Cannot parse {}", path.display())]
+    Parse {
+        path: PathBuf,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// A JSON file, e.g. an SPDX document or a third party licenses file, cannot be parsed or written
+    #[error("Invalid JSON file {}", path.display())]
+    Json { path: PathBuf, source: serde_json::Error },
+    /// Data like the effective settings cannot be serialized
+    #[error("Cannot serialize {what}")]
+    Serialize {
+        what: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// An external tool like `rpm` cannot be executed, e.g. because it is not installed
+    #[error("Cannot execute '{command}'")]
+    CommandNotFound { command: String, source: std::io::Error },
+    /// An external tool exited with an error
+    #[error("Execution of '{command}' failed: {stderr}")]
+    CommandFailed { command: String, stderr: String },
+    #[error("Cannot access {}", path.display())]
+    Io { path: PathBuf, source: std::io::Error },
+    /// The name of a source of the scan is unknown
+    #[error("Unknown source '{name}', supported are {}", quoted(supported))]
+    UnknownSource { name: String, supported: Vec<String> },
+    /// A path of the scan, e.g. the package path, does not exist or is no directory
+    #[error("The {what} {} is not a directory", path.display())]
+    NotADirectory { what: String, path: PathBuf },
+    /// The vcpkg ports are requested, but the project has no vcpkg installation
+    #[error("Cannot find vcpkg installation in {}", .0.display())]
+    VcpkgNotFound(PathBuf),
+    /// The vcpkg triplet is not installed
+    #[error("Cannot find triplet '{triplet}' in vcpkg installation {}", path.display())]
+    MissingTriplet { triplet: String, path: PathBuf },
+    /// A source of the scan fails, e.g. a Conan graph or a Java archive cannot be read
+    #[error(transparent)]
+    Scan(Box<dyn std::error::Error + Send + Sync>),
+}
+
+fn quoted(names: &[String]) -> String {
+    names.iter().map(|x| format!("'{x}'")).collect::<Vec<_>>().join(", ")
+}
+
+impl Error {
+    pub(crate) fn io(path: impl Into<PathBuf>) -> impl FnOnce(std::io::Error) -> Self {
+        let path = path.into();
+        move |source| Self::Io { path, source }
+    }
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(error: anyhow::Error) -> Self {
+        Self::Scan(error.into())
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -30,6 +30,15 @@ impl FileInfo {
 }
 
 pub fn find_files_recurse(path: &PathBuf, filter: &str, ignore_list: &[String]) -> std::io::Result<Vec<FileInfo>> {
+    find_files_recurse_with(path, &|name| name.contains(filter), ignore_list)
+}
+
+/// Finds the files whose names match the predicate, e.g. the libraries of a target operating system.
+pub fn find_files_recurse_with(
+    path: &PathBuf,
+    filter: &dyn Fn(&str) -> bool,
+    ignore_list: &[String],
+) -> std::io::Result<Vec<FileInfo>> {
     let mut libs: Vec<_> = vec![];
     for entry in fs::read_dir(path)? {
         let entry = entry?;
@@ -37,14 +46,43 @@ pub fn find_files_recurse(path: &PathBuf, filter: &str, ignore_list: &[String])
         let name = entry.file_name().to_string_lossy().into_owned();
 
         if path.is_dir() && !ignore_list.contains(&name) {
-            let sub_libs = find_files_recurse(&path, filter, ignore_list)?;
+            let sub_libs = find_files_recurse_with(&path, filter, ignore_list)?;
             for lib in sub_libs {
                 libs.push(lib);
             }
-        } else if path.is_file() && name.contains(filter) && !ignore_list.contains(&name) {
+        } else if path.is_file() && filter(&name) && !ignore_list.contains(&name) {
             libs.push(FileInfo::new(name, path.as_path()));
         }
     }
 
     Ok(libs)
 }
+
+/// Name of the directory of a package, e.g. in the export or the mismatch directory. Package names like Go
+/// module paths or npm scopes contain path separators, they are replaced to stay inside the parent directory.
+pub fn package_dir_name(package: &str) -> String {
+    let name = package.replace(['/', '\\', ':'], "_");
+    // `.` and `..` would refer to the parent directory or its parent:
+    if name.chars().all(|c| c == '.') {
+        format!("_{name}")
+    } else {
+        name
+    }
+}
+
+/// Prefixes of the upper case names of license files.
+pub const LICENSE_FILE_PREFIXES: [&str; 5] = ["LICENSE", "LICENCE", "COPYING", "UNLICENSE", "COPYRIGHT"];
+
+/// Finds the license files (e.g. `LICENSE`, `LICENSE-MIT`, `COPYING.txt`) in the top level of a directory.
+pub fn find_license_files(path: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut license_files = vec![];
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_uppercase();
+        if entry.path().is_file() && LICENSE_FILE_PREFIXES.iter().any(|prefix| name.starts_with(prefix)) {
+            license_files.push(entry.path());
+        }
+    }
+    license_files.sort();
+    Ok(license_files)
+}
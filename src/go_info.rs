@@ -0,0 +1,326 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use anyhow::{Context, Result};
+use log::*;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    binary_info,
+    file_info::{self, FileInfo},
+    license_detector::{LicenseDetector, LicenseFile},
+    license_info::{self, LicenseInfo},
+    settings,
+};
+
+/// Sections containing the build information: `.go.buildinfo` of ELF, `__go_buildinfo` of Mach-O and the data
+/// section of PE binaries.
+const BUILDINFO_SECTIONS: [&str; 3] = [".go.buildinfo", "__go_buildinfo", ".data"];
+const BUILDINFO_MAGIC: &[u8] = b"\xff Go buildinf:";
+const BUILDINFO_ALIGN: usize = 16;
+const BUILDINFO_HEADER_SIZE: usize = 32;
+/// Flag of Go 1.18 and later, the version strings follow the header instead of being referenced by pointers.
+const FLAG_VERSIONS_INLINE: u8 = 0x2;
+/// The module information is framed by 16 byte sentinels.
+const MODINFO_SENTINEL_SIZE: usize = 16;
+
+/// A module compiled into a Go binary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GoModule {
+    pub path: String,
+    pub version: String,
+}
+
+/// Build information embedded into a Go binary (`runtime/debug.BuildInfo`).
+#[derive(Debug, Clone)]
+pub struct GoBuildInfo {
+    pub go_version: String,
+    /// Import path of the main package
+    pub path: Option<String>,
+    pub main: Option<GoModule>,
+    pub dependencies: Vec<GoModule>,
+}
+
+fn read_varint(data: &[u8]) -> Option<(usize, usize)> {
+    let mut value = 0usize;
+    for (i, byte) in data.iter().enumerate().take(10) {
+        value |= ((byte & 0x7f) as usize) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+fn read_bytes(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (len, size) = read_varint(data)?;
+    let value = data.get(size..size.checked_add(len)?)?;
+    Some((value, &data[size + len..]))
+}
+
+fn find_buildinfo(data: &[u8]) -> Option<&[u8]> {
+    (0..data.len().saturating_sub(BUILDINFO_HEADER_SIZE))
+        .step_by(BUILDINFO_ALIGN)
+        .find(|&offset| data[offset..].starts_with(BUILDINFO_MAGIC))
+        .map(|offset| &data[offset..])
+}
+
+/// Parses the lines of the module information, `=>` lines replace the module of the preceding line.
+fn parse_modinfo(modinfo: &str) -> (Option<String>, Option<GoModule>, Vec<GoModule>) {
+    let mut path = None;
+    let mut main = None;
+    let mut dependencies: Vec<GoModule> = vec![];
+    let mut last_is_main = false;
+    for line in modinfo.lines() {
+        let mut fields = line.split('\t');
+        let kind = fields.next().unwrap_or_default();
+        let module = match (fields.next(), fields.next()) {
+            (Some(path), Some(version)) => Some(GoModule {
+                path: path.to_owned(),
+                version: version.to_owned(),
+            }),
+            (Some(path), None) => Some(GoModule {
+                path: path.to_owned(),
+                version: String::new(),
+            }),
+            _ => None,
+        };
+        match (kind, module) {
+            ("path", Some(module)) => path = Some(module.path),
+            ("mod", Some(module)) => {
+                main = Some(module);
+                last_is_main = true;
+            }
+            ("dep", Some(module)) => {
+                dependencies.push(module);
+                last_is_main = false;
+            }
+            ("=>", Some(module)) => {
+                if last_is_main {
+                    main = Some(module);
+                } else if let Some(last) = dependencies.last_mut() {
+                    *last = module;
+                }
+            }
+            _ => {}
+        }
+    }
+    (path, main, dependencies)
+}
+
+impl GoBuildInfo {
+    /// Parses the build information of a Go binary, returns `None` for other binaries.
+    pub fn parse(data: &[u8]) -> Result<Option<Self>> {
+        // Only binaries without section headers are searched entirely, otherwise e.g. a binary embedding
+        // another Go binary as data would be reported with the modules of the embedded binary:
+        let sections = BUILDINFO_SECTIONS
+            .iter()
+            .filter_map(|name| binary_info::section_data(data, name).ok().flatten())
+            .collect::<Vec<_>>();
+        let buildinfo = if binary_info::has_sections(data) {
+            sections.iter().find_map(|section| find_buildinfo(section))
+        } else {
+            find_buildinfo(data)
+        };
+        let Some(buildinfo) = buildinfo else {
+            return Ok(None);
+        };
+        let flags = buildinfo[BUILDINFO_MAGIC.len() + 1];
+        if flags & FLAG_VERSIONS_INLINE == 0 {
+            return Err(anyhow::anyhow!(
+                "Unsupported build information, the binary was built with Go before 1.18"
+            ));
+        }
+
+        let parse_strings = || {
+            let (go_version, rest) = read_bytes(&buildinfo[BUILDINFO_HEADER_SIZE..])?;
+            let (modinfo, _) = read_bytes(rest)?;
+            Some((go_version, modinfo))
+        };
+        let Some((go_version, modinfo)) = parse_strings() else {
+            return Err(anyhow::anyhow!("Truncated build information"));
+        };
+        let modinfo = if modinfo.len() > 2 * MODINFO_SENTINEL_SIZE
+            && modinfo[modinfo.len() - MODINFO_SENTINEL_SIZE - 1] == b'\n'
+        {
+            String::from_utf8_lossy(&modinfo[MODINFO_SENTINEL_SIZE..modinfo.len() - MODINFO_SENTINEL_SIZE])
+        } else {
+            Default::default()
+        };
+        let (path, main, dependencies) = parse_modinfo(&modinfo);
+
+        Ok(Some(Self {
+            go_version: String::from_utf8_lossy(go_version).into_owned(),
+            path,
+            main,
+            dependencies,
+        }))
+    }
+
+    pub fn read(file: &Path) -> Result<Option<Self>> {
+        let data = fs::read(file).with_context(|| format!("Cannot read binary {file:?}"))?;
+        Self::parse(data.as_slice()).with_context(|| format!("Cannot parse Go build information of binary {file:?}"))
+    }
+}
+
+/// Returns the module cache directory: `GOMODCACHE`, `$GOPATH/pkg/mod` or `~/go/pkg/mod`.
+pub fn default_module_cache() -> Option<PathBuf> {
+    if let Some(cache) = std::env::var_os("GOMODCACHE").filter(|x| !x.is_empty()) {
+        return Some(PathBuf::from(cache));
+    }
+    let gopath = std::env::var_os("GOPATH")
+        .filter(|x| !x.is_empty())
+        .map(|x| {
+            // Only the first entry of the list is used for the module cache
+            std::env::split_paths(&x).next().unwrap_or_default()
+        })
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join("go")))?;
+    Some(gopath.join("pkg").join("mod"))
+}
+
+/// Escapes a module path or version for the module cache, upper case letters are replaced by `!` and the lower case letter.
+fn escape_module_path(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+    for c in path.chars() {
+        if c.is_ascii_uppercase() {
+            escaped.push('!');
+            escaped.push(c.to_ascii_lowercase());
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+/// Finds the source directory of a module in the module cache.
+pub fn find_module_dir(module: &GoModule, module_cache: &Path) -> Option<PathBuf> {
+    let dir = module_cache.join(format!(
+        "{}@{}",
+        escape_module_path(&module.path),
+        escape_module_path(&module.version)
+    ));
+    dir.is_dir().then_some(dir)
+}
+
+fn module_license_info(
+    module: &GoModule,
+    module_dir: Option<&Path>,
+    lib_info: FileInfo,
+    overrides: &settings::Overrides,
+    detector: &LicenseDetector,
+) -> Result<LicenseInfo> {
+    let license_files = match module_dir {
+        Some(module_dir) => file_info::find_license_files(module_dir)?
+            .iter()
+            .map(|file| LicenseFile {
+                id: None,
+                file: file.to_string_lossy().into_owned(),
+            })
+            .collect(),
+        None => vec![],
+    };
+
+    // Go modules do not declare their license, it is detected from the license files:
+    let override_info = settings::Override::find_override("go", &module.path, Some(&module.version), &[], overrides);
+    let (license, license_files) = license_info::apply_override(override_info, None, license_files);
+
+    let license_expression = license
+        .as_deref()
+        .and_then(|license| license_info::parse_license_expression(&module.path, license));
+    let license_ids = license_info::license_ids(license_expression.as_ref());
+
+    let license_texts = detector.detect_licenses(&module.path, license_ids.as_slice(), license_files.as_slice())?;
+
+    let license = license.or_else(|| {
+        let mut ids = license_texts.iter().map(|x| x.id.clone()).collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+        if ids.is_empty() { None } else { Some(ids.join(" AND ")) }
+    });
+    let license = match license {
+        Some(license) => license,
+        None => {
+            warn!(
+                "Missing license for Go module '{}@{}', please add an override",
+                module.path, module.version
+            );
+            "NOASSERTION".to_owned()
+        }
+    };
+
+    Ok(LicenseInfo {
+        lib_info,
+        package_name: module.path.clone(),
+        license,
+        license_expression,
+        license_texts,
+        version: Some(module.version.clone()),
+        url: Some(format!("https://pkg.go.dev/{}", module.path)),
+        features: vec![],
+        dependencies: vec![],
+        binaries: vec![],
+    })
+}
+
+/// Collects the license information of the modules compiled into the given Go binaries.
+/// The licenses are detected from the module sources in the module cache.
+pub fn get_license_info(
+    binaries: &[FileInfo],
+    module_cache: Option<&Path>,
+    overrides: &settings::Overrides,
+    detector: &LicenseDetector,
+) -> Result<Vec<LicenseInfo>> {
+    let mut licenses: Vec<LicenseInfo> = vec![];
+
+    for binary in binaries {
+        let build_info = match GoBuildInfo::read(&binary.path) {
+            Ok(Some(build_info)) => build_info,
+            Ok(None) => continue,
+            Err(error) => {
+                warn!("{error:?}");
+                continue;
+            }
+        };
+        info!(
+            "Found {} Go modules in binary '{}' built with {}",
+            build_info.dependencies.len(),
+            binary.name,
+            build_info.go_version
+        );
+
+        for module in &build_info.dependencies {
+            if let Some(info) = licenses
+                .iter_mut()
+                .find(|x| x.package_name == module.path && x.version.as_deref() == Some(module.version.as_str()))
+            {
+                if !info.binaries.contains(&binary.name) {
+                    info.binaries.push(binary.name.clone());
+                }
+                continue;
+            }
+
+            // Modules replaced by a local directory have no version and are not in the module cache:
+            let module_dir = module_cache.and_then(|cache| find_module_dir(module, cache));
+            if module_dir.is_none() {
+                warn!(
+                    "Cannot find the sources of Go module '{}@{}' of binary '{}' in the module cache",
+                    module.path, module.version, binary.name
+                );
+            }
+            let mut info = module_license_info(module, module_dir.as_deref(), binary.clone(), overrides, detector)?;
+            info.binaries.push(binary.name.clone());
+            licenses.push(info);
+        }
+    }
+    Ok(licenses)
+}
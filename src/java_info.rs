@@ -0,0 +1,465 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use anyhow::{Context, Result};
+use log::*;
+use std::{
+    fs,
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    file_info::{self, FileInfo},
+    license_detector::LicenseDetector,
+    license_info::{self, LicenseInfo},
+    settings,
+};
+
+const JAR_EXTENSIONS: [&str; 3] = [".jar", ".war", ".ear"];
+const MANIFEST_FILE: &str = "META-INF/MANIFEST.MF";
+const MAVEN_DIR: &str = "META-INF/maven/";
+const POM_FILE: &str = "pom.xml";
+const POM_PROPERTIES_FILE: &str = "pom.properties";
+/// Limit of license texts and metadata files read from an archive.
+const MAX_TEXT_FILE_SIZE: u64 = 1024 * 1024;
+/// Limit of nested archives that are loaded into memory.
+const MAX_NESTED_JAR_SIZE: u64 = 256 * 1024 * 1024;
+
+/// SPDX ids of license names and URLs commonly used in `pom.xml` files and `Bundle-License` headers.
+/// The patterns are matched case insensitive against a part of the name, the first match wins.
+const LICENSE_NAMES: [(&str, &str); 24] = [
+    ("apache license, version 2.0", "Apache-2.0"),
+    ("apache software license, version 2.0", "Apache-2.0"),
+    ("apache license 2.0", "Apache-2.0"),
+    ("apache 2", "Apache-2.0"),
+    ("apache.org/licenses/license-2.0", "Apache-2.0"),
+    ("opensource.org/licenses/mit", "MIT"),
+    ("mit license", "MIT"),
+    ("eclipse public license - v 2.0", "EPL-2.0"),
+    ("eclipse public license v2.0", "EPL-2.0"),
+    ("eclipse.org/legal/epl-2.0", "EPL-2.0"),
+    ("eclipse public license - v 1.0", "EPL-1.0"),
+    ("eclipse.org/legal/epl-v10", "EPL-1.0"),
+    ("eclipse distribution license - v 1.0", "BSD-3-Clause"),
+    ("eclipse.org/org/documents/edl-v10", "BSD-3-Clause"),
+    ("bsd 3-clause", "BSD-3-Clause"),
+    ("new bsd license", "BSD-3-Clause"),
+    ("bsd 2-clause", "BSD-2-Clause"),
+    ("gnu lesser general public license, version 2.1", "LGPL-2.1-only"),
+    ("gnu lesser general public license v2.1", "LGPL-2.1-only"),
+    ("gpl2 w/ cpe", "GPL-2.0-only WITH Classpath-exception-2.0"),
+    (
+        "gnu general public license, version 2 with the classpath exception",
+        "GPL-2.0-only WITH Classpath-exception-2.0",
+    ),
+    ("common development and distribution license (cddl) v1.0", "CDDL-1.0"),
+    ("cddl 1.1", "CDDL-1.1"),
+    ("public domain", "LicenseRef-Public-Domain"),
+];
+
+/// A library found in a JAR file, shaded JARs contain several libraries.
+#[derive(Debug, Clone)]
+pub struct JavaLibrary {
+    /// Maven coordinates `groupId:artifactId` or the bundle name of the manifest
+    pub name: String,
+    pub version: Option<String>,
+    pub license: Option<String>,
+    pub license_texts: Vec<String>,
+    pub url: Option<String>,
+    /// The JAR containing the library, nested JARs are separated by `!/`
+    pub jar: String,
+}
+
+/// Maps a license name or URL to an SPDX id, valid SPDX expressions are taken as they are.
+fn license_of_name(name: &str) -> Option<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    if spdx::Expression::parse(name).is_ok() {
+        return Some(name.to_owned());
+    }
+    let lower_name = name.to_lowercase();
+    LICENSE_NAMES
+        .iter()
+        .find(|(pattern, _)| lower_name.contains(pattern))
+        .map(|(_, id)| (*id).to_owned())
+}
+
+/// Combines the licenses of a library, several licenses are interpreted as a choice.
+fn license_of_names<'a>(package: &str, names: impl Iterator<Item = &'a str>) -> Option<String> {
+    let mut ids: Vec<String> = vec![];
+    for name in names {
+        match license_of_name(name) {
+            Some(id) if !ids.contains(&id) => ids.push(id),
+            Some(_) => {}
+            None => warn!("Unknown license '{name}' of Java library '{package}'"),
+        }
+    }
+    match ids.len() {
+        0 => None,
+        1 => ids.pop(),
+        _ => Some(
+            ids.iter()
+                .map(|id| {
+                    if id.contains(' ') {
+                        format!("({id})")
+                    } else {
+                        id.clone()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" OR "),
+        ),
+    }
+}
+
+/// Parses the main section of a JAR manifest, continuation lines start with a single space.
+fn parse_manifest(content: &str) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = vec![];
+    for line in content.lines() {
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() {
+            break;
+        }
+        if let Some(continuation) = line.strip_prefix(' ') {
+            if let Some((_, value)) = fields.last_mut() {
+                value.push_str(continuation);
+            }
+        } else if let Some((key, value)) = line.split_once(':') {
+            fields.push((key.trim().to_owned(), value.trim().to_owned()));
+        }
+    }
+    fields
+}
+
+fn parse_properties(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with(['#', '!']))
+        .filter_map(|line| line.split_once(['=', ':']))
+        .map(|(key, value)| (key.trim().to_owned(), value.trim().to_owned()))
+        .collect()
+}
+
+fn property<'a>(fields: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    fields
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+        .map(|(_, v)| v.as_str())
+        .filter(|v| !v.is_empty())
+}
+
+/// Values referencing unresolved properties like `${project.version}` are ignored.
+fn resolved(value: Option<&str>) -> Option<String> {
+    value.filter(|x| !x.contains("${")).map(str::to_owned)
+}
+
+/// License names, project URL, name and coordinates of a `pom.xml`.
+struct Pom {
+    group_id: Option<String>,
+    artifact_id: Option<String>,
+    version: Option<String>,
+    licenses: Vec<String>,
+    url: Option<String>,
+}
+
+fn child<'a, 'input>(node: roxmltree::Node<'a, 'input>, name: &str) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|x| x.has_tag_name(name))
+}
+
+fn parse_pom(content: &str) -> Result<Pom> {
+    let document = roxmltree::Document::parse(content)?;
+    let project = document.root_element();
+    let child_text = |node: roxmltree::Node<'_, '_>, name: &str| {
+        child(node, name)
+            .and_then(|x| x.text())
+            .map(str::trim)
+            .map(str::to_owned)
+    };
+    let parent = child(project, "parent");
+
+    let licenses = child(project, "licenses")
+        .map(|licenses| {
+            licenses
+                .children()
+                .filter(|x| x.has_tag_name("license"))
+                .filter_map(|license| child_text(license, "name").or_else(|| child_text(license, "url")))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(Pom {
+        group_id: child_text(project, "groupId").or_else(|| parent.and_then(|x| child_text(x, "groupId"))),
+        artifact_id: child_text(project, "artifactId"),
+        version: child_text(project, "version").or_else(|| parent.and_then(|x| child_text(x, "version"))),
+        licenses,
+        url: resolved(child_text(project, "url").as_deref()),
+    })
+}
+
+/// Reads a text file of an archive, invalid UTF-8 is replaced. Unreadable files are skipped with a warning
+/// so that the other files of the archive are still used.
+fn read_text<R: Read>(reader: R, name: &str, jar: &str) -> Option<String> {
+    let mut data = vec![];
+    match reader.take(MAX_TEXT_FILE_SIZE).read_to_end(&mut data) {
+        Ok(_) => Some(String::from_utf8_lossy(&data).into_owned()),
+        Err(error) => {
+            warn!("Cannot read '{name}' of JAR '{jar}': {error}");
+            None
+        }
+    }
+}
+
+fn is_jar(name: &str) -> bool {
+    let name = name.to_lowercase();
+    JAR_EXTENSIONS.iter().any(|extension| name.ends_with(extension))
+}
+
+fn is_license_file(name: &str) -> bool {
+    let file_name = name.strip_prefix("META-INF/").unwrap_or(name);
+    // Only top level license files of the archive or its META-INF directory belong to the JAR itself
+    !file_name.contains('/')
+        && file_info::LICENSE_FILE_PREFIXES
+            .iter()
+            .any(|prefix| file_name.to_uppercase().starts_with(prefix))
+}
+
+/// Reads the libraries of a JAR archive and of the JARs nested into it.
+pub fn read_jar(data: &[u8], jar: &str, libraries: &mut Vec<JavaLibrary>) -> Result<()> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data)).with_context(|| format!("Cannot open JAR '{jar}'"))?;
+
+    let mut manifest = vec![];
+    let mut poms: Vec<(String, Pom)> = vec![];
+    let mut pom_properties: Vec<(String, Vec<(String, String)>)> = vec![];
+    let mut license_texts = vec![];
+    let mut nested_jars = vec![];
+
+    for index in 0..archive.len() {
+        let file = archive.by_index(index)?;
+        if file.is_dir() {
+            continue;
+        }
+        let name = file.name()?.into_owned();
+        if name.eq_ignore_ascii_case(MANIFEST_FILE) {
+            if let Some(text) = read_text(file, &name, jar) {
+                manifest = parse_manifest(&text);
+            }
+        } else if let Some(maven_path) = name.strip_prefix(MAVEN_DIR) {
+            // META-INF/maven/<groupId>/<artifactId>/pom.xml
+            let Some(dir) = maven_path.rsplit_once('/').map(|(dir, _)| dir.to_owned()) else {
+                continue;
+            };
+            if name.ends_with(POM_FILE) {
+                let Some(text) = read_text(file, &name, jar) else {
+                    continue;
+                };
+                match parse_pom(&text) {
+                    Ok(pom) => poms.push((dir, pom)),
+                    Err(error) => warn!("Cannot parse '{name}' of JAR '{jar}': {error}"),
+                }
+            } else if name.ends_with(POM_PROPERTIES_FILE)
+                && let Some(text) = read_text(file, &name, jar)
+            {
+                pom_properties.push((dir, parse_properties(&text)));
+            }
+        } else if is_license_file(&name) {
+            license_texts.extend(read_text(file, &name, jar));
+        } else if is_jar(&name) {
+            if file.size() > MAX_NESTED_JAR_SIZE {
+                warn!("Skip nested JAR '{name}' of JAR '{jar}', it is too large");
+                continue;
+            }
+            let mut nested = vec![];
+            file.take(MAX_NESTED_JAR_SIZE).read_to_end(&mut nested)?;
+            nested_jars.push((name, nested));
+        }
+    }
+
+    let manifest_name = property(&manifest, "Bundle-SymbolicName")
+        .map(|x| x.split(';').next().unwrap_or(x).trim())
+        .or_else(|| property(&manifest, "Implementation-Title"))
+        .or_else(|| property(&manifest, "Bundle-Name"));
+    let manifest_version =
+        property(&manifest, "Implementation-Version").or_else(|| property(&manifest, "Bundle-Version"));
+    let manifest_url = property(&manifest, "Bundle-DocURL");
+
+    let mut jar_libraries = vec![];
+    for (dir, pom) in &poms {
+        let properties = pom_properties
+            .iter()
+            .find(|(x, _)| x == dir)
+            .map(|(_, properties)| properties.as_slice())
+            .unwrap_or_default();
+        let group_id = property(properties, "groupId")
+            .map(str::to_owned)
+            .or(pom.group_id.clone());
+        let artifact_id = property(properties, "artifactId")
+            .map(str::to_owned)
+            .or(pom.artifact_id.clone());
+        let Some(artifact_id) = artifact_id else {
+            continue;
+        };
+        let name = match group_id {
+            Some(group_id) => format!("{group_id}:{artifact_id}"),
+            None => artifact_id,
+        };
+        let version = property(properties, "version")
+            .map(str::to_owned)
+            .or_else(|| resolved(pom.version.as_deref()));
+        jar_libraries.push(JavaLibrary {
+            license: license_of_names(&name, pom.licenses.iter().map(String::as_str)),
+            name,
+            version,
+            license_texts: vec![],
+            url: pom.url.clone(),
+            jar: jar.to_owned(),
+        });
+    }
+
+    let bundle_license = property(&manifest, "Bundle-License")
+        .and_then(|licenses| license_of_names(manifest_name.unwrap_or(jar), licenses.split(',')));
+    if jar_libraries.len() == 1 {
+        // The metadata of the manifest and the license files belong to the only Maven artifact:
+        let library = &mut jar_libraries[0];
+        library.license = library.license.take().or(bundle_license);
+        library.url = library.url.take().or(manifest_url.map(str::to_owned));
+        library.license_texts = license_texts;
+    } else if jar_libraries.is_empty() {
+        let jar_name = Path::new(jar.rsplit("!/").next().unwrap_or(jar))
+            .file_stem()
+            .map(|x| x.to_string_lossy().into_owned())
+            .unwrap_or_else(|| jar.to_owned());
+        jar_libraries.push(JavaLibrary {
+            name: manifest_name.map(str::to_owned).unwrap_or(jar_name),
+            version: manifest_version.map(str::to_owned),
+            license: bundle_license,
+            license_texts,
+            url: manifest_url.map(str::to_owned),
+            jar: jar.to_owned(),
+        });
+    } else {
+        // A shaded JAR, the license files belong to the artifact the manifest describes:
+        let primary = jar_libraries.iter_mut().find(|library| {
+            manifest_name.is_some_and(|name| library.name.ends_with(&format!(":{name}")) || library.name == name)
+        });
+        if let Some(library) = primary {
+            library.license = library.license.take().or(bundle_license);
+            library.license_texts = license_texts;
+        } else if !license_texts.is_empty() {
+            debug!("Cannot attribute the license files of shaded JAR '{jar}' to one of its artifacts");
+        }
+    }
+    libraries.extend(jar_libraries);
+
+    for (name, nested) in nested_jars {
+        let nested_jar = format!("{jar}!/{name}");
+        trace!("Found nested JAR '{nested_jar}'");
+        if let Err(error) = read_jar(&nested, &nested_jar, libraries) {
+            warn!("{error:?}");
+        }
+    }
+    Ok(())
+}
+
+impl JavaLibrary {
+    pub fn license_info(
+        &self,
+        lib_info: FileInfo,
+        overrides: &settings::Overrides,
+        detector: &LicenseDetector,
+    ) -> Result<LicenseInfo> {
+        let override_info =
+            settings::Override::find_override("java", &self.name, self.version.as_deref(), &[], overrides);
+        let (license, license_files) = license_info::apply_override(override_info, self.license.clone(), vec![]);
+
+        let license_expression = license
+            .as_deref()
+            .and_then(|license| license_info::parse_license_expression(&self.name, license));
+        let license_ids = license_info::license_ids(license_expression.as_ref());
+
+        // Detect license ids of the license texts, the license files of an override replace the texts of the JAR:
+        let license_texts = if license_files.is_empty() {
+            self.license_texts
+                .iter()
+                .map(|text| detector.detect_license(&self.name, license_ids.as_slice(), text))
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            detector.detect_licenses(&self.name, license_ids.as_slice(), license_files.as_slice())?
+        };
+
+        let license = match license {
+            Some(license) => license,
+            None => {
+                warn!(
+                    "Missing license identifier for Java library '{}' in '{}', please add an override",
+                    self.name, self.jar
+                );
+                "NOASSERTION".to_owned()
+            }
+        };
+
+        Ok(LicenseInfo {
+            lib_info,
+            package_name: self.name.clone(),
+            license,
+            license_expression,
+            license_texts,
+            version: self.version.clone(),
+            url: self.url.clone(),
+            features: vec![],
+            dependencies: vec![],
+            binaries: vec![self.jar.clone()],
+        })
+    }
+}
+
+/// Finds all JAR, WAR and EAR archives in a directory tree.
+pub fn find_jars_recurse(path: &PathBuf, ignore_list: &[String]) -> std::io::Result<Vec<FileInfo>> {
+    Ok(file_info::find_files_recurse(path, ".", ignore_list)?
+        .into_iter()
+        .filter(|file| is_jar(&file.name))
+        .collect())
+}
+
+/// Collects the license information of the libraries in the given JAR files including shaded and nested JARs.
+/// Libraries contained in several JARs are reported once.
+pub fn get_license_info(
+    jars: &[FileInfo],
+    overrides: &settings::Overrides,
+    detector: &LicenseDetector,
+) -> Result<Vec<LicenseInfo>> {
+    let mut licenses: Vec<LicenseInfo> = vec![];
+    for jar in jars {
+        let data = fs::read(&jar.path).with_context(|| format!("Cannot read JAR {:?}", jar.path))?;
+        let mut libraries = vec![];
+        if let Err(error) = read_jar(&data, &jar.name, &mut libraries) {
+            warn!("{error:?}");
+            continue;
+        }
+        info!("Found {} Java libraries in '{}'", libraries.len(), jar.name);
+
+        for library in libraries {
+            if let Some(info) = licenses
+                .iter_mut()
+                .find(|x| x.package_name == library.name && x.version == library.version)
+            {
+                if !info.binaries.contains(&library.jar) {
+                    info.binaries.push(library.jar.clone());
+                }
+                continue;
+            }
+            licenses.push(library.license_info(jar.clone(), overrides, detector)?);
+        }
+    }
+    Ok(licenses)
+}
@@ -0,0 +1,268 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use log::*;
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// Cache of the libraries in the search directories of the dynamic loader, written by `ldconfig`.
+pub const LD_SO_CACHE_FILE: &str = "etc/ld.so.cache";
+/// Configuration of the search directories `ldconfig` writes into the cache.
+pub const LD_SO_CONF_FILE: &str = "etc/ld.so.conf";
+
+const CACHE_MAGIC_OLD: &[u8] = b"ld.so-1.7.0";
+const CACHE_MAGIC_NEW: &[u8] = b"glibc-ld.so.cache1.1";
+const CACHE_HEADER_OLD_SIZE: usize = 16;
+const CACHE_ENTRY_OLD_SIZE: usize = 12;
+const CACHE_HEADER_NEW_SIZE: usize = 48;
+const CACHE_ENTRY_NEW_SIZE: usize = 24;
+const CACHE_FLAG_ARCH_MASK: u32 = 0xff00;
+/// Architecture flags of 64-bit libraries: sparc64, ia64, x86-64, s390x, ppc64, mips64 n64, aarch64,
+/// mips64 n64 nan2008, riscv64 (soft and double float) and loongarch64 (soft and double float)
+const CACHE_FLAGS_64: [u32; 12] = [
+    0x0100, 0x0200, 0x0300, 0x0400, 0x0500, 0x0700, 0x0a00, 0x0e00, 0x0f00, 0x1000, 0x1100, 0x1200,
+];
+
+/// Directories the dynamic loader always searches after the cache, `lib64` holds the 64-bit libraries of
+/// multilib systems.
+const TRUSTED_DIRS_64: [&str; 2] = ["/lib64", "/usr/lib64"];
+const TRUSTED_DIRS_32: [&str; 2] = ["/lib", "/usr/lib"];
+/// Maximum number of symbolic links followed, like `MAXSYMLINKS` of Linux.
+const MAX_SYMLINKS: usize = 40;
+
+/// A library of the loader cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    /// SONAME of the library
+    pub name: String,
+    /// Path of the library on the system
+    pub path: String,
+    pub is_64: bool,
+}
+
+/// Parses an `ld.so.cache` file, the entries are ordered like the loader prefers them.
+pub fn parse_cache(data: &[u8]) -> Option<Vec<CacheEntry>> {
+    let mut offset = 0;
+    // The format of glibc before 2.32 starts with the old format followed by the new one:
+    if data.starts_with(CACHE_MAGIC_OLD) {
+        let libs = read_u32(data, CACHE_MAGIC_OLD.len() + 1)? as usize;
+        offset = (CACHE_HEADER_OLD_SIZE + libs * CACHE_ENTRY_OLD_SIZE).next_multiple_of(8);
+    }
+    let cache = data.get(offset..)?;
+    if !cache.starts_with(CACHE_MAGIC_NEW) {
+        return None;
+    }
+    let libs = read_u32(cache, CACHE_MAGIC_NEW.len())? as usize;
+    let mut entries = Vec::with_capacity(libs);
+    for index in 0..libs {
+        let entry = CACHE_HEADER_NEW_SIZE + index * CACHE_ENTRY_NEW_SIZE;
+        let flags = read_u32(cache, entry)?;
+        // String offsets are relative to the start of the new format:
+        let name = read_str(cache, read_u32(cache, entry + 4)? as usize)?;
+        let path = read_str(cache, read_u32(cache, entry + 8)? as usize)?;
+        let arch = flags & CACHE_FLAG_ARCH_MASK;
+        entries.push(CacheEntry {
+            name,
+            path,
+            is_64: CACHE_FLAGS_64.contains(&arch),
+        });
+    }
+    Some(entries)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_ne_bytes(bytes.try_into().ok()?))
+}
+
+fn read_str(data: &[u8], offset: usize) -> Option<String> {
+    let bytes = data.get(offset..)?;
+    let end = bytes.iter().position(|&x| x == 0)?;
+    Some(String::from_utf8_lossy(&bytes[..end]).into_owned())
+}
+
+/// Resolves libraries like the dynamic loader of a system in a root directory: the loader cache is searched
+/// before the trusted directories. If there is no cache, the directories configured for `ldconfig` are
+/// searched instead.
+#[derive(Debug, Clone, Default)]
+pub struct LibraryResolver {
+    root: PathBuf,
+    cache: Vec<CacheEntry>,
+    conf_dirs: Vec<String>,
+}
+
+impl LibraryResolver {
+    pub fn new(root: &Path) -> Self {
+        let cache_file = root.join(LD_SO_CACHE_FILE);
+        let cache = match fs::read(&cache_file) {
+            Ok(data) => parse_cache(&data).unwrap_or_else(|| {
+                warn!("Cannot parse the loader cache {cache_file:?}, it is ignored");
+                vec![]
+            }),
+            Err(_) => vec![],
+        };
+        let conf_dirs = if cache.is_empty() {
+            let mut conf_dirs = vec![];
+            read_conf(root, &root.join(LD_SO_CONF_FILE), &mut conf_dirs, 0);
+            conf_dirs
+        } else {
+            vec![]
+        };
+        debug!(
+            "Resolve libraries in {root:?} with {} cache entries and {} configured directories",
+            cache.len(),
+            conf_dirs.len()
+        );
+        Self {
+            root: root.to_path_buf(),
+            cache,
+            conf_dirs,
+        }
+    }
+
+    /// Path of the library the loader would load for `name`, e.g. the SONAME `libz.so.1`. 64-bit libraries
+    /// are searched in `lib64` first, 32-bit ones only in `lib`.
+    pub fn resolve(&self, name: &str, is_64: Option<bool>) -> Option<String> {
+        if name.contains('/') {
+            return self.exists(name).then(|| name.to_owned());
+        }
+        if let Some(entry) = self
+            .cache
+            .iter()
+            .find(|entry| entry.name == name && is_64.is_none_or(|x| x == entry.is_64) && self.exists(&entry.path))
+        {
+            return Some(entry.path.clone());
+        }
+        let trusted_dirs: &[&str] = match is_64 {
+            Some(true) => &TRUSTED_DIRS_64,
+            Some(false) => &TRUSTED_DIRS_32,
+            None => &[TRUSTED_DIRS_64, TRUSTED_DIRS_32].concat(),
+        };
+        self.conf_dirs
+            .iter()
+            .map(String::as_str)
+            .chain(trusted_dirs.iter().copied())
+            .map(|dir| format!("{}/{name}", dir.trim_end_matches('/')))
+            .find(|path| self.exists(path))
+    }
+
+    /// The path and the paths its symbolic links resolve to in the order they are followed, e.g.
+    /// `/lib64/libz.so.1`, `/usr/lib64/libz.so.1` and `/usr/lib64/libz.so.1.3.1` if `/lib64` is a link to
+    /// `usr/lib64`. Links are resolved in the root directory, also absolute ones.
+    pub fn symlink_chain(&self, path: &str) -> Vec<String> {
+        let mut chain = vec![normalize(Path::new(path))];
+        'links: while chain.len() <= MAX_SYMLINKS {
+            let current = chain.last().cloned().unwrap_or_default();
+            let components = current.components().skip(1).collect::<Vec<_>>();
+            let mut prefix = PathBuf::from("/");
+            for (index, component) in components.iter().enumerate() {
+                prefix.push(component);
+                let Ok(target) = fs::read_link(self.host_path(&prefix)) else {
+                    continue;
+                };
+                let mut next = prefix.parent().unwrap_or(Path::new("/")).join(target);
+                next.extend(&components[index + 1..]);
+                let next = normalize(&next);
+                if chain.contains(&next) {
+                    break 'links;
+                }
+                chain.push(next);
+                continue 'links;
+            }
+            break;
+        }
+        chain.into_iter().map(|x| x.to_string_lossy().into_owned()).collect()
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        let chain = self.symlink_chain(path);
+        chain.last().is_some_and(|x| self.host_path(Path::new(x)).exists())
+    }
+
+    /// Path of a file of the system in the root directory.
+    fn host_path(&self, path: &Path) -> PathBuf {
+        self.root.join(path.strip_prefix("/").unwrap_or(path))
+    }
+}
+
+/// Reads the directories of an `ld.so.conf` file and the files it includes.
+fn read_conf(root: &Path, file: &Path, dirs: &mut Vec<String>, depth: usize) {
+    let Ok(content) = fs::read_to_string(file) else {
+        return;
+    };
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if let Some(pattern) = line
+            .strip_prefix("include")
+            .filter(|x| x.starts_with(char::is_whitespace))
+        {
+            if depth >= MAX_SYMLINKS {
+                warn!("Skip include '{line}' of {file:?}, includes are nested too deeply");
+                continue;
+            }
+            for pattern in pattern.split_whitespace() {
+                for include in conf_includes(root, file, pattern) {
+                    read_conf(root, &include, dirs, depth + 1);
+                }
+            }
+        } else if line.starts_with('/') {
+            // Directories may be followed by a library type, e.g. `/usr/lib/libc5-compat=libc5`:
+            let dir = line.split(['=', ' ', '\t', ',', ':']).next().unwrap_or(line);
+            if !dirs.iter().any(|x| x == dir) {
+                dirs.push(dir.to_owned());
+            }
+        }
+    }
+}
+
+/// Files matching an include pattern like `ld.so.conf.d/*.conf`, relative patterns are relative to the
+/// directory of the including file.
+fn conf_includes(root: &Path, file: &Path, pattern: &str) -> Vec<PathBuf> {
+    let pattern = match pattern.strip_prefix('/') {
+        Some(pattern) => root.join(pattern),
+        None => file.parent().unwrap_or(root).join(pattern),
+    };
+    let (Some(dir), Some(name)) = (pattern.parent(), pattern.file_name().map(|x| x.to_string_lossy())) else {
+        return vec![];
+    };
+    let Some((prefix, suffix)) = name.split_once('*') else {
+        return vec![pattern.clone()];
+    };
+    let mut includes = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .map(|x| x.to_string_lossy())
+                .is_some_and(|x| x.len() >= prefix.len() + suffix.len() && x.starts_with(prefix) && x.ends_with(suffix))
+        })
+        .collect::<Vec<_>>();
+    includes.sort();
+    includes
+}
+
+/// Removes `.` and `..` components of an absolute path without accessing the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::from("/");
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::Normal(name) => normalized.push(name),
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+        }
+    }
+    normalized
+}
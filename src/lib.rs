@@ -8,16 +8,63 @@
 //
 // SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
 
+pub use error::{Error, Result};
+
+pub mod error;
 pub mod file_info;
 pub mod license_info;
 pub mod license_text;
 pub mod third_party_licenses;
 
+#[cfg(feature = "scan")]
+pub mod apk_info;
+#[cfg(feature = "scan")]
+pub mod asset_info;
+#[cfg(feature = "scan")]
+pub mod binary_info;
+#[cfg(feature = "scan")]
+pub mod bundle;
+#[cfg(feature = "scan")]
+pub mod cargo_auditable;
+#[cfg(feature = "scan")]
+pub mod conan_info;
+#[cfg(feature = "scan")]
+pub mod crates_info;
+#[cfg(feature = "scan")]
+pub mod go_info;
+#[cfg(feature = "scan")]
+pub mod java_info;
+#[cfg(feature = "scan")]
+pub mod ld_so;
 #[cfg(feature = "scan")]
 pub mod license_detector;
 #[cfg(feature = "scan")]
+pub mod nix_info;
+#[cfg(feature = "scan")]
+pub mod node_info;
+#[cfg(feature = "scan")]
+pub mod package_file;
+#[cfg(feature = "scan")]
+pub mod package_source;
+#[cfg(feature = "scan")]
+pub mod pacman_info;
+#[cfg(feature = "scan")]
+pub mod python_info;
+#[cfg(feature = "scan")]
 pub mod rpm_info;
 #[cfg(feature = "scan")]
+pub mod scanner;
+#[cfg(feature = "scan")]
 pub mod settings;
 #[cfg(feature = "scan")]
+pub mod settings_lint;
+#[cfg(feature = "scan")]
+pub mod system_packages;
+#[cfg(feature = "scan")]
+pub mod target_os;
+#[cfg(feature = "scan")]
 pub mod vcpkg_spdx;
+#[cfg(feature = "scan")]
+pub mod vcpkg_status;
+#[cfg(feature = "scan")]
+pub mod version;
@@ -8,14 +8,17 @@
 //
 // SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
 
-use anyhow::{Context, Result};
 use log::*;
 use regex::Regex;
 use spdx::LicenseId;
-use std::path::Path;
-use std::{collections::HashMap, io::Write, sync::OnceLock};
+use std::path::{Path, PathBuf};
+use std::{collections::HashMap, sync::LazyLock};
 
-use crate::license_text::LicenseText;
+use crate::{
+    error::{Error, Result},
+    file_info,
+    license_text::LicenseText,
+};
 
 #[derive(Debug, Clone)]
 pub struct LicenseFile {
@@ -30,12 +33,59 @@ struct LicenseHash {
     word_hash: HashMap<String, u32>,
 }
 
+/// SPDX licenses whose texts are used as templates to detect the license of a text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TemplateSet {
+    /// All SPDX licenses
+    #[default]
+    All,
+    /// SPDX licenses that are not deprecated
+    Current,
+    /// SPDX licenses approved by the Open Source Initiative
+    OsiApproved,
+    /// SPDX licenses the Free Software Foundation considers free
+    FsfLibre,
+    /// SPDX licenses with the given ids
+    Ids(Vec<String>),
+    /// No SPDX licenses, only the custom templates are used
+    None,
+}
+
+impl TemplateSet {
+    fn contains(&self, license: spdx::LicenseId) -> bool {
+        match self {
+            Self::All => true,
+            Self::Current => !license.is_deprecated(),
+            Self::OsiApproved => license.is_osi_approved(),
+            Self::FsfLibre => license.is_fsf_free_libre(),
+            Self::Ids(ids) => ids.iter().any(|id| id == license.name),
+            Self::None => false,
+        }
+    }
+}
+
+/// Detects the SPDX license of license texts by comparing the words of the text with the words of license
+/// templates. [`LicenseDetector::shared`] is a detector with the default configuration.
 #[derive(Debug, Clone)]
 pub struct LicenseDetector {
     templates: Vec<LicenseHash>,
+    /// Maximum score of a detected license, texts with a higher score are not detected
+    threshold: Option<f32>,
+    /// Directory the texts of mismatching licenses are written to, nothing is written if `None`
+    mismatch_dir: Option<PathBuf>,
+}
+
+/// Configures a [`LicenseDetector`], by default all SPDX licenses are used as templates.
+#[derive(Debug, Clone, Default)]
+pub struct LicenseDetectorBuilder {
+    template_set: TemplateSet,
+    custom_templates: Vec<(String, String)>,
+    threshold: Option<f32>,
+    mismatch_dir: Option<PathBuf>,
 }
 
-static INSTANCE: OnceLock<LicenseDetector> = OnceLock::new();
+static SHARED: LazyLock<LicenseDetector> = LazyLock::new(LicenseDetector::default);
+static WORD_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\w+").expect("Invalid word regex"));
 
 impl LicenseHash {
     pub fn new(id: Option<String>, text: &str) -> Self {
@@ -49,33 +99,74 @@ impl LicenseHash {
 
     fn generate_hash(text: &str) -> HashMap<String, u32> {
         let mut word_hash = HashMap::new();
-        for word in Regex::new(r"\w+").unwrap().find_iter(text) {
+        for word in WORD_REGEX.find_iter(text) {
             *word_hash.entry(word.as_str().to_lowercase().clone()).or_insert(0) += 1;
         }
         word_hash
     }
 }
 
-impl LicenseDetector {
-    pub fn init() {
-        let license_detector = Self::build();
-        INSTANCE.set(license_detector).unwrap();
+impl LicenseDetectorBuilder {
+    pub fn template_set(mut self, template_set: TemplateSet) -> Self {
+        self.template_set = template_set;
+        self
+    }
+
+    /// Adds a license template, e.g. the license of an in-house library with the id `LicenseRef-Acme`.
+    pub fn template(mut self, id: impl Into<String>, text: impl Into<String>) -> Self {
+        self.custom_templates.push((id.into(), text.into()));
+        self
+    }
+
+    /// Maximum score of a detected license: the number of words that differ from the template divided by the
+    /// number of words of the template. Texts with a higher score are reported as `NOASSERTION` (default: no limit).
+    pub fn threshold(mut self, threshold: impl Into<Option<f32>>) -> Self {
+        self.threshold = threshold.into();
+        self
     }
 
-    pub fn instance() -> &'static Self {
-        INSTANCE
-            .get()
-            .expect("LicenseDetector is not initialized, please execute LicenseDetector::init()")
+    /// Writes the texts of licenses that differ from the declared license to `<mismatch_dir>/<package>` to
+    /// analyze the mismatch.
+    pub fn mismatch_dir(mut self, mismatch_dir: impl Into<Option<PathBuf>>) -> Self {
+        self.mismatch_dir = mismatch_dir.into();
+        self
     }
 
-    fn build() -> Self {
+    pub fn build(self) -> LicenseDetector {
         let mut templates = vec![];
         for license in spdx::identifiers::LICENSES {
-            let license = spdx::license_id(license.name).unwrap();
-
-            templates.push(LicenseHash::new(Some(license.name.to_owned()), license.text()));
+            let Some(license) = spdx::license_id(license.name) else {
+                continue;
+            };
+            if self.template_set.contains(license) {
+                templates.push(LicenseHash::new(Some(license.name.to_owned()), license.text()));
+            }
         }
-        LicenseDetector { templates }
+        for (id, text) in &self.custom_templates {
+            templates.push(LicenseHash::new(Some(id.clone()), text));
+        }
+        LicenseDetector {
+            templates,
+            threshold: self.threshold,
+            mismatch_dir: self.mismatch_dir,
+        }
+    }
+}
+
+impl Default for LicenseDetector {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl LicenseDetector {
+    pub fn builder() -> LicenseDetectorBuilder {
+        LicenseDetectorBuilder::default()
+    }
+
+    /// Detector with the default configuration, it is created on first use.
+    pub fn shared() -> &'static Self {
+        &SHARED
     }
 
     fn compare(text_hash: &HashMap<String, u32>, template_hash: &HashMap<String, u32>) -> u32 {
@@ -95,70 +186,82 @@ impl LicenseDetector {
         errors
     }
 
-    pub fn detect_license(&self, package: &str, license_ids: &[LicenseId], text: &str) -> LicenseText {
-        let mut text_hash = LicenseHash::new(None, text);
-        let mut best_score = None;
-        let mut best_template_text = "".to_owned();
-
-        if license_ids.is_empty() {
-            for template in &self.templates {
-                let total: u32 = template.word_hash.values().sum();
-                let errors = Self::compare(&text_hash.word_hash, &template.word_hash);
-                let score = (errors as f32) / (total as f32);
-
-                trace!("Score for {}: {}", template.id.as_ref().unwrap(), score);
-                if best_score.is_none() || score < best_score.unwrap() {
-                    best_score = Some(score);
-                    text_hash.id = template.id.clone();
-                    best_template_text = template.text.to_owned();
-                }
-            }
+    /// Writes the license text, the best matching template and the text of the declared license to
+    /// `<mismatch_dir>/<package>` to analyze why the detected license differs from the declared one.
+    fn write_mismatch(
+        mismatch_dir: &Path,
+        package: &str,
+        license_id: LicenseId,
+        best_template_text: &str,
+        text: &str,
+    ) -> Result<()> {
+        let tmp_dir = mismatch_dir.join(file_info::package_dir_name(package));
+        std::fs::create_dir_all(&tmp_dir).map_err(Error::io(&tmp_dir))?;
+        for (name, content) in [
+            ("best_template_text.txt", best_template_text),
+            ("original_text.txt", text),
+            ("spdx_id_text.txt", license_id.text()),
+        ] {
+            let text_file = tmp_dir.join(name);
+            std::fs::write(&text_file, content).map_err(Error::io(&text_file))?;
+        }
+        Ok(())
+    }
+
+    pub fn detect_license(&self, package: &str, license_ids: &[LicenseId], text: &str) -> Result<LicenseText> {
+        let text_hash = LicenseHash::new(None, text);
+        let declared_templates;
+        let templates = if license_ids.is_empty() {
+            &self.templates
         } else {
-            for license_id in license_ids {
-                let template = LicenseHash::new(Some(license_id.name.to_owned()), license_id.text());
-                let total: u32 = template.word_hash.values().sum();
-                let errors = Self::compare(&text_hash.word_hash, &template.word_hash);
-                let score = (errors as f32) / (total as f32);
-
-                trace!("Score for {}: {}", template.id.as_ref().unwrap(), score);
-                if best_score.is_none() || score < best_score.unwrap() {
-                    best_score = Some(score);
-                    text_hash.id = template.id.clone();
-                    best_template_text = template.text.to_owned();
-                }
+            declared_templates = license_ids
+                .iter()
+                .map(|license_id| LicenseHash::new(Some(license_id.name.to_owned()), license_id.text()))
+                .collect::<Vec<_>>();
+            &declared_templates
+        };
+
+        let mut best: Option<(f32, &LicenseHash)> = None;
+        for template in templates {
+            let total: u32 = template.word_hash.values().sum();
+            let errors = Self::compare(&text_hash.word_hash, &template.word_hash);
+            let score = (errors as f32) / (total as f32);
+
+            trace!("Score for {}: {}", template.id.as_deref().unwrap_or_default(), score);
+            if best.is_none_or(|(best_score, _)| score < best_score) {
+                best = Some((score, template));
             }
         }
-        let score = best_score.unwrap();
-
-        debug!("Best score was for {}: {}", text_hash.id.as_ref().unwrap(), score);
-
-        if license_ids.len() == 1 {
-            let license_id = license_ids.first().unwrap();
-            if text_hash.id.as_ref().unwrap() != license_id.name {
-                let tmp_dir = Path::new("license_detect").join(package);
-                std::fs::create_dir_all(tmp_dir.clone()).unwrap();
-
-                let text_file = tmp_dir.join("best_template_text.txt");
-                let mut file = std::fs::File::create(text_file).unwrap();
-                file.write_all(best_template_text.as_bytes()).unwrap();
-                drop(file);
-
-                let text_file = tmp_dir.join("original_text.txt");
-                let mut file = std::fs::File::create(text_file).unwrap();
-                file.write_all(text_hash.text.as_bytes()).unwrap();
-                drop(file);
-
-                let text_file = tmp_dir.join("spdx_id_text.txt");
-                let mut file = std::fs::File::create(text_file).unwrap();
-                file.write_all(license_id.text().as_bytes()).unwrap();
-                drop(file);
-            }
+        let Some((score, Some(id), best_template_text)) = best.map(|(score, x)| (score, x.id.clone(), &x.text)) else {
+            return Err(Error::NoLicenseTemplates(package.to_owned()));
+        };
+
+        debug!("Best score was for {id}: {score}");
+
+        if let Some(threshold) = self.threshold
+            && score > threshold
+        {
+            warn!(
+                "Cannot detect the license of a license text of package '{package}', the best match '{id}' has the score {score}"
+            );
+            return Ok(LicenseText {
+                id: "NOASSERTION".to_owned(),
+                text: text.to_owned(),
+            });
         }
 
-        LicenseText {
-            id: text_hash.id.unwrap(),
-            text: text.to_owned(),
+        if let Some(mismatch_dir) = &self.mismatch_dir
+            && let [license_id] = license_ids
+            && id != license_id.name
+            && let Err(error) = Self::write_mismatch(mismatch_dir, package, *license_id, best_template_text, text)
+        {
+            warn!("Cannot write license texts of package '{package}': {error}");
         }
+
+        Ok(LicenseText {
+            id,
+            text: text.to_owned(),
+        })
     }
 
     pub fn detect_licenses(
@@ -170,13 +273,12 @@ impl LicenseDetector {
         debug!("Detect license ids for license texts of package {package}");
         let mut license_texts = vec![];
         for license_file in license_files {
-            let text = std::fs::read_to_string(license_file.file.clone())
-                .with_context(|| format!("Cannot read third party license file {}", license_file.file))?;
+            let text = std::fs::read_to_string(&license_file.file).map_err(Error::io(&license_file.file))?;
 
             if let Some(id) = license_file.id.as_ref() {
                 license_texts.push(LicenseText { id: id.clone(), text });
             } else {
-                license_texts.push(LicenseDetector::instance().detect_license(package, license_ids, text.as_str()));
+                license_texts.push(self.detect_license(package, license_ids, text.as_str())?);
             }
         }
         Ok(license_texts)
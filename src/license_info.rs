@@ -9,6 +9,8 @@
 // SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
 
 use crate::{file_info::FileInfo, license_text::LicenseText};
+#[cfg(feature = "scan")]
+use crate::{license_detector::LicenseFile, settings};
 
 #[derive(Debug, Clone)]
 pub struct LicenseInfo {
@@ -19,4 +21,69 @@ pub struct LicenseInfo {
     pub license_texts: Vec<LicenseText>,
     pub version: Option<String>,
     pub url: Option<String>,
+    pub features: Vec<String>,
+    /// Names of the direct dependencies, the dependency tree is reconstructed from the packages of the report
+    pub dependencies: Vec<String>,
+    /// Binaries the package is attributed to, e.g. the executables a crate is compiled into
+    pub binaries: Vec<String>,
+}
+
+/// Replaces the license and the license files of a package by the values of its override.
+#[cfg(feature = "scan")]
+pub fn apply_override(
+    override_info: Option<&settings::Override>,
+    license: Option<String>,
+    license_files: Vec<LicenseFile>,
+) -> (Option<String>, Vec<LicenseFile>) {
+    let Some(override_info) = override_info else {
+        return (license, license_files);
+    };
+    let license = override_info.license_id.clone().or(license);
+    let license_files = if override_info.license_files.is_empty() {
+        license_files
+    } else {
+        override_info
+            .license_files
+            .iter()
+            .map(|license_file| LicenseFile {
+                id: license_file.id.clone(),
+                file: license_file.file.clone(),
+            })
+            .collect()
+    };
+    (license, license_files)
+}
+
+/// Parses a license expression leniently, e.g. `MIT/Apache-2.0` is accepted as `MIT OR Apache-2.0`.
+#[cfg(feature = "scan")]
+pub fn parse_license_expression(package: &str, license: &str) -> Option<spdx::Expression> {
+    parse_license_expression_mode(package, license, spdx::ParseMode::LAX)
+}
+
+/// Parses a valid SPDX license expression, e.g. of vcpkg ports that are checked by vcpkg.
+#[cfg(feature = "scan")]
+pub fn parse_strict_license_expression(package: &str, license: &str) -> Option<spdx::Expression> {
+    parse_license_expression_mode(package, license, spdx::ParseMode::STRICT)
+}
+
+#[cfg(feature = "scan")]
+fn parse_license_expression_mode(package: &str, license: &str, mode: spdx::ParseMode) -> Option<spdx::Expression> {
+    if license.is_empty() {
+        return None;
+    }
+    match spdx::Expression::parse_mode(license, mode) {
+        Ok(expr) => Some(expr),
+        Err(error) => {
+            log::warn!("Cannot parse license expression for package '{package}': {error}");
+            None
+        }
+    }
+}
+
+/// Collects the SPDX license ids of a license expression.
+pub fn license_ids(license_expression: Option<&spdx::Expression>) -> Vec<spdx::LicenseId> {
+    let Some(expr) = license_expression else {
+        return vec![];
+    };
+    expr.requirements().filter_map(|expr| expr.req.license.id()).collect()
 }
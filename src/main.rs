@@ -8,27 +8,60 @@
 //
 // SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
 
-use anyhow::{Context, Result};
+use anyhow::Result;
 use clap::{Args, Parser, Subcommand};
 use flexi_logger::Logger;
 use log::*;
 use sancus_lib::{
-    file_info,
-    file_info::FileInfo,
+    bundle::BundleKind,
     license_detector::LicenseDetector,
-    license_info::LicenseInfo,
-    rpm_info, settings,
-    third_party_licenses::{self, ThirdPartyLicenses},
-    vcpkg_spdx::{get_license_info, parse_spdx_files},
+    package_file::PackageFile,
+    scanner::{ScanBuilder, ScanReport, Scanner, Source},
+    settings::{self, Overrides},
+    settings_lint,
+    system_packages::PackageManager,
+    target_os::TargetOs,
+    third_party_licenses::ThirdPartyLicenses,
+};
+use std::{
+    ops::Deref,
+    panic,
+    path::{Path, PathBuf},
+    sync::Arc,
 };
-use std::{fs, ops::Deref, panic, path::PathBuf};
 
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Parse a project and create third party information for it
-    Create(ExtractFromProductArgs),
+    Create(Box<ExtractFromProductArgs>),
     /// Export third party license files to a directory
     Export(ExportFromJsonArgs),
+    /// Inspect the settings of a project
+    #[command(subcommand)]
+    Config(ConfigCommands),
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommands {
+    /// Print the effective settings, merged with the included settings files, and the origin of each entry
+    Show(ConfigShowArgs),
+    /// Print the JSON Schema of the settings file
+    Schema,
+    /// Scan the package like 'create' without writing any results and report stale or invalid settings
+    Lint(Box<ExtractFromProductArgs>),
+}
+
+#[derive(Args, Debug)]
+struct ConfigShowArgs {
+    /// Path to the project root directory, the settings file is searched in it and its parent directories
+    #[arg(long, default_value = ".")]
+    project_path: PathBuf,
+    /// Settings file in YAML, TOML or JSON format (default: SANCUS_CONFIG or 'sancus.yaml' found in the project)
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Directory of shared settings files that can be included (default: SANCUS_SHARED_SETTINGS_DIR)
+    #[arg(long)]
+    shared_settings_dir: Option<PathBuf>,
 }
 
 #[derive(Args, Debug)]
@@ -39,9 +72,12 @@ struct ExtractFromProductArgs {
     /// Path to the project root directory
     #[arg(long)]
     project_path: PathBuf,
-    /// Path to the package directory
+    /// Path to the package directory or to a '.rpm' or '.deb' package file
     #[arg(long)]
     package_path: PathBuf,
+    /// Layout of the package directory: directory, flatpak or snap (default: detected)
+    #[arg(long)]
+    bundle: Option<BundleKind>,
     /// Path to the result directory
     #[arg(long)]
     result_path: PathBuf,
@@ -51,6 +87,55 @@ struct ExtractFromProductArgs {
     /// Path to crates licenses
     #[arg(long)]
     additional_third_party_licenses: Vec<PathBuf>,
+    /// Path to the vcpkg installation directory (default: 'vcpkg_installed' in the project directory)
+    #[arg(long)]
+    vcpkg_root: Option<PathBuf>,
+    /// vcpkg triplet of the shipped libraries, only this triplet is analyzed
+    #[arg(long)]
+    triplet: Option<String>,
+    /// vcpkg host triplet, its ports are reported as build tools that are not shipped
+    #[arg(long)]
+    host_triplet: Option<String>,
+    /// Operating system the package is built for: linux, windows or macos (default: the host OS)
+    #[arg(long)]
+    target_os: Option<TargetOs>,
+    /// Conan dependency graph: the JSON output of 'conan graph info --format=json' or a 'conan.lock' file
+    #[arg(long)]
+    conan_graph: Option<PathBuf>,
+    /// Conan home directory containing the package cache (default: CONAN_HOME or ~/.conan2)
+    #[arg(long, requires = "conan_graph")]
+    conan_home: Option<PathBuf>,
+    /// Path to the Cargo.lock file of the Rust crates to collect licenses for
+    #[arg(long)]
+    cargo_lock: Option<PathBuf>,
+    /// Directories containing the crate sources (default: 'vendor' next to Cargo.lock and the local registry cache)
+    #[arg(long)]
+    cargo_vendor: Vec<PathBuf>,
+    /// Target triple the crates are compiled for, e.g. 'x86_64-unknown-linux-gnu'
+    #[arg(long, requires = "cargo_lock")]
+    cargo_target: Option<String>,
+    /// Package manager of the system packages: rpm, apk or pacman (default: detected from the system root)
+    #[arg(long)]
+    package_manager: Option<PackageManager>,
+    /// Root directory of the system the package is installed on, its rpm, apk or pacman database is read (default: '/')
+    #[arg(long)]
+    system_root: Option<PathBuf>,
+    /// Package metadata of the Nix package set, e.g. written by 'nix-env -qa --json --meta --out-path'
+    #[arg(long)]
+    nix_metadata: Option<PathBuf>,
+    /// Go module cache containing the module sources (default: GOMODCACHE, GOPATH/pkg/mod or ~/go/pkg/mod)
+    #[arg(long)]
+    go_mod_cache: Option<PathBuf>,
+    /// Sources to report, comma separated: vcpkg, conan, system, nix, crates, rust-binaries, go-binaries,
+    /// python, node, java and assets (default: all)
+    #[arg(long, value_delimiter = ',')]
+    sources: Vec<Source>,
+    /// Settings file in YAML, TOML or JSON format (default: SANCUS_CONFIG or 'sancus.yaml' found in the project)
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Directory of shared settings files that can be included (default: SANCUS_SHARED_SETTINGS_DIR)
+    #[arg(long)]
+    shared_settings_dir: Option<PathBuf>,
 }
 
 #[derive(Args, Debug)]
@@ -69,30 +154,24 @@ struct Cli {
     command: Commands,
 }
 
-const SYSTEM_THIRD_PARTY_LICENSES_FILE: &str = "system_third_party_licenses.json";
-const VCPKG_THIRD_PARTY_LICENSES_FILE: &str = "vcpkg_third_party_licenses.json";
-
-fn find_package_system_libs(package_libs: &Vec<FileInfo>, vcpkg_libs: &[FileInfo]) -> Vec<FileInfo> {
-    let mut system_libs = vec![];
-    for lib in package_libs {
-        if !vcpkg_libs.iter().any(|l| l.name == lib.name) {
-            system_libs.push(lib.clone());
+const PACKAGE_FILE_PAYLOAD_DIR: &str = "payload";
+const LICENSE_MISMATCH_DIR: &str = "license_detect";
+
+fn load_settings(
+    project_path: &Path,
+    config: Option<&Path>,
+    shared_settings_dir: Option<&Path>,
+) -> Result<settings::Settings> {
+    match settings::Settings::find_settings_file(project_path, config)? {
+        Some(settings_file) => {
+            info!("Using settings file {settings_file:?}");
+            Ok(settings::Settings::load(&settings_file, shared_settings_dir)?)
         }
-    }
-    system_libs
-}
-
-fn system_libs_info(system_libs: &Vec<FileInfo>, overrides: &[settings::Override]) -> Result<Vec<LicenseInfo>> {
-    let mut system_lib_info: Vec<LicenseInfo> = vec![];
-    for lib_info in system_libs {
-        trace!("Query system info of '{}'", lib_info.name);
-        let package = rpm_info::package_of_lib(&lib_info.name)?;
-        if !system_lib_info.iter().any(|info| info.package_name == package.name()) {
-            let info = package.license_info(lib_info.clone(), overrides)?;
-            system_lib_info.push(info);
+        None => {
+            info!("Cannot find a settings file, using the default settings");
+            Ok(settings::Settings::default())
         }
     }
-    Ok(system_lib_info)
 }
 
 fn run(args: &Cli) -> Result<()> {
@@ -103,124 +182,107 @@ fn run(args: &Cli) -> Result<()> {
         Commands::Export(args) => {
             export_from_json(args)?;
         }
+        Commands::Config(ConfigCommands::Show(args)) => {
+            show_settings(args)?;
+        }
+        Commands::Config(ConfigCommands::Schema) => {
+            println!("{}", settings::Settings::json_schema()?);
+        }
+        Commands::Config(ConfigCommands::Lint(args)) => {
+            lint_settings(args)?;
+        }
     }
     Ok(())
 }
 
-fn export_from_product(args: &ExtractFromProductArgs) -> Result<()> {
-    let mut vcpkg_find_ignore_list = vec!["debug".to_owned()];
-
-    #[cfg(target_os = "linux")]
-    {
-        vcpkg_find_ignore_list.push("x64-linux".to_owned()); // Only used to provide build tools
-    }
-
-    debug!("project = {:?}, package = {:?}", args.project_path, args.package_path);
+/// Builds the scanner of the package in `package_path`, `package_file` is the package file extracted to it.
+fn build_scanner(
+    args: &ExtractFromProductArgs,
+    settings: settings::Settings,
+    package_path: PathBuf,
+    package_file: Option<PackageFile>,
+    license_detector: LicenseDetector,
+) -> Result<Scanner> {
+    Ok(ScanBuilder::new(&args.project_path, package_path)
+        .settings(settings)
+        .sources(args.sources.iter().copied())
+        .package_file(package_file)
+        .bundle(args.bundle)
+        .vcpkg_root(args.vcpkg_root.clone())
+        .triplet(args.triplet.clone())
+        .host_triplet(args.host_triplet.clone())
+        .target_os(args.target_os)
+        .conan_graph(args.conan_graph.clone())
+        .conan_home(args.conan_home.clone())
+        .cargo_lock(args.cargo_lock.clone())
+        .cargo_vendor(args.cargo_vendor.clone())
+        .cargo_target(args.cargo_target.clone())
+        .package_manager(args.package_manager)
+        .system_root(args.system_root.clone())
+        .nix_metadata(args.nix_metadata.clone())
+        .go_mod_cache(args.go_mod_cache.clone())
+        .license_detector(Arc::new(license_detector))
+        .build()?)
+}
 
-    let settings_file = args.project_path.join(settings::Settings::default_settings_file());
-    let settings = if settings_file.is_file() {
-        settings::Settings::load(&args.project_path.join(settings::Settings::default_settings_file()))?
+/// Extracts a package file to `payload_path`, package directories are scanned as they are.
+fn package_path(args: &ExtractFromProductArgs, payload_path: &Path) -> Result<(PathBuf, Option<PackageFile>)> {
+    if args.package_path.is_file() {
+        let package_file = PackageFile::extract(&args.package_path, payload_path)?;
+        Ok((payload_path.to_path_buf(), Some(package_file)))
     } else {
-        settings::Settings::default()
-    };
-
-    for replace in &settings.overrides {
-        trace!(
-            "Found override for {} in settings. Comment: {}",
-            replace.package, replace.comment
-        )
-    }
-
-    for vcpkg_ignore in &settings.vcpkg_ignores {
-        let add_ignore = if let Some(os) = &vcpkg_ignore.os {
-            os == std::env::consts::OS
-        } else {
-            true
-        };
-        if add_ignore {
-            info!(
-                "Add vcpkg directory {} to ignore list. Comment: {}",
-                vcpkg_ignore.directory, vcpkg_ignore.comment
-            );
-            vcpkg_find_ignore_list.push(vcpkg_ignore.directory.clone());
-        }
+        Ok((args.package_path.clone(), None))
     }
+}
 
-    let mut package_find_ignore_list = vec!["debug".to_owned()];
-    for lib_ignore in &settings.lib_ignores {
-        info!(
-            "Add library {} to ignore list. Comment: {}",
-            lib_ignore.lib, lib_ignore.comment
-        );
-        package_find_ignore_list.push(lib_ignore.lib.clone());
+/// Loads the additional third party licenses files and applies the overrides of the crates to them.
+/// Returns the original and the overridden licenses of each file.
+fn additional_third_party_licenses(
+    args: &ExtractFromProductArgs,
+    overrides: &Overrides,
+) -> Result<Vec<(ThirdPartyLicenses, ThirdPartyLicenses)>> {
+    let mut licenses = vec![];
+    for crates_licenses_file in &args.additional_third_party_licenses {
+        let crates_third_party_licenses_orig = ThirdPartyLicenses::load(crates_licenses_file)?;
+        let mut crates_third_party_licenses = crates_third_party_licenses_orig.clone();
+        crates_third_party_licenses.apply_overrides("crates", overrides)?;
+        licenses.push((crates_third_party_licenses_orig, crates_third_party_licenses));
     }
+    Ok(licenses)
+}
 
-    // Find vcpkg installation
-    let mut vcpkg_installation: Option<PathBuf> = None;
-    for entry in fs::read_dir(args.project_path.clone())? {
-        let entry = entry?;
-        let path = entry.path().clone();
-        let name = entry.file_name().to_string_lossy().into_owned();
-
-        trace!("Project files: path={path:?} name={name:?}");
+/// Scans the package, the package file is extracted to the result directory and its payload is scanned.
+fn scan(args: &ExtractFromProductArgs) -> Result<ScanReport> {
+    let settings = load_settings(
+        &args.project_path,
+        args.config.as_deref(),
+        args.shared_settings_dir.as_deref(),
+    )?;
 
-        if path.is_dir() && name == "vcpkg_installed" {
-            info!("FOUND vcpkg installation at: {path:?}");
-            vcpkg_installation = Some(path);
+    // Package files are extracted to the result directory and analyzed like a package directory:
+    let (package_path, package_file) = package_path(args, &args.result_path.join(PACKAGE_FILE_PAYLOAD_DIR))?;
+    let license_detector = LicenseDetector::builder()
+        .mismatch_dir(PathBuf::from(LICENSE_MISMATCH_DIR))
+        .build();
+    let scanner = build_scanner(args, settings, package_path, package_file, license_detector)?;
+    let mut scan_report = scanner.scan()?;
+    let overrides = Overrides::new(scanner.settings().overrides.clone());
+
+    let mut exported = vec![];
+    for report in &scan_report.reports {
+        let third_party_licenses = report.third_party_licenses(&args.package_name);
+        third_party_licenses.save(&args.result_path.join(report.kind.file_name()))?;
+        third_party_licenses.print();
+        // Build tools and the runtime of the bundle are saved for reference but not exported:
+        if report.kind.is_shipped() {
+            exported.push(third_party_licenses);
         }
     }
-    let vcpkg_installation = if let Some(vcpkg_installation) = vcpkg_installation {
-        vcpkg_installation
-    } else {
-        return Err(anyhow::anyhow!(
-            "Cannot find vcpkg installation in {:?}",
-            args.project_path
-        ));
-    };
-    // Collect libraries in the vcpkg installation
-    let vcpkg_libs =
-        file_info::find_files_recurse(&vcpkg_installation.clone(), ".so", vcpkg_find_ignore_list.as_slice()).context(
-            format!("Cannot find '.so' files in '{}'", vcpkg_installation.to_string_lossy()),
-        )?;
-    let package_libs =
-        file_info::find_files_recurse(&args.package_path.clone(), ".so", package_find_ignore_list.as_slice()).context(
-            format!("Cannot find '.so' files in '{}'", args.package_path.to_string_lossy()),
-        )?;
-
-    // Find all vcpkg.spdx.json files
-    let vcpkg_spdx_files = file_info::find_files_recurse(
-        &vcpkg_installation,
-        "vcpkg.spdx.json",
-        vcpkg_find_ignore_list.as_slice(),
-    )?;
-
-    parse_spdx_files(&vcpkg_spdx_files)?;
-
-    let vcpkg_licenses = get_license_info(&vcpkg_spdx_files, settings.overrides.as_slice())?;
-
-    let vcpkg_third_party_licenses =
-        third_party_licenses::ThirdPartyLicenses::new(format!("{}-vcpkg", args.package_name).as_str(), &vcpkg_licenses);
-    vcpkg_third_party_licenses.save(&args.result_path.join(VCPKG_THIRD_PARTY_LICENSES_FILE))?;
-    vcpkg_third_party_licenses.print();
-
-    // Find libs that are either from vcpkg or from the system:
-    let system_libs = find_package_system_libs(&package_libs, &vcpkg_libs);
-
-    let system_licenses = system_libs_info(&system_libs, settings.overrides.as_slice())?;
-
-    let system_third_party_licenses = third_party_licenses::ThirdPartyLicenses::new(
-        format!("{}-system", args.package_name).as_str(),
-        &system_licenses,
-    );
-    system_third_party_licenses.save(&args.result_path.join(SYSTEM_THIRD_PARTY_LICENSES_FILE))?;
-    system_third_party_licenses.print();
-
-    for crates_licenses_file in &args.additional_third_party_licenses {
-        let crates_third_party_licenses_orig = ThirdPartyLicenses::load(crates_licenses_file)?;
-        let mut crates_third_party_licenses = crates_third_party_licenses_orig.clone();
-
-        crates_third_party_licenses.apply_overrides(settings.overrides.as_slice())?;
 
+    let additional = additional_third_party_licenses(args, &overrides)?;
+    for (crates_licenses_file, (crates_third_party_licenses_orig, crates_third_party_licenses)) in
+        args.additional_third_party_licenses.iter().zip(&additional)
+    {
         if crates_third_party_licenses != crates_third_party_licenses_orig {
             crates_third_party_licenses.save(crates_licenses_file)?;
         }
@@ -232,10 +294,21 @@ fn export_from_product(args: &ExtractFromProductArgs) -> Result<()> {
     }
 
     if let Some(export_path) = &args.export_path {
-        vcpkg_third_party_licenses.export(export_path)?;
-        system_third_party_licenses.export(export_path)?;
+        for third_party_licenses in &exported {
+            third_party_licenses.export(export_path)?;
+        }
     }
 
+    for (index, packages) in overrides.matches() {
+        scan_report.override_matches.entry(index).or_default().extend(packages);
+    }
+    settings::Override::report_matches(overrides.as_slice(), &scan_report.override_matches);
+
+    Ok(scan_report)
+}
+
+fn export_from_product(args: &ExtractFromProductArgs) -> Result<()> {
+    scan(args)?;
     Ok(())
 }
 
@@ -249,6 +322,88 @@ fn export_from_json(args: &ExportFromJsonArgs) -> Result<()> {
     Ok(())
 }
 
+fn show_settings(args: &ConfigShowArgs) -> Result<()> {
+    let settings = load_settings(
+        &args.project_path,
+        args.config.as_deref(),
+        args.shared_settings_dir.as_deref(),
+    )?;
+    print!("{}", settings.describe()?);
+    Ok(())
+}
+
+/// Scans the package without saving or exporting the results and reports the problems of the settings.
+/// Package files are extracted to a temporary directory, errors of the scan are reported as problems.
+fn lint_settings(args: &ExtractFromProductArgs) -> Result<()> {
+    let settings = load_settings(
+        &args.project_path,
+        args.config.as_deref(),
+        args.shared_settings_dir.as_deref(),
+    )?;
+
+    let payload_path = std::env::temp_dir().join(format!("sancus-lint-{}", std::process::id()));
+    let scan_result = lint_scan(args, &settings, &payload_path);
+    if payload_path.exists()
+        && let Err(error) = std::fs::remove_dir_all(&payload_path)
+    {
+        warn!("Cannot remove the temporary directory {payload_path:?}: {error}");
+    }
+
+    let mut problems = match &scan_result {
+        Ok(scan_report) => settings_lint::lint(
+            &settings,
+            &scan_report.package_root,
+            Some(&scan_report.override_matches),
+        ),
+        Err(_) => settings_lint::lint(&settings, &args.package_path, None),
+    };
+    if let Err(error) = scan_result {
+        problems.insert(
+            0,
+            settings_lint::Problem {
+                severity: settings_lint::Severity::Error,
+                origin: None,
+                message: format!("Cannot scan the package, unused overrides are not reported: {error:#}"),
+            },
+        );
+    }
+    for problem in &problems {
+        println!("{problem}");
+    }
+    let errors = problems
+        .iter()
+        .filter(|x| x.severity == settings_lint::Severity::Error)
+        .count();
+    if errors > 0 {
+        anyhow::bail!(
+            "Found {errors} errors and {} warnings in the settings",
+            problems.len() - errors
+        );
+    }
+    info!("Found no errors and {} warnings in the settings", problems.len());
+    Ok(())
+}
+
+/// Scans the package for the lint, the overrides applied to the additional third party licenses are matched
+/// but the files are not changed.
+fn lint_scan(args: &ExtractFromProductArgs, settings: &settings::Settings, payload_path: &Path) -> Result<ScanReport> {
+    let (package_path, package_file) = package_path(args, payload_path)?;
+    let scanner = build_scanner(
+        args,
+        settings.clone(),
+        package_path,
+        package_file,
+        LicenseDetector::builder().build(),
+    )?;
+    let mut scan_report = scanner.scan()?;
+    let overrides = Overrides::new(settings.overrides.clone());
+    additional_third_party_licenses(args, &overrides)?;
+    for (index, packages) in overrides.matches() {
+        scan_report.override_matches.entry(index).or_default().extend(packages);
+    }
+    Ok(scan_report)
+}
+
 fn logging_init() {
     Logger::try_with_env_or_str("trace")
         .expect("Cannot init logging")
@@ -284,7 +439,6 @@ fn main() -> std::process::ExitCode {
     let args = Cli::parse();
 
     logging_init();
-    LicenseDetector::init();
 
     if let Err(error) = run(&args) {
         error!("{error:?}");
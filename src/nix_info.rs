@@ -0,0 +1,430 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use anyhow::{Context, Result};
+use log::*;
+use serde_json::Value;
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    binary_info::BinaryInfo,
+    file_info::{self, FileInfo},
+    license_detector::{LicenseDetector, LicenseFile},
+    license_info::{self, LicenseInfo},
+    settings,
+};
+
+const NIX_STORE_EXECUTABLE: &str = "nix-store";
+/// Length of the base32 hash prefix of store path names.
+const STORE_HASH_LENGTH: usize = 32;
+const LICENSES_DIR: &str = "share/licenses";
+const DOC_DIR: &str = "share/doc";
+
+/// A path in the Nix store, `/nix/store/<hash>-<name>-<version>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorePath {
+    pub path: PathBuf,
+    pub hash: String,
+    /// Name of the store path including the version
+    pub name: String,
+    pub pname: String,
+    pub version: Option<String>,
+}
+
+/// Splits a derivation name into name and version like `builtins.parseDrvName`, the version starts
+/// at the first `-` not followed by a letter.
+fn parse_drv_name(name: &str) -> (String, Option<String>) {
+    let split = name
+        .match_indices('-')
+        .find(|(i, _)| !name[i + 1..].starts_with(|c: char| c.is_ascii_alphabetic()))
+        .map(|(i, _)| i);
+    match split {
+        Some(i) => (name[..i].to_owned(), Some(name[i + 1..].to_owned())),
+        None => (name.to_owned(), None),
+    }
+}
+
+impl StorePath {
+    /// Finds the store path containing `path`, symbolic links into the store are resolved.
+    pub fn of(path: &Path) -> Option<Self> {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let components = path.components().collect::<Vec<_>>();
+        let index = components.windows(3).position(|window| {
+            window[0] == Component::Normal("nix".as_ref()) && window[1] == Component::Normal("store".as_ref())
+        })?;
+        let entry = components[index + 2].as_os_str().to_string_lossy();
+        let (hash, name) = entry.split_once('-')?;
+        if hash.len() != STORE_HASH_LENGTH || name.is_empty() {
+            return None;
+        }
+        let (pname, version) = parse_drv_name(name);
+        Some(Self {
+            path: components[..index + 3].iter().collect(),
+            hash: hash.to_owned(),
+            name: name.to_owned(),
+            pname,
+            version,
+        })
+    }
+
+    /// Queries the derivation that built the store path, requires `nix-store` and the `.drv` file in the store.
+    fn deriver(&self) -> Option<PathBuf> {
+        let output = Command::new(NIX_STORE_EXECUTABLE)
+            .args(["--query", "--deriver"])
+            .arg(&self.path)
+            .output()
+            .ok()?;
+        let deriver = String::from_utf8(output.stdout).ok()?;
+        let deriver = PathBuf::from(deriver.trim());
+        (output.status.success() && deriver.is_file()).then_some(deriver)
+    }
+}
+
+/// Metadata of a derivation, from `meta` of the package set or the environment of the `.drv` file.
+#[derive(Debug, Clone, Default)]
+pub struct NixMeta {
+    pub pname: Option<String>,
+    pub version: Option<String>,
+    pub license: Option<String>,
+    pub homepage: Option<String>,
+}
+
+/// Converts `meta.license`, a license attribute set, a string or a list of them, to an SPDX expression.
+fn meta_license(license: &Value) -> Option<String> {
+    match license {
+        Value::String(license) => Some(license.clone()),
+        Value::Object(license) => license
+            .get("spdxId")
+            .or_else(|| license.get("shortName"))
+            .and_then(Value::as_str)
+            .map(str::to_owned),
+        // All licenses of the list apply to the package:
+        Value::Array(licenses) => {
+            let ids = licenses.iter().filter_map(meta_license).collect::<Vec<_>>();
+            if ids.is_empty() { None } else { Some(ids.join(" AND ")) }
+        }
+        _ => None,
+    }
+}
+
+impl NixMeta {
+    fn from_json(package: &Value) -> Self {
+        let meta = package.get("meta");
+        Self {
+            pname: package.get("pname").and_then(Value::as_str).map(str::to_owned),
+            version: package.get("version").and_then(Value::as_str).map(str::to_owned),
+            license: meta.and_then(|x| x.get("license")).and_then(meta_license),
+            homepage: meta.and_then(|x| x.get("homepage")).and_then(|x| match x {
+                Value::Array(urls) => urls.first().and_then(Value::as_str).map(str::to_owned),
+                _ => x.as_str().map(str::to_owned),
+            }),
+        }
+    }
+
+    /// Reads `pname` and `version` of the environment of a `.drv` file, derivations with structured
+    /// attributes (`__json`) may carry `meta` as well.
+    fn from_drv(content: &str) -> Self {
+        let mut meta = Self::default();
+        // `Derive(outputs, input derivations, input sources, system, builder, arguments, environment)`
+        let Some(ATerm::Constructor(name, fields)) = ATerm::parse(content) else {
+            return meta;
+        };
+        if name != "Derive" {
+            return meta;
+        }
+        let Some(ATerm::List(environment)) = fields.last() else {
+            return meta;
+        };
+        for pair in environment {
+            let ATerm::Tuple(pair) = pair else {
+                continue;
+            };
+            let (Some(ATerm::String(key)), Some(ATerm::String(value))) = (pair.first(), pair.get(1)) else {
+                continue;
+            };
+            match key.as_str() {
+                "pname" => meta.pname = Some(value.clone()),
+                "version" => meta.version = Some(value.clone()),
+                "__json" => {
+                    if let Ok(json) = serde_json::from_str::<Value>(value) {
+                        let json_meta = Self::from_json(&json);
+                        meta.pname = meta.pname.or(json_meta.pname);
+                        meta.version = meta.version.or(json_meta.version);
+                        meta.license = json_meta.license;
+                        meta.homepage = json_meta.homepage;
+                    }
+                }
+                _ => {}
+            }
+        }
+        meta
+    }
+}
+
+/// Term of the ATerm format of `.drv` files.
+#[derive(Debug)]
+enum ATerm {
+    String(String),
+    List(Vec<ATerm>),
+    Tuple(Vec<ATerm>),
+    Constructor(String, Vec<ATerm>),
+}
+
+impl ATerm {
+    fn parse(content: &str) -> Option<Self> {
+        let mut chars = content.trim().chars().peekable();
+        Self::parse_term(&mut chars)
+    }
+
+    fn parse_term(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<Self> {
+        match chars.peek()? {
+            '"' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next()? {
+                        '"' => return Some(Self::String(value)),
+                        '\\' => match chars.next()? {
+                            'n' => value.push('\n'),
+                            't' => value.push('\t'),
+                            'r' => value.push('\r'),
+                            c => value.push(c),
+                        },
+                        c => value.push(c),
+                    }
+                }
+            }
+            '[' => {
+                chars.next();
+                Some(Self::List(Self::parse_terms(chars, ']')?))
+            }
+            '(' => {
+                chars.next();
+                Some(Self::Tuple(Self::parse_terms(chars, ')')?))
+            }
+            _ => {
+                let mut name = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric()) {
+                    name.push(c);
+                }
+                if name.is_empty() || chars.next()? != '(' {
+                    return None;
+                }
+                Some(Self::Constructor(name, Self::parse_terms(chars, ')')?))
+            }
+        }
+    }
+
+    fn parse_terms(chars: &mut std::iter::Peekable<std::str::Chars>, end: char) -> Option<Vec<Self>> {
+        let mut terms = vec![];
+        if chars.next_if_eq(&end).is_some() {
+            return Some(terms);
+        }
+        loop {
+            terms.push(Self::parse_term(chars)?);
+            match chars.next()? {
+                ',' => {}
+                c if c == end => return Some(terms),
+                _ => return None,
+            }
+        }
+    }
+}
+
+/// Package metadata exported from the package set, e.g. with `nix-env -qa --json --meta --out-path`.
+/// The packages are matched by their output paths or by their name.
+#[derive(Debug, Default)]
+pub struct NixMetadata {
+    packages: Vec<Value>,
+}
+
+impl NixMetadata {
+    pub fn load(file: &Path) -> Result<Self> {
+        let content = fs::read_to_string(file).with_context(|| format!("Cannot read Nix metadata {file:?}"))?;
+        let json: Value =
+            serde_json::from_str(&content).with_context(|| format!("Cannot parse Nix metadata {file:?}"))?;
+        let packages = match json {
+            Value::Array(packages) => packages,
+            Value::Object(packages) => packages.into_iter().map(|(_, package)| package).collect(),
+            _ => anyhow::bail!("Invalid Nix metadata {file:?}, expected an object or a list of packages"),
+        };
+        Ok(Self { packages })
+    }
+
+    fn find(&self, store_path: &StorePath) -> Option<NixMeta> {
+        let path = store_path.path.to_string_lossy();
+        let has_output = |package: &Value| {
+            package.get("outPath").and_then(Value::as_str) == Some(&path)
+                || package
+                    .get("outputs")
+                    .and_then(Value::as_object)
+                    .is_some_and(|outputs| outputs.values().any(|x| x.as_str() == Some(&path)))
+        };
+        self.packages
+            .iter()
+            .find(|package| has_output(package))
+            .or_else(|| {
+                self.packages
+                    .iter()
+                    .find(|package| package.get("name").and_then(Value::as_str) == Some(store_path.name.as_str()))
+            })
+            .map(NixMeta::from_json)
+    }
+}
+
+/// Resolves the needed libraries of the given libraries with their search paths in the Nix store.
+/// Nix sets the `RUNPATH` of binaries to the library directories of their dependencies.
+pub fn resolve_store_libs(libs: &[FileInfo], needed: &[String]) -> Vec<FileInfo> {
+    let mut resolved: Vec<FileInfo> = vec![];
+    for lib in libs {
+        let Ok(Some(binary_info)) = BinaryInfo::read(&lib.path) else {
+            continue;
+        };
+        for name in binary_info.needed_file_names().iter().filter(|x| needed.contains(x)) {
+            let found = binary_info
+                .search_paths
+                .iter()
+                .map(|dir| Path::new(dir).join(name))
+                .find(|path| path.is_file() && StorePath::of(path).is_some());
+            if let Some(path) = found
+                && !resolved.iter().any(|x| x.name == *name)
+            {
+                resolved.push(FileInfo::new(name.clone(), &path));
+            }
+        }
+    }
+    resolved
+}
+
+/// Collects the license files of a store path: `share/licenses`, the license files in `share/doc/*`
+/// and the top level of the store path.
+fn find_store_license_files(store_path: &Path) -> Vec<PathBuf> {
+    let mut license_files = vec![];
+    if let Ok(entries) = fs::read_dir(store_path.join(LICENSES_DIR)) {
+        for dir in entries.flatten().map(|x| x.path()) {
+            if dir.is_file() {
+                license_files.push(dir);
+            } else if let Ok(files) = fs::read_dir(&dir) {
+                license_files.extend(files.flatten().map(|x| x.path()).filter(|x| x.is_file()));
+            }
+        }
+    }
+    if let Ok(entries) = fs::read_dir(store_path.join(DOC_DIR)) {
+        for dir in entries.flatten().map(|x| x.path()).filter(|x| x.is_dir()) {
+            license_files.extend(file_info::find_license_files(&dir).unwrap_or_default());
+        }
+    }
+    license_files.extend(file_info::find_license_files(store_path).unwrap_or_default());
+    license_files.sort();
+    license_files.dedup();
+    license_files
+}
+
+fn store_path_license_info(
+    store_path: &StorePath,
+    meta: NixMeta,
+    lib_info: FileInfo,
+    overrides: &settings::Overrides,
+    detector: &LicenseDetector,
+) -> Result<LicenseInfo> {
+    let package_name = meta.pname.unwrap_or_else(|| store_path.pname.clone());
+    let license_files = find_store_license_files(&store_path.path)
+        .iter()
+        .map(|file| LicenseFile {
+            id: None,
+            file: file.to_string_lossy().into_owned(),
+        })
+        .collect();
+
+    let override_info = settings::Override::find_override(
+        "nix",
+        &package_name,
+        meta.version.as_deref().or(store_path.version.as_deref()),
+        &[],
+        overrides,
+    );
+    let (license, license_files) = license_info::apply_override(override_info, meta.license, license_files);
+
+    let license_expression = license
+        .as_deref()
+        .and_then(|license| license_info::parse_license_expression(&package_name, license));
+    let license_ids = license_info::license_ids(license_expression.as_ref());
+
+    let license_texts = detector.detect_licenses(&package_name, license_ids.as_slice(), license_files.as_slice())?;
+
+    // Without metadata the license is detected from the license files of the store path:
+    let license = license.or_else(|| {
+        let mut ids = license_texts.iter().map(|x| x.id.clone()).collect::<Vec<_>>();
+        ids.dedup();
+        if ids.is_empty() { None } else { Some(ids.join(" AND ")) }
+    });
+    let license = match license {
+        Some(license) => license,
+        None => {
+            warn!(
+                "Missing license for Nix store path '{}', please add an override",
+                store_path.path.to_string_lossy()
+            );
+            "NOASSERTION".to_owned()
+        }
+    };
+
+    Ok(LicenseInfo {
+        lib_info,
+        package_name,
+        license,
+        license_expression,
+        license_texts,
+        version: meta.version.or_else(|| store_path.version.clone()),
+        url: meta.homepage,
+        features: vec![],
+        dependencies: vec![],
+        binaries: vec![],
+    })
+}
+
+/// Collects the license information of the store paths the given libraries are located in.
+/// The metadata is taken from the exported package metadata or the derivation of the store path,
+/// otherwise the name and version of the store path are reported.
+pub fn get_license_info(
+    libs: &[FileInfo],
+    metadata: &NixMetadata,
+    overrides: &settings::Overrides,
+    detector: &LicenseDetector,
+) -> Result<Vec<LicenseInfo>> {
+    let mut licenses: Vec<(PathBuf, LicenseInfo)> = vec![];
+    for lib in libs {
+        let Some(store_path) = StorePath::of(&lib.path) else {
+            continue;
+        };
+        if let Some((_, info)) = licenses.iter_mut().find(|(path, _)| *path == store_path.path) {
+            info.binaries.push(lib.name.clone());
+            continue;
+        }
+        let meta = metadata
+            .find(&store_path)
+            .or_else(|| {
+                let drv = fs::read_to_string(store_path.deriver()?).ok()?;
+                Some(NixMeta::from_drv(&drv))
+            })
+            .unwrap_or_else(|| {
+                debug!("No metadata found for Nix store path {:?}", store_path.path);
+                NixMeta::default()
+            });
+        let mut info = store_path_license_info(&store_path, meta, lib.clone(), overrides, detector)?;
+        info.binaries.push(lib.name.clone());
+        licenses.push((store_path.path, info));
+    }
+    Ok(licenses.into_iter().map(|(_, info)| info).collect())
+}
@@ -0,0 +1,246 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use anyhow::{Context, Result};
+use log::*;
+use serde_json::Value;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    file_info::{self, FileInfo},
+    license_detector::{LicenseDetector, LicenseFile},
+    license_info::{self, LicenseInfo},
+    settings,
+};
+
+const NODE_MODULES_DIR: &str = "node_modules";
+const PACKAGE_JSON_FILE: &str = "package.json";
+const SEE_LICENSE_IN: &str = "SEE LICENSE IN ";
+const UNLICENSED: &str = "UNLICENSED";
+
+/// A package installed into a `node_modules` directory.
+#[derive(Debug, Clone)]
+pub struct NodePackage {
+    pub name: String,
+    pub version: Option<String>,
+    pub package_json: PathBuf,
+    pub license: Option<String>,
+    pub license_files: Vec<PathBuf>,
+    pub url: Option<String>,
+    pub dependencies: Vec<String>,
+}
+
+/// Reads a license entry, either a string or the deprecated `{ "type": ..., "url": ... }` object.
+fn license_of_value(value: &Value) -> Option<&str> {
+    match value {
+        Value::String(license) => Some(license.as_str()),
+        Value::Object(license) => license.get("type").and_then(Value::as_str),
+        _ => None,
+    }
+}
+
+fn url_of_value(value: &Value) -> Option<String> {
+    match value {
+        Value::String(url) => Some(url.clone()),
+        Value::Object(url) => url.get("url").and_then(Value::as_str).map(str::to_owned),
+        _ => None,
+    }
+}
+
+impl NodePackage {
+    pub fn load(package_dir: &Path) -> Result<Self> {
+        let package_json = package_dir.join(PACKAGE_JSON_FILE);
+        let content =
+            fs::read_to_string(&package_json).with_context(|| format!("Cannot read Node package {package_json:?}"))?;
+        let manifest = serde_json::from_str::<Value>(&content)
+            .with_context(|| format!("Cannot parse Node package {package_json:?}"))?;
+
+        let Some(name) = manifest.get("name").and_then(Value::as_str) else {
+            return Err(anyhow::anyhow!("Missing package name in Node package {package_json:?}"));
+        };
+        let mut license_files = file_info::find_license_files(package_dir)?;
+
+        // The `licenses` array is deprecated in favour of a single SPDX expression in `license`:
+        let mut license = match (manifest.get("license"), manifest.get("licenses")) {
+            (Some(license), _) => license_of_value(license).map(str::to_owned),
+            (None, Some(Value::Array(licenses))) => {
+                let ids = licenses.iter().filter_map(license_of_value).collect::<Vec<_>>();
+                if ids.is_empty() { None } else { Some(ids.join(" OR ")) }
+            }
+            _ => None,
+        };
+        if let Some(file) = license.as_deref().and_then(|x| x.strip_prefix(SEE_LICENSE_IN)) {
+            let path = package_dir.join(file.trim());
+            if path.is_file() && !license_files.contains(&path) {
+                license_files.push(path);
+            }
+            license = None;
+        } else if license.as_deref() == Some(UNLICENSED) {
+            warn!("Node package '{name}' is not licensed for use by others");
+            license = None;
+        }
+
+        let dependencies = match manifest.get("dependencies") {
+            Some(Value::Object(dependencies)) => dependencies.keys().cloned().collect(),
+            _ => vec![],
+        };
+
+        Ok(Self {
+            name: name.to_owned(),
+            version: manifest.get("version").and_then(Value::as_str).map(str::to_owned),
+            package_json,
+            license,
+            license_files,
+            url: manifest
+                .get("homepage")
+                .and_then(url_of_value)
+                .or_else(|| manifest.get("repository").and_then(url_of_value)),
+            dependencies,
+        })
+    }
+
+    pub fn license_info(&self, overrides: &settings::Overrides, detector: &LicenseDetector) -> Result<LicenseInfo> {
+        let license_files = self
+            .license_files
+            .iter()
+            .map(|file| LicenseFile {
+                id: None,
+                file: file.to_string_lossy().into_owned(),
+            })
+            .collect();
+        let override_info =
+            settings::Override::find_override("node", &self.name, self.version.as_deref(), &[], overrides);
+        let (license, license_files) = license_info::apply_override(override_info, self.license.clone(), license_files);
+
+        let license_expression = license
+            .as_deref()
+            .and_then(|license| license_info::parse_license_expression(&self.name, license));
+        let license_ids = license_info::license_ids(license_expression.as_ref());
+
+        // Detect license ids of license texts:
+        let license_texts = detector.detect_licenses(&self.name, license_ids.as_slice(), license_files.as_slice())?;
+
+        let license = match license {
+            Some(license) => license,
+            None => {
+                warn!(
+                    "Missing license identifier for Node package '{}', please add an override",
+                    self.name
+                );
+                "NOASSERTION".to_owned()
+            }
+        };
+
+        Ok(LicenseInfo {
+            lib_info: FileInfo::new(PACKAGE_JSON_FILE.to_owned(), &self.package_json),
+            package_name: self.name.clone(),
+            license,
+            license_expression,
+            license_texts,
+            version: self.version.clone(),
+            url: self.url.clone(),
+            features: vec![],
+            dependencies: self.dependencies.clone(),
+            binaries: vec![],
+        })
+    }
+}
+
+/// Finds the top level `node_modules` directories in a directory tree, nested ones are
+/// handled by [`find_node_packages`].
+pub fn find_node_modules(path: &Path, ignore_list: &[String]) -> Result<Vec<PathBuf>> {
+    let mut node_modules = vec![];
+    for entry in fs::read_dir(path).with_context(|| format!("Cannot read directory {path:?}"))? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !path.is_dir() || path.is_symlink() || ignore_list.contains(&name) {
+            continue;
+        }
+        if name == NODE_MODULES_DIR {
+            node_modules.push(path);
+        } else {
+            node_modules.extend(find_node_modules(&path, ignore_list)?);
+        }
+    }
+    Ok(node_modules)
+}
+
+fn find_packages_recurse(node_modules: &Path, packages: &mut Vec<NodePackage>) -> Result<()> {
+    let mut package_dirs = vec![];
+    for entry in fs::read_dir(node_modules).with_context(|| format!("Cannot read directory {node_modules:?}"))? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        // Skip `.bin`, `.cache` and the hidden lock file of npm
+        if !path.is_dir() || name.starts_with('.') {
+            continue;
+        }
+        if name.starts_with('@') {
+            // Scoped packages, e.g. `@types/node`
+            for entry in fs::read_dir(&path).with_context(|| format!("Cannot read directory {path:?}"))? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    package_dirs.push(path);
+                }
+            }
+        } else {
+            package_dirs.push(path);
+        }
+    }
+    package_dirs.sort();
+
+    for package_dir in package_dirs {
+        if !package_dir.join(PACKAGE_JSON_FILE).is_file() {
+            continue;
+        }
+        trace!("Found Node package in {package_dir:?}");
+        // Packages without name, e.g. the `package.json` of a subdirectory declaring its module type, are skipped:
+        match NodePackage::load(&package_dir) {
+            Ok(package) => {
+                if !packages
+                    .iter()
+                    .any(|x| x.name == package.name && x.version == package.version)
+                {
+                    packages.push(package);
+                }
+            }
+            Err(error) => warn!("Skip Node package {package_dir:?}: {error:#}"),
+        }
+        let nested = package_dir.join(NODE_MODULES_DIR);
+        if nested.is_dir() {
+            find_packages_recurse(&nested, packages)?;
+        }
+    }
+    Ok(())
+}
+
+/// Loads all packages of the given `node_modules` directories including nested and scoped packages.
+/// Packages installed multiple times with the same version are reported once.
+pub fn find_node_packages(node_modules: &[PathBuf]) -> Result<Vec<NodePackage>> {
+    let mut packages = vec![];
+    for dir in node_modules {
+        find_packages_recurse(dir, &mut packages)?;
+    }
+    Ok(packages)
+}
+
+pub fn get_license_info(
+    packages: &[NodePackage],
+    overrides: &settings::Overrides,
+    detector: &LicenseDetector,
+) -> Result<Vec<LicenseInfo>> {
+    packages
+        .iter()
+        .map(|package| package.license_info(overrides, detector))
+        .collect()
+}
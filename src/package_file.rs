@@ -0,0 +1,584 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use anyhow::{Context, Result};
+use log::*;
+use std::{
+    fmt, fs,
+    io::Read,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    binary_info::BinaryInfo,
+    bundle::{self, BundleKind},
+    file_info::FileInfo,
+    license_detector::LicenseDetector,
+    license_info::LicenseInfo,
+    rpm_info::PackageDB,
+    settings,
+    system_packages::SystemPackages,
+};
+
+const RPM_LEAD_MAGIC: &[u8] = &[0xed, 0xab, 0xee, 0xdb];
+const RPM_LEAD_SIZE: usize = 96;
+const RPM_HEADER_MAGIC: &[u8] = &[0x8e, 0xad, 0xe8, 0x01];
+const RPM_HEADER_INTRO_SIZE: usize = 16;
+const RPM_INDEX_ENTRY_SIZE: usize = 16;
+const RPMTAG_NAME: u32 = 1000;
+const RPMTAG_VERSION: u32 = 1001;
+const RPMTAG_RELEASE: u32 = 1002;
+const RPMTAG_LICENSE: u32 = 1014;
+const RPMTAG_URL: u32 = 1020;
+const RPMTAG_REQUIREFLAGS: u32 = 1048;
+const RPMTAG_REQUIRENAME: u32 = 1049;
+const RPM_STRING_TYPE: u32 = 6;
+const RPM_STRING_ARRAY_TYPE: u32 = 8;
+const RPM_I18NSTRING_TYPE: u32 = 9;
+const RPM_INT32_TYPE: u32 = 4;
+/// Requirements on features of rpm itself, e.g. `rpmlib(PayloadIsZstd)`
+const RPMSENSE_RPMLIB: u32 = 1 << 24;
+
+const CPIO_NEWC_MAGIC: &[u8] = b"070701";
+const CPIO_CRC_MAGIC: &[u8] = b"070702";
+const CPIO_HEADER_SIZE: usize = 110;
+const CPIO_TRAILER: &str = "TRAILER!!!";
+const S_IFMT: u32 = 0o170000;
+const S_IFREG: u32 = 0o100000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFLNK: u32 = 0o120000;
+
+const AR_MAGIC: &[u8] = b"!<arch>\n";
+const AR_HEADER_SIZE: usize = 60;
+const DEB_CONTROL_MEMBER: &str = "control.tar";
+const DEB_DATA_MEMBER: &str = "data.tar";
+const DEB_CONTROL_FILE: &str = "control";
+const DPKG_STATUS_FILE: &str = "/var/lib/dpkg/status";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageFileKind {
+    Rpm,
+    Deb,
+}
+
+impl fmt::Display for PackageFileKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Rpm => "RPM",
+            Self::Deb => "Debian",
+        })
+    }
+}
+
+/// A `.rpm` or `.deb` package file, its payload is extracted to analyze it like a package directory.
+#[derive(Debug, Clone)]
+pub struct PackageFile {
+    pub kind: PackageFileKind,
+    pub file: PathBuf,
+    pub name: String,
+    pub version: Option<String>,
+    pub license: Option<String>,
+    pub url: Option<String>,
+    /// Capabilities of `Requires` or packages of `Depends` and `Pre-Depends`, alternatives are separated by `|`
+    pub requires: Vec<String>,
+}
+
+/// Decompresses gzip, xz, lzma or zstd compressed data, uncompressed data is returned as it is.
+fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    let mut out = vec![];
+    if data.starts_with(&[0x1f, 0x8b]) {
+        flate2::read::MultiGzDecoder::new(data).read_to_end(&mut out)?;
+    } else if data.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        lzma_rs::xz_decompress(&mut &data[..], &mut out).map_err(|error| anyhow::anyhow!("{error:?}"))?;
+    } else if data.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        ruzstd::decoding::StreamingDecoder::new(data)
+            .map_err(|error| anyhow::anyhow!("{error}"))?
+            .read_to_end(&mut out)?;
+    } else if data.starts_with(&[0x5d, 0x00, 0x00]) {
+        lzma_rs::lzma_decompress(&mut &data[..], &mut out).map_err(|error| anyhow::anyhow!("{error:?}"))?;
+    } else if data.starts_with(b"BZh") {
+        anyhow::bail!("bzip2 compressed payloads are not supported");
+    } else {
+        out = data.to_vec();
+    }
+    Ok(out)
+}
+
+/// Maps an archive member name to a path in `target_dir`, names leaving the directory are rejected.
+fn target_path(target_dir: &Path, name: &str) -> Option<PathBuf> {
+    let mut path = target_dir.to_path_buf();
+    for component in Path::new(name).components() {
+        // Do not follow symbolic links of the archive, they may point outside of the target directory
+        if path.symlink_metadata().is_ok_and(|x| x.file_type().is_symlink()) {
+            return None;
+        }
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir | Component::RootDir => {}
+            Component::ParentDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(path)
+}
+
+fn parse_hex(field: &[u8]) -> Result<u32> {
+    let text = std::str::from_utf8(field)?;
+    u32::from_str_radix(text, 16).with_context(|| format!("Invalid cpio header field '{text}'"))
+}
+
+/// Extracts a `newc` or `crc` cpio archive, device files are skipped.
+fn extract_cpio(data: &[u8], target_dir: &Path) -> Result<()> {
+    let mut offset = 0;
+    while offset + CPIO_HEADER_SIZE <= data.len() {
+        let header = &data[offset..offset + CPIO_HEADER_SIZE];
+        if !header.starts_with(CPIO_NEWC_MAGIC) && !header.starts_with(CPIO_CRC_MAGIC) {
+            anyhow::bail!("Unsupported cpio archive format at offset {offset}");
+        }
+        let field = |index: usize| parse_hex(&header[6 + index * 8..14 + index * 8]);
+        let mode = field(1)?;
+        let file_size = field(6)? as usize;
+        let name_size = field(11)? as usize;
+
+        let name_start = offset + CPIO_HEADER_SIZE;
+        let name = data
+            .get(name_start..name_start + name_size.saturating_sub(1))
+            .context("Truncated cpio archive")?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        let data_start = (name_start + name_size).next_multiple_of(4);
+        let content = data
+            .get(data_start..data_start + file_size)
+            .context("Truncated cpio archive")?;
+        offset = (data_start + file_size).next_multiple_of(4);
+
+        if name == CPIO_TRAILER {
+            break;
+        }
+        let Some(path) = target_path(target_dir, &name) else {
+            warn!("Skip cpio entry '{name}', it is outside of the target directory");
+            continue;
+        };
+        match mode & S_IFMT {
+            S_IFDIR => fs::create_dir_all(&path)?,
+            S_IFREG => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&path, content).with_context(|| format!("Cannot write {path:?}"))?;
+            }
+            S_IFLNK => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                #[cfg(unix)]
+                {
+                    let _ = fs::remove_file(&path);
+                    std::os::unix::fs::symlink(String::from_utf8_lossy(content).as_ref(), &path)
+                        .with_context(|| format!("Cannot create symbolic link {path:?}"))?;
+                }
+            }
+            _ => trace!("Skip special file '{name}'"),
+        }
+    }
+    Ok(())
+}
+
+struct RpmHeader<'a> {
+    index: &'a [u8],
+    store: &'a [u8],
+}
+
+impl<'a> RpmHeader<'a> {
+    /// Parses a header structure, returns the header and its size.
+    fn parse(data: &'a [u8]) -> Result<(Self, usize)> {
+        if !data.starts_with(RPM_HEADER_MAGIC) || data.len() < RPM_HEADER_INTRO_SIZE {
+            anyhow::bail!("Invalid RPM header");
+        }
+        let read_u32 = |offset: usize| u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+        let index_count = read_u32(8);
+        let store_size = read_u32(12);
+        let index_end = RPM_HEADER_INTRO_SIZE + index_count * RPM_INDEX_ENTRY_SIZE;
+        let size = index_end + store_size;
+        if data.len() < size {
+            anyhow::bail!("Truncated RPM header");
+        }
+        Ok((
+            Self {
+                index: &data[RPM_HEADER_INTRO_SIZE..index_end],
+                store: &data[index_end..size],
+            },
+            size,
+        ))
+    }
+
+    fn entry(&self, tag: u32) -> Option<(u32, usize, usize)> {
+        self.index.chunks_exact(RPM_INDEX_ENTRY_SIZE).find_map(|entry| {
+            let value = |i: usize| u32::from_be_bytes(entry[i * 4..i * 4 + 4].try_into().unwrap());
+            (value(0) == tag).then(|| (value(1), value(2) as usize, value(3) as usize))
+        })
+    }
+
+    fn strings(&self, tag: u32) -> Vec<String> {
+        let Some((kind, offset, count)) = self.entry(tag) else {
+            return vec![];
+        };
+        if ![RPM_STRING_TYPE, RPM_STRING_ARRAY_TYPE, RPM_I18NSTRING_TYPE].contains(&kind) {
+            return vec![];
+        }
+        self.store
+            .get(offset..)
+            .unwrap_or_default()
+            .split(|&x| x == 0)
+            .take(count)
+            .map(|x| String::from_utf8_lossy(x).into_owned())
+            .collect()
+    }
+
+    fn string(&self, tag: u32) -> Option<String> {
+        self.strings(tag).into_iter().next().filter(|x| !x.is_empty())
+    }
+
+    fn ints(&self, tag: u32) -> Vec<u32> {
+        match self.entry(tag) {
+            Some((RPM_INT32_TYPE, offset, count)) => self
+                .store
+                .get(offset..offset + count * 4)
+                .unwrap_or_default()
+                .chunks_exact(4)
+                .map(|x| u32::from_be_bytes(x.try_into().unwrap()))
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+fn parse_control(content: &str) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = vec![];
+    for line in content.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = fields.last_mut() {
+                value.push('\n');
+                value.push_str(line.trim());
+            }
+        } else if let Some((key, value)) = line.split_once(':') {
+            fields.push((key.trim().to_owned(), value.trim().to_owned()));
+        } else if line.is_empty() {
+            fields.push((String::new(), String::new()));
+        }
+    }
+    fields
+}
+
+/// Splits a `Depends` field into its packages, version constraints and architecture qualifiers are removed.
+fn parse_depends(depends: &str) -> Vec<String> {
+    depends
+        .split(',')
+        .map(|dependency| {
+            dependency
+                .split('|')
+                .map(|alternative| {
+                    let name = alternative.split(['(', '[', '<']).next().unwrap_or_default().trim();
+                    name.split(':').next().unwrap_or(name).to_owned()
+                })
+                .filter(|x| !x.is_empty())
+                .collect::<Vec<_>>()
+                .join("|")
+        })
+        .filter(|x| !x.is_empty())
+        .collect()
+}
+
+/// Reads the installed packages of the dpkg database.
+fn dpkg_installed_packages() -> Result<Vec<Vec<(String, String)>>> {
+    let content = fs::read_to_string(DPKG_STATUS_FILE)
+        .with_context(|| format!("Cannot read dpkg database {DPKG_STATUS_FILE}"))?;
+    Ok(parse_control(&content)
+        .split(|(key, _)| key.is_empty())
+        .filter(|paragraph| {
+            paragraph
+                .iter()
+                .any(|(key, value)| key == "Status" && value.ends_with(" installed"))
+        })
+        .map(|paragraph| paragraph.to_vec())
+        .collect())
+}
+
+fn field<'a>(fields: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    fields.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+}
+
+impl PackageFile {
+    pub fn detect(file: &Path) -> Option<PackageFileKind> {
+        let mut magic = [0u8; 8];
+        fs::File::open(file).ok()?.read_exact(&mut magic).ok()?;
+        if magic.starts_with(RPM_LEAD_MAGIC) {
+            Some(PackageFileKind::Rpm)
+        } else if magic == AR_MAGIC {
+            Some(PackageFileKind::Deb)
+        } else {
+            None
+        }
+    }
+
+    /// Reads the metadata of a package file and extracts its payload to `target_dir`.
+    pub fn extract(file: &Path, target_dir: &Path) -> Result<Self> {
+        let data = fs::read(file).with_context(|| format!("Cannot read package file {file:?}"))?;
+        if target_dir.exists() {
+            fs::remove_dir_all(target_dir).with_context(|| format!("Cannot clean directory {target_dir:?}"))?;
+        }
+        fs::create_dir_all(target_dir).with_context(|| format!("Cannot create directory {target_dir:?}"))?;
+
+        let package = match Self::detect(file) {
+            Some(PackageFileKind::Rpm) => Self::extract_rpm(file, &data, target_dir),
+            Some(PackageFileKind::Deb) => Self::extract_deb(file, &data, target_dir),
+            None => Err(anyhow::anyhow!("Unknown package file format")),
+        }
+        .with_context(|| format!("Cannot extract package file {file:?}"))?;
+        info!(
+            "Extracted {} package '{}' to: {target_dir:?}",
+            package.kind, package.name
+        );
+        Ok(package)
+    }
+
+    fn extract_rpm(file: &Path, data: &[u8], target_dir: &Path) -> Result<Self> {
+        if data.len() < RPM_LEAD_SIZE {
+            anyhow::bail!("Truncated RPM lead");
+        }
+        // The signature header is padded to a multiple of 8 bytes:
+        let (_, signature_size) = RpmHeader::parse(&data[RPM_LEAD_SIZE..])?;
+        let header_start = RPM_LEAD_SIZE + signature_size.next_multiple_of(8);
+        let (header, header_size) = RpmHeader::parse(data.get(header_start..).unwrap_or_default())?;
+
+        let payload = decompress(&data[header_start + header_size..])?;
+        extract_cpio(&payload, target_dir)?;
+
+        let flags = header.ints(RPMTAG_REQUIREFLAGS);
+        let mut requires: Vec<String> = vec![];
+        for (index, require) in header.strings(RPMTAG_REQUIRENAME).into_iter().enumerate() {
+            let is_rpmlib = flags.get(index).is_some_and(|x| x & RPMSENSE_RPMLIB != 0);
+            if !is_rpmlib && !require.starts_with("rpmlib(") && !requires.contains(&require) {
+                requires.push(require);
+            }
+        }
+        let version = match (header.string(RPMTAG_VERSION), header.string(RPMTAG_RELEASE)) {
+            (Some(version), Some(release)) => Some(format!("{version}-{release}")),
+            (version, _) => version,
+        };
+
+        Ok(Self {
+            kind: PackageFileKind::Rpm,
+            file: file.to_path_buf(),
+            name: header.string(RPMTAG_NAME).context("Missing RPM package name")?,
+            version,
+            license: header.string(RPMTAG_LICENSE),
+            url: header.string(RPMTAG_URL),
+            requires,
+        })
+    }
+
+    fn extract_deb(file: &Path, data: &[u8], target_dir: &Path) -> Result<Self> {
+        let mut control = None;
+        let mut offset = AR_MAGIC.len();
+        while offset + AR_HEADER_SIZE <= data.len() {
+            let header = &data[offset..offset + AR_HEADER_SIZE];
+            let name = String::from_utf8_lossy(&header[0..16])
+                .trim_end()
+                .trim_end_matches('/')
+                .to_owned();
+            let size = std::str::from_utf8(&header[48..58])?
+                .trim()
+                .parse::<usize>()
+                .context("Invalid ar member size")?;
+            let member = data
+                .get(offset + AR_HEADER_SIZE..offset + AR_HEADER_SIZE + size)
+                .context("Truncated ar archive")?;
+            offset = (offset + AR_HEADER_SIZE + size).next_multiple_of(2);
+
+            if name.starts_with(DEB_CONTROL_MEMBER) {
+                let tar_data = decompress(member)?;
+                let mut archive = tar::Archive::new(tar_data.as_slice());
+                for entry in archive.entries()? {
+                    let mut entry = entry?;
+                    if entry.path()?.file_name().is_some_and(|x| x == DEB_CONTROL_FILE) {
+                        let mut content = String::new();
+                        entry.read_to_string(&mut content)?;
+                        control = Some(content);
+                    }
+                }
+            } else if name.starts_with(DEB_DATA_MEMBER) {
+                let tar_data = decompress(member)?;
+                tar::Archive::new(tar_data.as_slice())
+                    .unpack(target_dir)
+                    .with_context(|| format!("Cannot extract the data archive to {target_dir:?}"))?;
+            }
+        }
+
+        let control = control.context("Missing control file")?;
+        let fields = parse_control(&control);
+        let mut requires = vec![];
+        for key in ["Pre-Depends", "Depends"] {
+            for dependency in parse_depends(field(&fields, key).unwrap_or_default()) {
+                if !requires.contains(&dependency) {
+                    requires.push(dependency);
+                }
+            }
+        }
+
+        Ok(Self {
+            kind: PackageFileKind::Deb,
+            file: file.to_path_buf(),
+            name: field(&fields, "Package")
+                .context("Missing Debian package name")?
+                .to_owned(),
+            version: field(&fields, "Version").map(str::to_owned),
+            license: None,
+            url: field(&fields, "Homepage").map(str::to_owned),
+            requires,
+        })
+    }
+
+    /// Collects the license information of the system packages the package depends on, the system
+    /// libraries found in the payload are attributed to them. The packages of rpm package files are looked up
+    /// in the rpm database of the system packages, dependencies that are not installed are reported as warning.
+    pub fn license_info(
+        &self,
+        libs: &[FileInfo],
+        system_packages: Option<&SystemPackages>,
+        overrides: &settings::Overrides,
+        detector: &LicenseDetector,
+    ) -> Result<Vec<LicenseInfo>> {
+        match self.kind {
+            PackageFileKind::Rpm => {
+                let Some(system_packages) = system_packages else {
+                    anyhow::bail!(
+                        "Cannot query the packages required by '{}', system packages are only supported for Linux targets",
+                        self.name
+                    );
+                };
+                let rpm_packages = system_packages.rpm_packages()?;
+                let mut licenses = self.rpm_dependencies_license_info(rpm_packages, overrides, detector)?;
+                for lib in libs {
+                    let package = rpm_packages.query_by_library(lib)?;
+                    match licenses.iter_mut().find(|x| x.package_name == package.name()) {
+                        Some(info) => info.binaries.push(lib.name.clone()),
+                        None => licenses.push(package.license_info(lib.clone(), overrides, detector)?),
+                    }
+                }
+                Ok(licenses)
+            }
+            PackageFileKind::Deb => {
+                let mut licenses = self.deb_dependencies_license_info(overrides, detector)?;
+                for lib in libs {
+                    let soname = BinaryInfo::read(&lib.path)
+                        .ok()
+                        .flatten()
+                        .and_then(|x| x.name)
+                        .unwrap_or_else(|| lib.name.clone());
+                    let candidates = bundle::debian_package_names(&soname);
+                    match licenses.iter_mut().find(|x| candidates.contains(&x.package_name)) {
+                        Some(info) => info.binaries.push(lib.name.clone()),
+                        None => licenses.push(bundle::unattributed_license_info(
+                            lib,
+                            BundleKind::Directory,
+                            overrides,
+                            detector,
+                        )?),
+                    }
+                }
+                Ok(licenses)
+            }
+        }
+    }
+
+    fn rpm_dependencies_license_info(
+        &self,
+        rpm_packages: &PackageDB,
+        overrides: &settings::Overrides,
+        detector: &LicenseDetector,
+    ) -> Result<Vec<LicenseInfo>> {
+        let mut licenses: Vec<LicenseInfo> = vec![];
+        for require in &self.requires {
+            // Capabilities are package names, files or sonames like `libz.so.1()(64bit)`, sonames of 32-bit
+            // libraries have no `(64bit)` suffix:
+            let soname = require.split('(').next().unwrap_or(require);
+            let package = if require.starts_with('/') {
+                rpm_packages.query_by_file(require)
+            } else if soname.contains(".so") && !soname.contains(char::is_whitespace) {
+                rpm_packages
+                    .query_by_library_name(soname, Some(require.ends_with("(64bit)")))
+                    .map(Some)
+            } else if require.contains('(') {
+                debug!("Skip requirement '{require}' of package '{}'", self.name);
+                continue;
+            } else {
+                let name = require.split_whitespace().next().unwrap_or(require);
+                Ok(rpm_packages.query_by_name(name))
+            };
+            let package = match package {
+                Ok(Some(package)) => package,
+                Ok(None) => {
+                    warn!(
+                        "Cannot find installed package providing '{require}' required by '{}'",
+                        self.name
+                    );
+                    continue;
+                }
+                Err(error) => {
+                    warn!("{error}");
+                    continue;
+                }
+            };
+            if licenses.iter().any(|x| x.package_name == package.name()) {
+                continue;
+            }
+            let info = package.license_info(FileInfo::new(require.clone(), &self.file), overrides, detector)?;
+            licenses.push(info);
+        }
+        Ok(licenses)
+    }
+
+    fn deb_dependencies_license_info(
+        &self,
+        overrides: &settings::Overrides,
+        detector: &LicenseDetector,
+    ) -> Result<Vec<LicenseInfo>> {
+        let installed = dpkg_installed_packages()?;
+        let mut licenses: Vec<LicenseInfo> = vec![];
+        for dependency in &self.requires {
+            // The first installed alternative satisfies the dependency:
+            let package = dependency.split('|').find_map(|name| {
+                installed.iter().find(|fields| {
+                    field(fields, "Package") == Some(name)
+                        || field(fields, "Provides").is_some_and(|x| parse_depends(x).iter().any(|x| x == name))
+                })
+            });
+            let Some(package) = package else {
+                warn!(
+                    "Cannot find installed package providing '{dependency}' required by '{}'",
+                    self.name
+                );
+                continue;
+            };
+            let name = field(package, "Package").unwrap_or_default();
+            if licenses.iter().any(|x| x.package_name == name) {
+                continue;
+            }
+            licenses.push(bundle::debian_package_license_info(
+                Path::new("/"),
+                name,
+                field(package, "Version").map(str::to_owned),
+                field(package, "Homepage").map(str::to_owned),
+                overrides,
+                detector,
+            )?);
+        }
+        Ok(licenses)
+    }
+}
@@ -0,0 +1,97 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use log::*;
+use std::{fmt, sync::Arc};
+
+use crate::{
+    error::Result, file_info::FileInfo, license_detector::LicenseDetector, license_info::LicenseInfo, settings,
+};
+
+/// A source of packages the files of a package are attributed to, e.g. the vcpkg installation, the rpm
+/// database or an in-house artifact repository.
+pub trait PackageSource: fmt::Debug {
+    /// Name of the source, overrides with a `source` only apply to the packages of the source with this name.
+    fn name(&self) -> &str;
+
+    /// Name of the package owning the file, `None` if no package of this source owns it.
+    fn package_of_file(&self, file: &FileInfo) -> Result<Option<String>>;
+
+    /// License information of a package of this source, `file` is the first file attributed to the package.
+    fn license_info(
+        &self,
+        package: &str,
+        file: &FileInfo,
+        overrides: &settings::Overrides,
+        detector: &LicenseDetector,
+    ) -> Result<LicenseInfo>;
+}
+
+/// Result of the attribution of files to the packages of a [`PackageSources`] chain.
+#[derive(Debug, Default)]
+pub struct Attribution {
+    /// License information of the packages owning files, one entry per source in the order of the chain
+    pub licenses: Vec<Vec<LicenseInfo>>,
+    /// Files no source owns
+    pub unattributed: Vec<FileInfo>,
+}
+
+/// Ordered chain of package sources, each file is attributed to the first source owning it.
+#[derive(Debug, Clone, Default)]
+pub struct PackageSources {
+    sources: Vec<Arc<dyn PackageSource>>,
+}
+
+impl PackageSources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a source to the chain, it is asked after all sources pushed before.
+    pub fn push(&mut self, source: Arc<dyn PackageSource>) {
+        self.sources.push(source);
+    }
+
+    pub fn sources(&self) -> &[Arc<dyn PackageSource>] {
+        &self.sources
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Attributes the files to the packages of the sources, the license information of a package is
+    /// collected once for the first file it owns.
+    pub fn attribute(
+        &self,
+        files: &[FileInfo],
+        overrides: &settings::Overrides,
+        detector: &LicenseDetector,
+    ) -> Result<Attribution> {
+        let mut attribution = Attribution {
+            licenses: vec![vec![]; self.sources.len()],
+            unattributed: vec![],
+        };
+        'files: for file in files {
+            for (source, licenses) in self.sources.iter().zip(attribution.licenses.iter_mut()) {
+                let Some(package) = source.package_of_file(file)? else {
+                    continue;
+                };
+                trace!("Attribute '{}' to {} package '{package}'", file.name, source.name());
+                if !licenses.iter().any(|info| info.package_name == package) {
+                    licenses.push(source.license_info(&package, file, overrides, detector)?);
+                }
+                continue 'files;
+            }
+            attribution.unattributed.push(file.clone());
+        }
+        Ok(attribution)
+    }
+}
@@ -0,0 +1,89 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use anyhow::{Context, Result};
+use log::*;
+use std::{fs, path::Path};
+
+use crate::system_packages::SystemPackage;
+
+/// Database of the installed packages of Arch Linux, relative to the root directory. It contains a
+/// directory `<name>-<version>` per package with the `desc` and `files` files.
+pub const LOCAL_DB_DIR: &str = "var/lib/pacman/local";
+const DESC_FILE: &str = "desc";
+const FILES_FILE: &str = "files";
+
+/// Parses the sections of a pacman database file, a section starts with a `%NAME%` line followed by
+/// its values and ends with an empty line.
+fn parse_sections(content: &str) -> Vec<(&str, Vec<&str>)> {
+    let mut sections: Vec<(&str, Vec<&str>)> = vec![];
+    for line in content.lines() {
+        if let Some(name) = line.strip_prefix('%').and_then(|x| x.strip_suffix('%')) {
+            sections.push((name, vec![]));
+        } else if !line.is_empty()
+            && let Some((_, values)) = sections.last_mut()
+        {
+            values.push(line);
+        }
+    }
+    sections
+}
+
+fn section<'a>(sections: &[(&str, Vec<&'a str>)], name: &str) -> Vec<&'a str> {
+    sections
+        .iter()
+        .find(|(x, _)| *x == name)
+        .map(|(_, values)| values.clone())
+        .unwrap_or_default()
+}
+
+/// Parses the `desc` and `files` files of a package, directories are not part of the package files.
+pub fn parse_package(desc: &str, files: &str) -> SystemPackage {
+    let desc = parse_sections(desc);
+    let files = parse_sections(files);
+    // Packages list several licenses that all apply, e.g. `GPL-2.0-or-later` and `LGPL-2.1-or-later`:
+    let licenses = section(&desc, "LICENSE");
+    SystemPackage {
+        name: section(&desc, "NAME").first().copied().unwrap_or_default().to_owned(),
+        version: section(&desc, "VERSION").first().map(|x| (*x).to_owned()),
+        license: (!licenses.is_empty()).then(|| licenses.join(" AND ")),
+        url: section(&desc, "URL").first().map(|x| (*x).to_owned()),
+        files: section(&files, "FILES")
+            .into_iter()
+            .filter(|file| !file.ends_with('/'))
+            .map(str::to_owned)
+            .collect(),
+    }
+}
+
+/// Reads the installed packages of the pacman database in `root`.
+pub fn read_installed_packages(root: &Path) -> Result<Vec<SystemPackage>> {
+    let db_dir = root.join(LOCAL_DB_DIR);
+    let mut packages = vec![];
+    for entry in fs::read_dir(&db_dir).with_context(|| format!("Cannot read pacman database {db_dir:?}"))? {
+        let package_dir = entry?.path();
+        let desc_file = package_dir.join(DESC_FILE);
+        if !desc_file.is_file() {
+            continue;
+        }
+        let desc = fs::read_to_string(&desc_file).with_context(|| format!("Cannot read {desc_file:?}"))?;
+        // The file list is missing for packages installed with `--dbonly`:
+        let files = fs::read_to_string(package_dir.join(FILES_FILE)).unwrap_or_else(|_| {
+            debug!("Missing file list of package {package_dir:?}");
+            String::new()
+        });
+        let package = parse_package(&desc, &files);
+        if !package.name.is_empty() {
+            packages.push(package);
+        }
+    }
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(packages)
+}
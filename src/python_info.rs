@@ -0,0 +1,353 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use anyhow::{Context, Result};
+use log::*;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    file_info::{self, FileInfo},
+    license_detector::{LicenseDetector, LicenseFile},
+    license_info::{self, LicenseInfo},
+    settings,
+};
+
+const SITE_PACKAGES_DIRS: [&str; 2] = ["site-packages", "dist-packages"];
+const DIST_INFO_SUFFIX: &str = ".dist-info";
+const EGG_INFO_SUFFIX: &str = ".egg-info";
+const DIST_INFO_METADATA_FILE: &str = "METADATA";
+const EGG_INFO_METADATA_FILE: &str = "PKG-INFO";
+const RECORD_FILE: &str = "RECORD";
+const LICENSES_DIR: &str = "licenses";
+const EXTENSION_MODULE_SUFFIXES: [&str; 3] = [".so", ".pyd", ".dylib"];
+const LICENSE_CLASSIFIER_PREFIX: &str = "License :: ";
+
+/// SPDX ids of the trove license classifiers that identify a single license.
+const LICENSE_CLASSIFIERS: [(&str, &str); 24] = [
+    ("OSI Approved :: Apache Software License", "Apache-2.0"),
+    ("OSI Approved :: MIT License", "MIT"),
+    ("OSI Approved :: MIT No Attribution License (MIT-0)", "MIT-0"),
+    ("OSI Approved :: ISC License (ISCL)", "ISC"),
+    ("OSI Approved :: Python Software Foundation License", "PSF-2.0"),
+    ("OSI Approved :: Mozilla Public License 2.0 (MPL 2.0)", "MPL-2.0"),
+    ("OSI Approved :: The Unlicense (Unlicense)", "Unlicense"),
+    ("OSI Approved :: zlib/libpng License", "Zlib"),
+    ("OSI Approved :: Boost Software License 1.0 (BSL-1.0)", "BSL-1.0"),
+    ("OSI Approved :: Artistic License", "Artistic-2.0"),
+    ("OSI Approved :: Eclipse Public License 2.0 (EPL-2.0)", "EPL-2.0"),
+    (
+        "OSI Approved :: European Union Public Licence 1.2 (EUPL 1.2)",
+        "EUPL-1.2",
+    ),
+    ("OSI Approved :: GNU Affero General Public License v3", "AGPL-3.0-only"),
+    (
+        "OSI Approved :: GNU Affero General Public License v3 or later (AGPLv3+)",
+        "AGPL-3.0-or-later",
+    ),
+    ("OSI Approved :: GNU General Public License v2 (GPLv2)", "GPL-2.0-only"),
+    (
+        "OSI Approved :: GNU General Public License v2 or later (GPLv2+)",
+        "GPL-2.0-or-later",
+    ),
+    ("OSI Approved :: GNU General Public License v3 (GPLv3)", "GPL-3.0-only"),
+    (
+        "OSI Approved :: GNU General Public License v3 or later (GPLv3+)",
+        "GPL-3.0-or-later",
+    ),
+    (
+        "OSI Approved :: GNU Lesser General Public License v2 (LGPLv2)",
+        "LGPL-2.0-only",
+    ),
+    (
+        "OSI Approved :: GNU Lesser General Public License v2 or later (LGPLv2+)",
+        "LGPL-2.0-or-later",
+    ),
+    (
+        "OSI Approved :: GNU Lesser General Public License v3 (LGPLv3)",
+        "LGPL-3.0-only",
+    ),
+    (
+        "OSI Approved :: GNU Lesser General Public License v3 or later (LGPLv3+)",
+        "LGPL-3.0-or-later",
+    ),
+    ("OSI Approved :: Universal Permissive License (UPL)", "UPL-1.0"),
+    ("CC0 1.0 Universal (CC0 1.0) Public Domain Dedication", "CC0-1.0"),
+];
+
+/// An installed Python distribution (`*.dist-info` or `*.egg-info` directory).
+#[derive(Debug, Clone)]
+pub struct PythonPackage {
+    pub name: String,
+    pub version: Option<String>,
+    pub metadata_file: PathBuf,
+    pub license: Option<String>,
+    pub license_files: Vec<PathBuf>,
+    pub url: Option<String>,
+    /// Native extension modules and bundled shared libraries installed by the distribution
+    pub extension_modules: Vec<FileInfo>,
+}
+
+/// Parses the RFC 822 style header of a `METADATA` or `PKG-INFO` file.
+fn parse_metadata(content: &str) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = vec![];
+    for line in content.lines() {
+        if line.is_empty() {
+            // The description body follows the header
+            break;
+        }
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = fields.last_mut() {
+                value.push('\n');
+                value.push_str(line.trim());
+            }
+        } else if let Some((key, value)) = line.split_once(':') {
+            fields.push((key.trim().to_owned(), value.trim().to_owned()));
+        }
+    }
+    fields
+}
+
+fn license_of_classifiers(classifiers: &[&str]) -> Option<String> {
+    let ids = classifiers
+        .iter()
+        .filter_map(|classifier| classifier.strip_prefix(LICENSE_CLASSIFIER_PREFIX))
+        .filter_map(|classifier| {
+            LICENSE_CLASSIFIERS
+                .iter()
+                .find(|(name, _)| *name == classifier)
+                .map(|(_, id)| *id)
+        })
+        .collect::<Vec<_>>();
+    if ids.is_empty() { None } else { Some(ids.join(" OR ")) }
+}
+
+/// The `License` field is free text, it is only used if it is a valid SPDX expression.
+fn license_of_license_field(license: &str) -> Option<String> {
+    if license.is_empty() || license.contains('\n') {
+        return None;
+    }
+    spdx::Expression::parse_mode(license, spdx::ParseMode::LAX)
+        .ok()
+        .map(|_| license.to_owned())
+}
+
+fn extension_modules_of_record(record_file: &Path, site_packages: &Path) -> Vec<FileInfo> {
+    let Ok(content) = fs::read_to_string(record_file) else {
+        return vec![];
+    };
+    content
+        .lines()
+        .filter_map(|line| {
+            let path = if let Some(quoted) = line.strip_prefix('"') {
+                quoted.split('"').next()
+            } else {
+                line.split(',').next()
+            }?;
+            let name = Path::new(path).file_name()?.to_string_lossy().into_owned();
+            let is_extension_module = EXTENSION_MODULE_SUFFIXES
+                .iter()
+                .any(|suffix| name.ends_with(suffix) || name.contains(&format!("{suffix}.")));
+            is_extension_module.then(|| FileInfo::new(name, &site_packages.join(path)))
+        })
+        .collect()
+}
+
+fn collect_files_recurse(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir).with_context(|| format!("Cannot read directory {dir:?}"))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files_recurse(&path, files)?;
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+impl PythonPackage {
+    pub fn load(info_dir: &Path) -> Result<Self> {
+        let site_packages = info_dir.parent().unwrap_or(info_dir);
+        let is_dist_info = info_dir.to_string_lossy().ends_with(DIST_INFO_SUFFIX);
+        let metadata_file = info_dir.join(if is_dist_info {
+            DIST_INFO_METADATA_FILE
+        } else {
+            EGG_INFO_METADATA_FILE
+        });
+        let content = fs::read_to_string(&metadata_file)
+            .with_context(|| format!("Cannot read Python package metadata {metadata_file:?}"))?;
+        let fields = parse_metadata(&content);
+        let field = |key: &str| {
+            fields
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v.as_str())
+        };
+        let fields_of = |key: &str| {
+            fields
+                .iter()
+                .filter(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        let Some(name) = field("Name") else {
+            return Err(anyhow::anyhow!(
+                "Missing package name in Python package metadata {metadata_file:?}"
+            ));
+        };
+        let license = field("License-Expression")
+            .map(str::to_owned)
+            .or_else(|| field("License").and_then(license_of_license_field))
+            .or_else(|| license_of_classifiers(&fields_of("Classifier")));
+        let url = field("Home-page").map(str::to_owned).or_else(|| {
+            fields_of("Project-URL")
+                .iter()
+                .find_map(|x| x.split_once(',').map(|(_, url)| url.trim().to_owned()))
+        });
+
+        // License files of PEP 639 are located in the `licenses` directory, older tools put them into the info directory:
+        let mut license_files = vec![];
+        let licenses_dir = info_dir.join(LICENSES_DIR);
+        if licenses_dir.is_dir() {
+            collect_files_recurse(&licenses_dir, &mut license_files)?;
+        }
+        for license_file in fields_of("License-File") {
+            let path = info_dir.join(license_file);
+            if path.is_file() && !license_files.contains(&path) {
+                license_files.push(path);
+            }
+        }
+        for path in file_info::find_license_files(info_dir)? {
+            if !license_files.contains(&path) {
+                license_files.push(path);
+            }
+        }
+
+        Ok(Self {
+            name: name.to_owned(),
+            version: field("Version").map(str::to_owned),
+            metadata_file,
+            license,
+            license_files,
+            url,
+            extension_modules: extension_modules_of_record(&info_dir.join(RECORD_FILE), site_packages),
+        })
+    }
+
+    pub fn license_info(&self, overrides: &settings::Overrides, detector: &LicenseDetector) -> Result<LicenseInfo> {
+        let license_files = self
+            .license_files
+            .iter()
+            .map(|file| LicenseFile {
+                id: None,
+                file: file.to_string_lossy().into_owned(),
+            })
+            .collect();
+        let override_info =
+            settings::Override::find_override("python", &self.name, self.version.as_deref(), &[], overrides);
+        let (license, license_files) = license_info::apply_override(override_info, self.license.clone(), license_files);
+
+        let license_expression = license
+            .as_deref()
+            .and_then(|license| license_info::parse_license_expression(&self.name, license));
+        let license_ids = license_info::license_ids(license_expression.as_ref());
+
+        // Detect license ids of license texts:
+        let license_texts = detector.detect_licenses(&self.name, license_ids.as_slice(), license_files.as_slice())?;
+
+        let license = match license {
+            Some(license) => license,
+            None => {
+                warn!(
+                    "Missing license identifier for Python package '{}', please add an override",
+                    self.name
+                );
+                "NOASSERTION".to_owned()
+            }
+        };
+
+        // The metadata file is `METADATA` of wheels or `PKG-INFO` of eggs:
+        let metadata_name = self
+            .metadata_file
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(LicenseInfo {
+            lib_info: FileInfo::new(metadata_name, &self.metadata_file),
+            package_name: self.name.clone(),
+            license,
+            license_expression,
+            license_texts,
+            version: self.version.clone(),
+            url: self.url.clone(),
+            features: vec![],
+            dependencies: vec![],
+            binaries: self.extension_modules.iter().map(|x| x.name.clone()).collect(),
+        })
+    }
+}
+
+/// Finds the `site-packages` and `dist-packages` directories of all Python installations in a directory tree.
+pub fn find_site_packages(path: &Path, ignore_list: &[String]) -> Result<Vec<PathBuf>> {
+    let mut site_packages = vec![];
+    for entry in fs::read_dir(path).with_context(|| format!("Cannot read directory {path:?}"))? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !path.is_dir() || path.is_symlink() || ignore_list.contains(&name) {
+            continue;
+        }
+        if SITE_PACKAGES_DIRS.contains(&name.as_str()) {
+            site_packages.push(path);
+        } else {
+            site_packages.extend(find_site_packages(&path, ignore_list)?);
+        }
+    }
+    Ok(site_packages)
+}
+
+/// Loads all Python distributions installed into the given `site-packages` directories, distributions with
+/// unreadable metadata are skipped with a warning.
+pub fn find_python_packages(site_packages: &[PathBuf]) -> Result<Vec<PythonPackage>> {
+    let mut packages = vec![];
+    for dir in site_packages {
+        let mut info_dirs = fs::read_dir(dir)
+            .with_context(|| format!("Cannot read directory {dir:?}"))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                let name = path.to_string_lossy();
+                path.is_dir() && (name.ends_with(DIST_INFO_SUFFIX) || name.ends_with(EGG_INFO_SUFFIX))
+            })
+            .collect::<Vec<_>>();
+        info_dirs.sort();
+        for info_dir in info_dirs {
+            trace!("Found Python package metadata in {info_dir:?}");
+            match PythonPackage::load(&info_dir) {
+                Ok(package) => packages.push(package),
+                Err(error) => warn!("Skip Python package {info_dir:?}: {error:#}"),
+            }
+        }
+    }
+    Ok(packages)
+}
+
+pub fn get_license_info(
+    packages: &[PythonPackage],
+    overrides: &settings::Overrides,
+    detector: &LicenseDetector,
+) -> Result<Vec<LicenseInfo>> {
+    packages
+        .iter()
+        .map(|package| package.license_info(overrides, detector))
+        .collect()
+}
@@ -8,52 +8,106 @@
 //
 // SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
 
-use anyhow::{Context, Result};
-use log::*;
-use std::{path::Path, process::Command};
+use serde::Deserialize;
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
 
-use crate::license_info::LicenseInfo;
-use crate::settings;
+use crate::license_info::{self, LicenseInfo};
+use crate::settings::{self, SettingsFormat};
 use crate::{
+    binary_info::BinaryInfo,
+    error::{Error, Result},
     file_info::FileInfo,
+    ld_so::LibraryResolver,
     license_detector::{LicenseDetector, LicenseFile},
+    package_source::PackageSource,
 };
 
 const RPM_EXECUTABLE: &str = "rpm";
+/// Name of the source of rpm packages, e.g. in overrides
+pub const RPM_SOURCE: &str = "rpm";
 
-#[derive(Debug)]
-struct PackageDB {
-    packages: Vec<Package>,
+fn unique_package(packages: Vec<&Package>, query: String) -> Result<Option<Package>> {
+    if packages.len() > 1 {
+        return Err(Error::AmbiguousPackage {
+            query,
+            packages: packages.iter().map(|pkg| pkg.name.clone()).collect(),
+        });
+    }
+    Ok(packages.first().map(|package| (*package).clone()))
 }
 
-impl PackageDB {
-    pub fn new() -> Result<Self> {
+/// Database of the installed rpm packages.
+pub trait RpmDatabase: fmt::Debug + Send + Sync {
+    /// The installed packages with their files.
+    fn packages(&self) -> Result<Vec<Package>>;
+
+    /// Root directory the files of the packages are installed to.
+    fn root(&self) -> PathBuf {
+        PathBuf::from("/")
+    }
+}
+
+/// Queries the installed packages with the `rpm` executable, the database of the root directory is used
+/// (default: the database of the host).
+#[derive(Debug, Clone)]
+pub struct RpmCommand {
+    root: PathBuf,
+}
+
+impl Default for RpmCommand {
+    fn default() -> Self {
+        Self::new("/")
+    }
+}
+
+impl RpmCommand {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Executes rpm with the database of the root directory and returns its output.
+    fn rpm(&self, args: &[&str]) -> Result<String> {
+        let root = self.root.to_string_lossy();
+        let args = if self.root == Path::new("/") {
+            args.to_vec()
+        } else {
+            [&["--root", root.as_ref()], args].concat()
+        };
+        let command = format!("{RPM_EXECUTABLE} {}", args.join(" "));
+        let output = Command::new(RPM_EXECUTABLE)
+            .args(&args)
+            .output()
+            .map_err(|source| Error::CommandNotFound {
+                command: command.clone(),
+                source,
+            })?;
+        if !output.status.success() {
+            return Err(Error::CommandFailed {
+                command,
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+            });
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+impl RpmDatabase for RpmCommand {
+    fn packages(&self) -> Result<Vec<Package>> {
         let list_packages_args = vec![
             "--query",
             "--all",
             "--queryformat",
             "%{NAME}\\n%{LICENSE}\\n%{VERSION}\\n%{URL}\\n",
         ];
-        let output = Command::new(RPM_EXECUTABLE)
-            .args(&list_packages_args)
-            .output()
-            .context(format!(
-                "Cannot create command '{} {}'",
-                RPM_EXECUTABLE,
-                list_packages_args.join(" ")
-            ))?;
-        if !output.status.success() {
-            let error = String::from_utf8(output.stderr)?;
-            return Err(anyhow::anyhow!(
-                "Execution of '{} {}' failed",
-                RPM_EXECUTABLE,
-                list_packages_args.join(" ")
-            )
-            .context(error));
-        }
-        let stdout = String::from_utf8(output.stdout)?;
+        let stdout = self.rpm(&list_packages_args)?;
         let mut lines = stdout.lines();
         let mut packages = Vec::new();
+        // Each package spans several lines that are consumed by the loop body:
+        #[allow(clippy::while_let_loop)]
         loop {
             let Some(name) = lines.next() else {
                 break;
@@ -69,77 +123,190 @@ impl PackageDB {
                 .and_then(|line| if line.is_empty() { None } else { Some(line.to_string()) });
 
             // Query the package files
-            let list_packages_args = vec!["-ql", name];
-            let output = Command::new(RPM_EXECUTABLE)
-                .args(&list_packages_args)
-                .output()
-                .context(format!(
-                    "Cannot create command '{} {}'",
-                    RPM_EXECUTABLE,
-                    list_packages_args.join(" ")
-                ))?;
-            if !output.status.success() {
-                let error = String::from_utf8(output.stderr)?;
-                return Err(anyhow::anyhow!(
-                    "Execution of '{} {}' failed",
-                    RPM_EXECUTABLE,
-                    list_packages_args.join(" ")
-                )
-                .context(error)
-                .context(format!("Cannot get package content of package '{name}'")));
-            }
-            let files = String::from_utf8(output.stdout)?
-                .lines()
-                .map(String::from)
-                .collect::<Vec<_>>();
+            let files = self.rpm(&["-ql", name])?.lines().map(String::from).collect::<Vec<_>>();
 
-            packages.push(Package {
-                name: name.to_string(),
-                license,
-                version,
-                url,
-                files,
-            });
+            packages.push(Package::new(name, version, license, url, files));
         }
+        Ok(packages)
+    }
+
+    fn root(&self) -> PathBuf {
+        self.root.clone()
+    }
+}
 
-        Ok(Self { packages })
+/// Packages described by a YAML or JSON file, e.g. to test the attribution without an rpm database:
+///
+/// ```yaml
+/// root: root
+/// packages:
+///   - name: zlib
+///     version: 1.3.1
+///     license: Zlib
+///     files:
+///       - /usr/lib64/libz.so.1
+///       - /usr/share/licenses/zlib/LICENSE
+/// ```
+///
+/// The files are installed to `root`, relative to the directory of the file (default: the directory of the file).
+#[derive(Debug, Clone, Deserialize)]
+pub struct RpmFixture {
+    #[serde(default)]
+    root: PathBuf,
+    packages: Vec<Package>,
+}
+
+impl RpmFixture {
+    pub fn load(file: &Path) -> Result<Self> {
+        let str = fs::read_to_string(file).map_err(Error::io(file))?;
+        let mut fixture = SettingsFormat::of(file)?
+            .parse::<Self>(&str)
+            .map_err(|source| Error::Parse {
+                path: file.to_path_buf(),
+                source,
+            })?;
+        fixture.root = file.parent().unwrap_or(Path::new("")).join(&fixture.root);
+        Ok(fixture)
+    }
+}
+
+impl RpmDatabase for RpmFixture {
+    fn packages(&self) -> Result<Vec<Package>> {
+        Ok(self.packages.clone())
     }
 
-    pub fn query_by_library_name(&self, library_name: &str) -> Result<Package> {
-        let packages = self
-            .packages
-            .iter()
-            .filter(|pkg| pkg.files.iter().any(|file| file.contains(library_name)))
-            .collect::<Vec<_>>();
-        if packages.len() > 1 {
-            return Err(anyhow::anyhow!(
-                "Cannot find unique package containing the library '{library_name}', packages found: {}",
-                packages
-                    .iter()
-                    .map(|pkg| format!("'{}'", pkg.name))
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            ));
+    fn root(&self) -> PathBuf {
+        self.root.clone()
+    }
+}
+
+/// The installed rpm packages, libraries are attributed to the package owning the file the dynamic loader
+/// would load.
+#[derive(Debug, Clone)]
+pub struct PackageDB {
+    packages: Vec<Package>,
+    resolver: LibraryResolver,
+}
+
+impl PackageDB {
+    pub fn load(database: &dyn RpmDatabase) -> Result<Self> {
+        let root = database.root();
+        let packages = database
+            .packages()?
+            .into_iter()
+            .map(|package| Package {
+                root: root.clone(),
+                ..package
+            })
+            .collect();
+        Ok(Self {
+            packages,
+            resolver: LibraryResolver::new(&root),
+        })
+    }
+
+    /// Finds the package owning a library, e.g. `libz.so.1`. The library is resolved like the dynamic loader
+    /// resolves it, `is_64` selects the `lib64` or `lib` libraries of multilib systems. Like `rpm -qf`, the
+    /// owner is looked up by the exact path, symbolic links are followed if no package owns the link.
+    pub fn query_by_library_name(&self, library_name: &str, is_64: Option<bool>) -> Result<Package> {
+        let query = format!("the library '{library_name}'");
+        let Some(path) = self.resolver.resolve(library_name, is_64) else {
+            return Err(Error::PackageNotFound(query));
+        };
+        for path in self.resolver.symlink_chain(&path) {
+            let packages = self
+                .packages
+                .iter()
+                .filter(|pkg| pkg.files.contains(&path))
+                .collect::<Vec<_>>();
+            if let Some(package) = unique_package(packages, format!("{query} at '{path}'"))? {
+                return Ok(package);
+            }
+        }
+        Err(Error::PackageNotFound(format!("{query} at '{path}'")))
+    }
+
+    /// Finds the package owning a library, it is looked up by its SONAME and the class of the binary.
+    pub fn query_by_library(&self, lib: &FileInfo) -> Result<Package> {
+        let binary_info = BinaryInfo::read(&lib.path).ok().flatten();
+        let is_64 = binary_info.as_ref().map(|x| x.is_64);
+        match binary_info.and_then(|x| x.name) {
+            Some(soname) if soname != lib.name => match self.query_by_library_name(&soname, is_64) {
+                Err(Error::PackageNotFound(_)) => self.query_by_library_name(&lib.name, is_64),
+                result => result,
+            },
+            _ => self.query_by_library_name(&lib.name, is_64),
         }
-        if let Some(package) = packages.first() {
-            return Ok((*package).clone());
+    }
+
+    /// Number of installed packages.
+    pub fn len(&self) -> usize {
+        self.packages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+    }
+
+    pub fn query_by_name(&self, name: &str) -> Option<Package> {
+        self.packages.iter().find(|pkg| pkg.name == name).cloned()
+    }
+
+    /// Finds the package owning a file, `path` is relative to the root directory. Like `rpm -qf`, symbolic
+    /// links are followed if no package owns the link.
+    pub fn query_by_file(&self, path: &str) -> Result<Option<Package>> {
+        let query = format!("the file '{path}'");
+        for path in self
+            .resolver
+            .symlink_chain(&format!("/{}", path.trim_start_matches('/')))
+        {
+            let packages = self
+                .packages
+                .iter()
+                .filter(|pkg| pkg.files.contains(&path))
+                .collect::<Vec<_>>();
+            if let Some(package) = unique_package(packages, format!("{query} at '{path}'"))? {
+                return Ok(Some(package));
+            }
         }
-        Err(anyhow::anyhow!(
-            "Cannot find any package containing the library '{library_name}'"
-        ))
+        Ok(None)
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct Package {
     name: String,
+    #[serde(default)]
     version: Option<String>,
+    #[serde(default)]
     license: Option<String>,
+    #[serde(default)]
     url: Option<String>,
+    #[serde(default)]
     files: Vec<String>,
+    /// Root directory the files are installed to
+    #[serde(skip)]
+    root: PathBuf,
 }
 
 impl Package {
+    pub fn new(
+        name: impl Into<String>,
+        version: Option<String>,
+        license: Option<String>,
+        url: Option<String>,
+        files: Vec<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            version,
+            license,
+            url,
+            files,
+            root: PathBuf::from("/"),
+        }
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -148,102 +315,56 @@ impl Package {
         let mut found_files: Vec<_> = vec![];
         for pkg_file in &self.files {
             if patterns.iter().any(|p| pkg_file.contains(p)) {
-                let path = Path::new(pkg_file).to_path_buf();
+                let path = self.root.join(pkg_file.trim_start_matches('/'));
                 if path.is_file() {
                     found_files.push(path.to_string_lossy().into_owned());
                 } else {
-                    return Err(anyhow::anyhow!(
-                        "The installed file '{}' of package '{}' is missing in the file system",
-                        path.to_string_lossy().into_owned(),
-                        self.name
-                    ));
+                    return Err(Error::Io {
+                        path,
+                        source: std::io::Error::new(
+                            std::io::ErrorKind::NotFound,
+                            format!("The installed file of package '{}' is missing", self.name),
+                        ),
+                    });
                 }
             }
         }
         Ok(found_files)
     }
 
-    pub fn license_info(&self, lib_info: FileInfo, overrides: &[settings::Override]) -> Result<LicenseInfo> {
-        let override_info = settings::Override::find_override(&self.name, overrides);
-
-        let license = {
-            if override_info.is_some_and(|x| x.license_id.is_some()) {
-                override_info.unwrap().license_id.clone()
-            } else {
-                self.license.clone()
-            }
-        };
-
-        let license = if let Some(license) = license {
-            license
-        } else {
-            return Err(anyhow::anyhow!(
-                "Missing license identifier for RPM package '{}'",
-                self.name
-            ));
-        };
+    pub fn license_info(
+        &self,
+        lib_info: FileInfo,
+        overrides: &settings::Overrides,
+        detector: &LicenseDetector,
+    ) -> Result<LicenseInfo> {
+        let override_info =
+            settings::Override::find_override(RPM_SOURCE, &self.name, self.version.as_deref(), &[], overrides);
 
-        let license_file_patterns = vec!["COPY", "LICENSE", "License"];
-        let license_files: Vec<_> = if override_info.is_some_and(|x| !x.license_files.is_empty()) {
-            override_info
-                .unwrap()
-                .license_files
-                .iter()
-                .map(|license_file| LicenseFile {
-                    id: license_file.id.clone(),
-                    file: license_file.file.clone(),
-                })
-                .collect()
+        // The installed license files are only required if the override does not replace them:
+        let package_license_files = if override_info.is_some_and(|x| !x.license_files.is_empty()) {
+            vec![]
         } else {
-            self.collect_files(license_file_patterns)?
-                .iter()
-                .map(|file| LicenseFile {
-                    id: None,
-                    file: file.clone(),
-                })
+            self.collect_files(vec!["COPY", "LICENSE", "License"])?
+                .into_iter()
+                .map(|file| LicenseFile { id: None, file })
                 .collect()
         };
+        let (license, license_files) =
+            license_info::apply_override(override_info, self.license.clone(), package_license_files);
 
-        // Create SPDX license expression from the license IDs:
-        let license_expression = if !license.is_empty() {
-            match spdx::Expression::parse_mode(license.as_str(), spdx::ParseMode::LAX)
-                .context(format!("Cannot parse license expression for package '{}'", self.name))
-            {
-                Ok(expr) => Some(expr),
-                Err(error) => {
-                    warn!("{error:?}");
-                    None
-                }
-            }
-        } else {
-            None
+        let Some(license) = license else {
+            return Err(Error::MissingLicense {
+                kind: "RPM".to_owned(),
+                package: self.name.clone(),
+            });
         };
 
-        let license_ids = if let Some(expr) = &license_expression {
-            let mut collection = vec![];
-            expr.iter()
-                .map(|l| match l {
-                    spdx::expression::ExprNode::Req(req) => Some(req),
-                    spdx::expression::ExprNode::Op(_op) => None,
-                })
-                .for_each(|expr| {
-                    if let Some(expr) = expr
-                        && let Some(license) = &expr.req.license.id()
-                    {
-                        collection.push(*license);
-                    }
-                });
-            collection
-        } else {
-            vec![]
-        };
+        let license_expression = license_info::parse_license_expression(&self.name, &license);
+        let license_ids = license_info::license_ids(license_expression.as_ref());
 
         // Detect license ids of license texts:
-        let license_texts = LicenseDetector::instance().detect_licenses(
-            &self.name,
-            license_ids.as_slice(),
-            license_files.as_slice(),
-        )?;
+        let license_texts = detector.detect_licenses(&self.name, license_ids.as_slice(), license_files.as_slice())?;
 
         Ok(LicenseInfo {
             lib_info,
@@ -253,22 +374,37 @@ impl Package {
             license_texts,
             version: self.version.clone(),
             url: self.url.clone(),
+            features: vec![],
+            dependencies: vec![],
+            binaries: vec![],
         })
     }
 }
 
-fn static_package_db() -> Result<&'static PackageDB> {
-    static PACKAGE_LIST: std::sync::OnceLock<Result<PackageDB>> = std::sync::OnceLock::new();
+impl PackageSource for PackageDB {
+    fn name(&self) -> &str {
+        RPM_SOURCE
+    }
 
-    match PACKAGE_LIST.get_or_init(PackageDB::new) {
-        Ok(packages) => Ok(packages),
-        Err(err) => {
-            anyhow::bail!("Failed to initialize: {:?}", err);
+    fn package_of_file(&self, file: &FileInfo) -> Result<Option<String>> {
+        match self.query_by_library(file) {
+            Ok(package) => Ok(Some(package.name)),
+            Err(Error::PackageNotFound(_)) => Ok(None),
+            Err(error) => Err(error),
         }
     }
-}
 
-pub fn package_of_lib(library_name: &str) -> Result<Package> {
-    let db = static_package_db()?;
-    db.query_by_library_name(library_name)
+    fn license_info(
+        &self,
+        package: &str,
+        file: &FileInfo,
+        overrides: &settings::Overrides,
+        detector: &LicenseDetector,
+    ) -> Result<LicenseInfo> {
+        let package = self.query_by_name(package).ok_or_else(|| Error::UnknownPackage {
+            kind: RPM_SOURCE.to_owned(),
+            package: package.to_owned(),
+        })?;
+        package.license_info(file.clone(), overrides, detector)
+    }
 }
@@ -0,0 +1,1030 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use log::*;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::{
+    asset_info,
+    binary_info::{self, BinaryInfo},
+    bundle::{self, Bundle, BundleKind},
+    cargo_auditable, conan_info,
+    crates_info::{self, CargoTarget},
+    error::{Error, Result},
+    file_info::{self, FileInfo},
+    go_info, java_info,
+    license_detector::LicenseDetector,
+    license_info::LicenseInfo,
+    nix_info, node_info,
+    package_file::PackageFile,
+    package_source::{PackageSource, PackageSources},
+    python_info::{self, PythonPackage},
+    rpm_info::RpmDatabase,
+    settings::{OverrideMatches, Overrides, Settings},
+    system_packages::{PackageManager, SystemPackages},
+    target_os::TargetOs,
+    third_party_licenses::ThirdPartyLicenses,
+    vcpkg_spdx::{self, VcpkgPorts},
+    vcpkg_status::VcpkgStatus,
+};
+
+const VCPKG_INSTALLED_DIR: &str = "vcpkg_installed";
+
+/// Source of third party components, a scan reports the components of the enabled sources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// vcpkg ports of the project, including the build tools of the host triplet
+    Vcpkg,
+    /// Conan packages of the dependency graph
+    Conan,
+    /// System packages providing the libraries that are not part of another source, including the runtime of bundles
+    System,
+    /// Packages of the Nix store
+    Nix,
+    /// Crates of the `Cargo.lock` file
+    Crates,
+    /// Crates embedded into Rust binaries by cargo auditable
+    RustBinaries,
+    /// Modules of Go binaries
+    GoBinaries,
+    Python,
+    Node,
+    Java,
+    /// Fonts, icon themes and data files
+    Assets,
+}
+
+impl Source {
+    pub const ALL: [Self; 11] = [
+        Self::Vcpkg,
+        Self::Conan,
+        Self::System,
+        Self::Nix,
+        Self::Crates,
+        Self::RustBinaries,
+        Self::GoBinaries,
+        Self::Python,
+        Self::Node,
+        Self::Java,
+        Self::Assets,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Vcpkg => "vcpkg",
+            Self::Conan => "conan",
+            Self::System => "system",
+            Self::Nix => "nix",
+            Self::Crates => "crates",
+            Self::RustBinaries => "rust-binaries",
+            Self::GoBinaries => "go-binaries",
+            Self::Python => "python",
+            Self::Node => "node",
+            Self::Java => "java",
+            Self::Assets => "assets",
+        }
+    }
+}
+
+impl FromStr for Source {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|x| x.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| Error::UnknownSource {
+                name: s.to_owned(),
+                supported: Self::ALL.iter().map(|x| x.name().to_owned()).collect(),
+            })
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Kind of a report of a scan, each kind is saved to its own third party licenses file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReportKind {
+    Vcpkg,
+    /// vcpkg ports of the host triplet
+    VcpkgBuildTools,
+    Conan,
+    System,
+    Nix,
+    /// Libraries provided by the runtime of a Flatpak or Snap bundle
+    Runtime,
+    Crates,
+    RustBinaries,
+    GoBinaries,
+    Python,
+    Node,
+    Java,
+    Assets,
+    /// Libraries attributed to a [`PackageSource`] registered with [`ScanBuilder::package_source`], the name
+    /// of the source
+    Source(String),
+}
+
+impl ReportKind {
+    /// Suffix of the root name of the third party licenses, e.g. `vcpkg-build-tools`.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Vcpkg => "vcpkg",
+            Self::VcpkgBuildTools => "vcpkg-build-tools",
+            Self::Conan => "conan",
+            Self::System => "system",
+            Self::Nix => "nix",
+            Self::Runtime => "runtime",
+            Self::Crates => "crates",
+            Self::RustBinaries => "rust-binaries",
+            Self::GoBinaries => "go-binaries",
+            Self::Python => "python",
+            Self::Node => "node",
+            Self::Java => "java",
+            Self::Assets => "assets",
+            Self::Source(name) => name,
+        }
+    }
+
+    /// Name of the third party licenses file of the report, e.g. `vcpkg_build_tools_third_party_licenses.json`.
+    pub fn file_name(&self) -> String {
+        format!("{}_third_party_licenses.json", self.name().replace('-', "_"))
+    }
+
+    /// Build tools and the libraries of the bundle runtime are not shipped with the package, they are
+    /// saved for reference but not exported.
+    pub fn is_shipped(&self) -> bool {
+        !matches!(self, Self::VcpkgBuildTools | Self::Runtime)
+    }
+}
+
+/// Third party components of one kind found by a scan.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub kind: ReportKind,
+    pub licenses: Vec<LicenseInfo>,
+}
+
+impl Report {
+    /// Third party licenses of the report, the root name is the package name with the name of the report kind.
+    pub fn third_party_licenses(&self, package_name: &str) -> ThirdPartyLicenses {
+        ThirdPartyLicenses::new(format!("{package_name}-{}", self.kind.name()).as_str(), &self.licenses)
+    }
+}
+
+/// Result of a scan of a package.
+#[derive(Debug, Clone)]
+pub struct ScanReport {
+    pub target_os: TargetOs,
+    pub bundle_kind: BundleKind,
+    /// Directory containing the analyzed files of the package
+    pub package_root: PathBuf,
+    /// Libraries the package depends on that are not part of it
+    pub unbundled_libs: Vec<String>,
+    pub reports: Vec<Report>,
+    /// Packages matched by the overrides of the settings
+    pub override_matches: OverrideMatches,
+}
+
+impl ScanReport {
+    pub fn report(&self, kind: ReportKind) -> Option<&Report> {
+        self.reports.iter().find(|x| x.kind == kind)
+    }
+}
+
+/// Configures a [`Scanner`] of a package built from a project. Only the project and package paths are required,
+/// all sources are enabled by default. The vcpkg ports are only reported by default if the project has a vcpkg
+/// installation.
+#[derive(Debug, Clone)]
+pub struct ScanBuilder {
+    project_path: PathBuf,
+    package_path: PathBuf,
+    settings: Settings,
+    sources: Vec<Source>,
+    package_file: Option<PackageFile>,
+    bundle: Option<BundleKind>,
+    vcpkg_root: Option<PathBuf>,
+    triplet: Option<String>,
+    host_triplet: Option<String>,
+    target_os: Option<TargetOs>,
+    conan_graph: Option<PathBuf>,
+    conan_home: Option<PathBuf>,
+    cargo_lock: Option<PathBuf>,
+    cargo_vendor: Vec<PathBuf>,
+    cargo_target: Option<String>,
+    package_manager: Option<PackageManager>,
+    system_root: Option<PathBuf>,
+    rpm_database: Option<Arc<dyn RpmDatabase>>,
+    nix_metadata: Option<PathBuf>,
+    go_mod_cache: Option<PathBuf>,
+    package_sources: PackageSources,
+    license_detector: Option<Arc<LicenseDetector>>,
+}
+
+impl ScanBuilder {
+    pub fn new(project_path: impl Into<PathBuf>, package_path: impl Into<PathBuf>) -> Self {
+        Self {
+            project_path: project_path.into(),
+            package_path: package_path.into(),
+            settings: Settings::default(),
+            sources: vec![],
+            package_file: None,
+            bundle: None,
+            vcpkg_root: None,
+            triplet: None,
+            host_triplet: None,
+            target_os: None,
+            conan_graph: None,
+            conan_home: None,
+            cargo_lock: None,
+            cargo_vendor: vec![],
+            cargo_target: None,
+            package_manager: None,
+            system_root: None,
+            rpm_database: None,
+            nix_metadata: None,
+            go_mod_cache: None,
+            package_sources: PackageSources::new(),
+            license_detector: None,
+        }
+    }
+
+    pub fn settings(mut self, settings: Settings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Sources to report, all sources are reported if empty. A missing vcpkg installation is only an error if
+    /// the vcpkg source is requested.
+    pub fn sources(mut self, sources: impl IntoIterator<Item = Source>) -> Self {
+        self.sources = sources.into_iter().collect();
+        self
+    }
+
+    /// Package file whose payload was extracted to the package path, its required packages are reported
+    /// instead of the installed system packages.
+    pub fn package_file(mut self, package_file: impl Into<Option<PackageFile>>) -> Self {
+        self.package_file = package_file.into();
+        self
+    }
+
+    /// Layout of the package directory (default: detected).
+    pub fn bundle(mut self, bundle: impl Into<Option<BundleKind>>) -> Self {
+        self.bundle = bundle.into();
+        self
+    }
+
+    /// vcpkg installation directory (default: the settings or `vcpkg_installed` in the project directory).
+    pub fn vcpkg_root(mut self, vcpkg_root: impl Into<Option<PathBuf>>) -> Self {
+        self.vcpkg_root = vcpkg_root.into();
+        self
+    }
+
+    pub fn triplet(mut self, triplet: impl Into<Option<String>>) -> Self {
+        self.triplet = triplet.into();
+        self
+    }
+
+    pub fn host_triplet(mut self, host_triplet: impl Into<Option<String>>) -> Self {
+        self.host_triplet = host_triplet.into();
+        self
+    }
+
+    pub fn target_os(mut self, target_os: impl Into<Option<TargetOs>>) -> Self {
+        self.target_os = target_os.into();
+        self
+    }
+
+    pub fn conan_graph(mut self, conan_graph: impl Into<Option<PathBuf>>) -> Self {
+        self.conan_graph = conan_graph.into();
+        self
+    }
+
+    pub fn conan_home(mut self, conan_home: impl Into<Option<PathBuf>>) -> Self {
+        self.conan_home = conan_home.into();
+        self
+    }
+
+    pub fn cargo_lock(mut self, cargo_lock: impl Into<Option<PathBuf>>) -> Self {
+        self.cargo_lock = cargo_lock.into();
+        self
+    }
+
+    pub fn cargo_vendor(mut self, cargo_vendor: Vec<PathBuf>) -> Self {
+        self.cargo_vendor = cargo_vendor;
+        self
+    }
+
+    pub fn cargo_target(mut self, cargo_target: impl Into<Option<String>>) -> Self {
+        self.cargo_target = cargo_target.into();
+        self
+    }
+
+    pub fn package_manager(mut self, package_manager: impl Into<Option<PackageManager>>) -> Self {
+        self.package_manager = package_manager.into();
+        self
+    }
+
+    pub fn system_root(mut self, system_root: impl Into<Option<PathBuf>>) -> Self {
+        self.system_root = system_root.into();
+        self
+    }
+
+    /// Database of the installed rpm packages, e.g. an [`RpmFixture`](crate::rpm_info::RpmFixture)
+    /// (default: queried with `rpm --root` in the system root). The system packages are rpm packages installed
+    /// to the root of the database.
+    pub fn rpm_database(mut self, rpm_database: Arc<dyn RpmDatabase>) -> Self {
+        self.rpm_database = Some(rpm_database);
+        self
+    }
+
+    pub fn nix_metadata(mut self, nix_metadata: impl Into<Option<PathBuf>>) -> Self {
+        self.nix_metadata = nix_metadata.into();
+        self
+    }
+
+    pub fn go_mod_cache(mut self, go_mod_cache: impl Into<Option<PathBuf>>) -> Self {
+        self.go_mod_cache = go_mod_cache.into();
+        self
+    }
+
+    /// Registers a source of packages, e.g. an in-house artifact repository. Libraries are attributed to the
+    /// registered sources in order, after the vcpkg ports and before the system packages. The libraries of each
+    /// source are reported separately.
+    pub fn package_source(mut self, source: Arc<dyn PackageSource>) -> Self {
+        self.package_sources.push(source);
+        self
+    }
+
+    /// Detector of the licenses of license texts (default: [`LicenseDetector::shared`]).
+    pub fn license_detector(mut self, license_detector: impl Into<Option<Arc<LicenseDetector>>>) -> Self {
+        self.license_detector = license_detector.into();
+        self
+    }
+
+    pub fn build(self) -> Result<Scanner> {
+        if !self.package_path.is_dir() {
+            return Err(Error::NotADirectory {
+                what: "package path".to_owned(),
+                path: self.package_path,
+            });
+        }
+        if !self.project_path.is_dir() {
+            return Err(Error::NotADirectory {
+                what: "project path".to_owned(),
+                path: self.project_path,
+            });
+        }
+        Ok(Scanner { config: self })
+    }
+}
+
+fn find_package_system_libs(package_libs: &Vec<FileInfo>, vcpkg_libs: &[FileInfo]) -> Vec<FileInfo> {
+    let mut system_libs = vec![];
+    for lib in package_libs {
+        if !vcpkg_libs.iter().any(|l| l.name == lib.name) {
+            system_libs.push(lib.clone());
+        }
+    }
+    system_libs
+}
+
+fn find_vcpkg_installation(project_path: &Path) -> Option<PathBuf> {
+    let path = project_path.join(VCPKG_INSTALLED_DIR);
+    if !path.is_dir() {
+        return None;
+    }
+    info!("FOUND vcpkg installation at: {path:?}");
+    Some(path)
+}
+
+/// Parses the bundled libraries and returns the libraries they depend on that are not part of the package.
+/// These are expected to be provided by the operating system or the runtime of the bundle.
+fn find_unbundled_dependencies(package_libs: &[FileInfo], target_os: TargetOs) -> Vec<String> {
+    let mut unbundled: Vec<String> = vec![];
+    for lib in package_libs {
+        let binary_info = match BinaryInfo::read(&lib.path) {
+            Ok(Some(binary_info)) => binary_info,
+            Ok(None) => {
+                trace!("Skip library '{}', it is no binary", lib.name);
+                continue;
+            }
+            Err(error) => {
+                warn!("{error:?}");
+                continue;
+            }
+        };
+        if binary_info.format != target_os.binary_format() {
+            warn!(
+                "Library '{}' has the format {:?}, but {:?} is expected for target OS '{target_os}'",
+                lib.name,
+                binary_info.format,
+                target_os.binary_format()
+            );
+        }
+        trace!("Library '{}' depends on: {}", lib.name, binary_info.needed.join(", "));
+        for needed in binary_info.needed_file_names() {
+            let is_bundled = package_libs.iter().any(|l| l.name.eq_ignore_ascii_case(&needed));
+            if !is_bundled && !unbundled.contains(&needed) {
+                unbundled.push(needed);
+            }
+        }
+    }
+    unbundled.sort();
+    unbundled
+}
+
+/// State of a scan shared by the sources.
+struct ScanContext<'a> {
+    overrides: &'a Overrides,
+    detector: &'a LicenseDetector,
+    target_os: TargetOs,
+    bundle: Bundle,
+    /// Names of the directories and files ignored in the package
+    ignore_list: Vec<String>,
+    reports: Vec<Report>,
+}
+
+impl ScanContext<'_> {
+    /// Directory containing the files of the package
+    fn package_path(&self) -> &PathBuf {
+        &self.bundle.root
+    }
+
+    fn report(&mut self, kind: ReportKind, licenses: Vec<LicenseInfo>) {
+        self.reports.push(Report { kind, licenses });
+    }
+}
+
+/// Scans a package for its third party components, the file system is only read.
+#[derive(Debug, Clone)]
+pub struct Scanner {
+    config: ScanBuilder,
+}
+
+impl Scanner {
+    pub fn settings(&self) -> &Settings {
+        &self.config.settings
+    }
+
+    fn is_enabled(&self, source: Source) -> bool {
+        self.config.sources.is_empty() || self.config.sources.contains(&source)
+    }
+
+    pub fn scan(&self) -> Result<ScanReport> {
+        let config = &self.config;
+        let settings = &config.settings;
+        let detector = match &config.license_detector {
+            Some(detector) => detector,
+            None => LicenseDetector::shared(),
+        };
+
+        debug!(
+            "project = {:?}, package = {:?}",
+            config.project_path, config.package_path
+        );
+
+        for replace in &settings.overrides {
+            trace!(
+                "Found override for {} in settings. Comment: {}",
+                replace.package, replace.comment
+            )
+        }
+
+        let bundle = Bundle::open(&config.package_path, config.bundle)?;
+        let target_os = config.target_os.or(settings.target_os).unwrap_or_else(TargetOs::host);
+        if bundle.kind != BundleKind::Directory && target_os != TargetOs::Linux {
+            warn!(
+                "{} bundles are Linux packages, but the target OS is '{target_os}'",
+                bundle.kind
+            );
+        }
+        info!("Analyze package for target OS '{target_os}'");
+        let overrides = Overrides::new(settings.overrides.clone());
+        let mut ctx = ScanContext {
+            overrides: &overrides,
+            detector,
+            target_os,
+            bundle,
+            ignore_list: self.package_ignore_list(),
+            reports: vec![],
+        };
+
+        let system_packages = self.open_system_packages(target_os)?;
+        // The libraries of package files, Flatpak and Snap bundles are not installed from the system packages
+        // of the host:
+        let installed_system_packages = system_packages
+            .clone()
+            .filter(|_| ctx.bundle.kind == BundleKind::Directory && config.package_file.is_none());
+
+        let package_libs = file_info::find_files_recurse_with(
+            ctx.package_path(),
+            &|name| target_os.is_library(name),
+            ctx.ignore_list.as_slice(),
+        )
+        .map_err(Error::io(ctx.package_path()))?;
+
+        let unbundled_libs = find_unbundled_dependencies(&package_libs, target_os);
+        // Libraries of Nix builds are loaded from the Nix store by the search paths of the binaries:
+        let nix_store_libs = nix_info::resolve_store_libs(&package_libs, &unbundled_libs);
+        let unbundled_libs = unbundled_libs
+            .into_iter()
+            .filter(|lib| !nix_store_libs.iter().any(|x| x.name == *lib))
+            .collect::<Vec<_>>();
+        let runtime_licenses = ctx.bundle.runtime_license_info(&unbundled_libs);
+        if runtime_licenses.is_none() {
+            for lib in &unbundled_libs {
+                info!(
+                    "Library '{lib}' is not part of the package and is expected to be provided by the operating system"
+                );
+            }
+        }
+
+        // Python packages installed into the package, their extension modules are attributed to them:
+        let (site_packages, python_packages) = if self.is_enabled(Source::Python) {
+            let site_packages = python_info::find_site_packages(ctx.package_path(), ctx.ignore_list.as_slice())?;
+            let python_packages = python_info::find_python_packages(&site_packages)?;
+            (site_packages, python_packages)
+        } else {
+            (vec![], vec![])
+        };
+        let python_modules = python_packages
+            .iter()
+            .flat_map(|package| package.extension_modules.iter().map(|module| module.path.clone()))
+            .collect::<Vec<_>>();
+        let package_libs = package_libs
+            .into_iter()
+            .filter(|lib| !python_modules.contains(&lib.path))
+            .collect::<Vec<_>>();
+
+        // Libraries are attributed to the first source owning them: the vcpkg ports, the registered sources and
+        // the installed system packages:
+        let mut package_sources = PackageSources::new();
+        let (vcpkg_status, vcpkg_licenses) = if self.is_enabled(Source::Vcpkg) {
+            self.scan_vcpkg(&mut ctx, &mut package_sources)?
+        } else {
+            (VcpkgStatus::default(), vec![])
+        };
+        let conan_libs = self.scan_conan(&mut ctx, &package_libs)?;
+
+        // Find libs that are either from Conan or from the other sources, libraries of the Nix store are not
+        // installed from system packages:
+        let (nix_libs, libs): (Vec<_>, Vec<_>) = find_package_system_libs(&package_libs, &conan_libs)
+            .into_iter()
+            .partition(|lib| nix_info::StorePath::of(&lib.path).is_some());
+        let nix_libs = [nix_libs, nix_store_libs].concat();
+
+        self.scan_system(
+            &mut ctx,
+            package_sources,
+            &libs,
+            system_packages.as_deref(),
+            installed_system_packages.as_ref(),
+        )?;
+        self.scan_nix(&mut ctx, &nix_libs)?;
+
+        // Libraries provided by the runtime of the bundle are not part of the third party content:
+        if let Some(runtime_licenses) = runtime_licenses
+            && self.is_enabled(Source::System)
+        {
+            ctx.report(ReportKind::Runtime, vec![runtime_licenses]);
+        }
+
+        self.scan_crates(&mut ctx)?;
+        self.scan_binaries(&mut ctx)?;
+        if self.is_enabled(Source::Python) {
+            scan_python(&mut ctx, &python_packages, site_packages.len())?;
+        }
+        if self.is_enabled(Source::Node) {
+            scan_node(&mut ctx)?;
+        }
+        if self.is_enabled(Source::Java) {
+            scan_java(&mut ctx)?;
+        }
+        if self.is_enabled(Source::Assets) {
+            scan_assets(
+                &mut ctx,
+                &vcpkg_status,
+                &vcpkg_licenses,
+                installed_system_packages.as_deref(),
+            )?;
+        }
+
+        Ok(ScanReport {
+            target_os,
+            bundle_kind: ctx.bundle.kind,
+            package_root: ctx.bundle.root,
+            unbundled_libs,
+            reports: ctx.reports,
+            override_matches: overrides.matches(),
+        })
+    }
+
+    /// Names of the directories and libraries ignored in the package.
+    fn package_ignore_list(&self) -> Vec<String> {
+        let mut ignore_list = vec!["debug".to_owned()];
+        for lib_ignore in &self.config.settings.lib_ignores {
+            info!(
+                "Add library {} to ignore list. Comment: {}",
+                lib_ignore.lib, lib_ignore.comment
+            );
+            ignore_list.push(lib_ignore.lib.clone());
+        }
+        ignore_list
+    }
+
+    /// Packages installed on the system, they are only queried for Linux targets.
+    fn open_system_packages(&self, target_os: TargetOs) -> Result<Option<Arc<SystemPackages>>> {
+        if target_os != TargetOs::Linux || !self.is_enabled(Source::System) {
+            return Ok(None);
+        }
+        if let Some(rpm_database) = &self.config.rpm_database {
+            debug!("Query system packages with rpm in {:?}", rpm_database.root());
+            return Ok(Some(Arc::new(SystemPackages::rpm(rpm_database.clone()))));
+        }
+        let system_root = self.config.system_root.clone().unwrap_or_else(|| PathBuf::from("/"));
+        let package_manager = self
+            .config
+            .package_manager
+            .unwrap_or_else(|| PackageManager::detect(&system_root));
+        debug!("Query system packages with {package_manager} in {system_root:?}");
+        Ok(Some(Arc::new(SystemPackages::open(package_manager, &system_root)?)))
+    }
+
+    /// Names of the directories ignored in the vcpkg installation.
+    fn vcpkg_ignore_list(&self, target_os: TargetOs, triplet: Option<&str>, host_triplet: Option<&str>) -> Vec<String> {
+        let mut ignore_list = vec!["debug".to_owned()];
+        if cfg!(target_os = "linux") && triplet.is_none() {
+            ignore_list.push("x64-linux".to_owned()); // Only used to provide build tools
+        }
+        // The ports of the host triplet are reported as build tools, not as shipped ports:
+        if let Some(host_triplet) = host_triplet
+            && triplet != Some(host_triplet)
+            && !ignore_list.iter().any(|x| x == host_triplet)
+        {
+            ignore_list.push(host_triplet.to_owned());
+        }
+
+        for vcpkg_ignore in &self.config.settings.vcpkg_ignores {
+            let add_ignore = if let Some(os) = &vcpkg_ignore.os {
+                os == target_os.name()
+            } else {
+                true
+            };
+            if add_ignore {
+                info!(
+                    "Add vcpkg directory {} to ignore list. Comment: {}",
+                    vcpkg_ignore.directory, vcpkg_ignore.comment
+                );
+                ignore_list.push(vcpkg_ignore.directory.clone());
+            }
+        }
+        ignore_list
+    }
+
+    /// Reports the vcpkg ports of the target and the host triplet, the libraries of the ports are attributed to
+    /// them first.
+    fn scan_vcpkg(
+        &self,
+        ctx: &mut ScanContext,
+        package_sources: &mut PackageSources,
+    ) -> Result<(VcpkgStatus, Vec<LicenseInfo>)> {
+        let config = &self.config;
+        let settings = &config.settings;
+        let triplet = config.triplet.clone().or(settings.vcpkg_triplet.clone());
+        let host_triplet = config.host_triplet.clone().or(settings.vcpkg_host_triplet.clone());
+        let vcpkg_find_ignore_list = self.vcpkg_ignore_list(ctx.target_os, triplet.as_deref(), host_triplet.as_deref());
+        let target_os = ctx.target_os;
+
+        // Find vcpkg installation
+        let vcpkg_installation = match config
+            .vcpkg_root
+            .clone()
+            .or(settings.vcpkg_root.clone().map(PathBuf::from))
+        {
+            Some(vcpkg_root) if vcpkg_root.is_dir() => vcpkg_root,
+            Some(vcpkg_root) => {
+                return Err(Error::NotADirectory {
+                    what: "vcpkg root".to_owned(),
+                    path: vcpkg_root,
+                });
+            }
+            None => match find_vcpkg_installation(&config.project_path) {
+                Some(vcpkg_installation) => vcpkg_installation,
+                // All sources are scanned by default, the project does not have to use vcpkg:
+                None if config.sources.is_empty() => {
+                    info!(
+                        "Skip vcpkg ports, there is no vcpkg installation in {:?}",
+                        config.project_path
+                    );
+                    return Ok((VcpkgStatus::default(), vec![]));
+                }
+                None => return Err(Error::VcpkgNotFound(config.project_path.clone())),
+            },
+        };
+        // Only analyze the target triplet if it is known, otherwise rely on the ignore list:
+        let vcpkg_target_dir = if let Some(triplet) = &triplet {
+            let vcpkg_target_dir = vcpkg_installation.join(triplet);
+            if !vcpkg_target_dir.is_dir() {
+                return Err(Error::MissingTriplet {
+                    triplet: triplet.clone(),
+                    path: vcpkg_installation,
+                });
+            }
+            info!("Analyze vcpkg triplet '{triplet}' at: {vcpkg_target_dir:?}");
+            vcpkg_target_dir
+        } else {
+            vcpkg_installation.clone()
+        };
+        // Collect libraries in the vcpkg installation
+        let vcpkg_libs = file_info::find_files_recurse_with(
+            &vcpkg_target_dir,
+            &|name| target_os.is_library(name),
+            vcpkg_find_ignore_list.as_slice(),
+        )
+        .map_err(Error::io(&vcpkg_target_dir))?;
+
+        // Find all vcpkg.spdx.json files
+        let vcpkg_spdx_files =
+            file_info::find_files_recurse(&vcpkg_target_dir, "vcpkg.spdx.json", vcpkg_find_ignore_list.as_slice())
+                .map_err(Error::io(&vcpkg_target_dir))?;
+
+        vcpkg_spdx::parse_spdx_files(&vcpkg_spdx_files)?;
+
+        let vcpkg_status = VcpkgStatus::load(&vcpkg_installation)?;
+        #[cfg(feature = "debug")]
+        vcpkg_status.print();
+
+        let vcpkg_licenses =
+            vcpkg_spdx::get_license_info(&vcpkg_spdx_files, &vcpkg_status, ctx.overrides, ctx.detector)?;
+        ctx.report(ReportKind::Vcpkg, vcpkg_licenses.clone());
+
+        // Report the ports of the host triplet separately, they are build tools and not shipped:
+        match (&host_triplet, &triplet) {
+            (Some(host_triplet), Some(triplet)) if host_triplet == triplet => {
+                info!(
+                    "The vcpkg host triplet equals the target triplet '{triplet}', build tools are not reported separately"
+                );
+            }
+            (Some(host_triplet), _) => {
+                let vcpkg_host_dir = vcpkg_installation.join(host_triplet);
+                let host_spdx_files = if vcpkg_host_dir.is_dir() {
+                    file_info::find_files_recurse(&vcpkg_host_dir, "vcpkg.spdx.json", &["debug".to_owned()])
+                        .map_err(Error::io(&vcpkg_host_dir))?
+                } else {
+                    warn!("Cannot find host triplet '{host_triplet}' in vcpkg installation {vcpkg_installation:?}");
+                    vec![]
+                };
+                let host_licenses =
+                    vcpkg_spdx::get_license_info(&host_spdx_files, &vcpkg_status, ctx.overrides, ctx.detector)?;
+                info!(
+                    "Found {} vcpkg ports of host triplet '{host_triplet}': build tools, not shipped",
+                    host_licenses.len()
+                );
+                ctx.report(ReportKind::VcpkgBuildTools, host_licenses);
+            }
+            (None, _) => {}
+        }
+        package_sources.push(Arc::new(VcpkgPorts::new(
+            vcpkg_status.clone(),
+            vcpkg_libs,
+            vcpkg_licenses.clone(),
+        )));
+        Ok((vcpkg_status, vcpkg_licenses))
+    }
+
+    /// Reports the Conan packages of the host context and returns their libraries.
+    fn scan_conan(&self, ctx: &mut ScanContext, package_libs: &[FileInfo]) -> Result<Vec<FileInfo>> {
+        let Some(conan_graph) = &self.config.conan_graph else {
+            return Ok(vec![]);
+        };
+        if !self.is_enabled(Source::Conan) {
+            return Ok(vec![]);
+        }
+        let conan_home = self.config.conan_home.clone().or_else(conan_info::default_conan_home);
+        let conan_packages = conan_info::read_packages(conan_graph, conan_home.as_deref())?;
+        info!("Found {} Conan packages", conan_packages.len());
+        let conan_libs = conan_packages
+            .iter()
+            .filter(|package| !package.is_build_context)
+            .flat_map(|package| package.libraries(ctx.target_os))
+            .collect::<Vec<_>>();
+        let conan_licenses = conan_info::get_license_info(
+            &conan_packages,
+            package_libs,
+            ctx.target_os,
+            ctx.overrides,
+            ctx.detector,
+        )?;
+        ctx.report(ReportKind::Conan, conan_licenses);
+        Ok(conan_libs)
+    }
+
+    /// Attributes the libraries to the registered sources and the installed system packages, the libraries no
+    /// source owns are reported as system libraries of the package file or the bundle.
+    fn scan_system(
+        &self,
+        ctx: &mut ScanContext,
+        mut package_sources: PackageSources,
+        libs: &[FileInfo],
+        system_packages: Option<&SystemPackages>,
+        installed_system_packages: Option<&Arc<SystemPackages>>,
+    ) -> Result<()> {
+        let config = &self.config;
+        let vcpkg_sources = package_sources.sources().len();
+        for source in config.package_sources.sources() {
+            package_sources.push(source.clone());
+        }
+        if let Some(system_packages) = installed_system_packages {
+            package_sources.push(system_packages.clone());
+        }
+        let attribution = package_sources.attribute(libs, ctx.overrides, ctx.detector)?;
+        // The vcpkg ports are reported with all ports, including header only libraries:
+        let mut attributed_licenses = attribution.licenses.into_iter().skip(vcpkg_sources);
+        for (source, licenses) in config.package_sources.sources().iter().zip(&mut attributed_licenses) {
+            info!("Found {} packages of source '{}'", licenses.len(), source.name());
+            ctx.report(ReportKind::Source(source.name().to_owned()), licenses);
+        }
+        let system_libs = attribution.unattributed;
+
+        if !self.is_enabled(Source::System) {
+            return Ok(());
+        }
+        let (overrides, detector) = (ctx.overrides, ctx.detector);
+        let system_licenses = match ctx.bundle.kind {
+            // The system packages required by a package file are installed with it:
+            BundleKind::Directory if let Some(package_file) = &config.package_file => {
+                package_file.license_info(&system_libs, system_packages, overrides, detector)?
+            }
+            BundleKind::Directory if installed_system_packages.is_some() => {
+                if let Some(lib) = system_libs.first() {
+                    return Err(Error::PackageNotFound(format!("the library '{}'", lib.name)));
+                }
+                attributed_licenses.next().unwrap_or_default()
+            }
+            BundleKind::Directory => {
+                for lib in &system_libs {
+                    warn!(
+                        "Cannot attribute library '{}' to a package, system packages are only supported for Linux targets",
+                        lib.name
+                    );
+                }
+                vec![]
+            }
+            BundleKind::Snap => ctx.bundle.license_info(&system_libs, overrides, detector)?,
+            BundleKind::Flatpak => system_libs
+                .iter()
+                .map(|lib| bundle::unattributed_license_info(lib, ctx.bundle.kind, overrides, detector))
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        };
+        ctx.report(ReportKind::System, system_licenses);
+        Ok(())
+    }
+
+    fn scan_nix(&self, ctx: &mut ScanContext, nix_libs: &[FileInfo]) -> Result<()> {
+        if nix_libs.is_empty() || !self.is_enabled(Source::Nix) {
+            return Ok(());
+        }
+        info!("Found {} libraries in the Nix store", nix_libs.len());
+        let nix_metadata = match &self.config.nix_metadata {
+            Some(file) => nix_info::NixMetadata::load(file)?,
+            None => nix_info::NixMetadata::default(),
+        };
+        let nix_licenses = nix_info::get_license_info(nix_libs, &nix_metadata, ctx.overrides, ctx.detector)?;
+        ctx.report(ReportKind::Nix, nix_licenses);
+        Ok(())
+    }
+
+    fn scan_crates(&self, ctx: &mut ScanContext) -> Result<()> {
+        let config = &self.config;
+        let Some(cargo_lock) = &config.cargo_lock else {
+            return Ok(());
+        };
+        if !self.is_enabled(Source::Crates) {
+            return Ok(());
+        }
+        let search_dirs = if config.cargo_vendor.is_empty() {
+            crates_info::default_search_dirs(cargo_lock)
+        } else {
+            config.cargo_vendor.clone()
+        };
+        let target = CargoTarget::new(ctx.target_os, config.cargo_target.clone());
+        let crates_licenses =
+            crates_info::get_license_info(cargo_lock, &search_dirs, &target, ctx.overrides, ctx.detector)?;
+        ctx.report(ReportKind::Crates, crates_licenses);
+        Ok(())
+    }
+
+    /// Reports the crates embedded into Rust binaries by cargo auditable and the modules listed in the build
+    /// information of Go binaries.
+    fn scan_binaries(&self, ctx: &mut ScanContext) -> Result<()> {
+        let config = &self.config;
+        if !self.is_enabled(Source::RustBinaries) && !self.is_enabled(Source::GoBinaries) {
+            return Ok(());
+        }
+        let package_binaries = binary_info::find_binaries_recurse(ctx.package_path(), ctx.ignore_list.as_slice())
+            .map_err(Error::io(ctx.package_path()))?;
+        if self.is_enabled(Source::RustBinaries) {
+            let crate_search_dirs = if config.cargo_vendor.is_empty() {
+                crates_info::registry_src_dirs()
+            } else {
+                config.cargo_vendor.clone()
+            };
+            let rust_binaries_licenses =
+                cargo_auditable::get_license_info(&package_binaries, &crate_search_dirs, ctx.overrides, ctx.detector)?;
+            if !rust_binaries_licenses.is_empty() {
+                ctx.report(ReportKind::RustBinaries, rust_binaries_licenses);
+            }
+        }
+        if self.is_enabled(Source::GoBinaries) {
+            let go_mod_cache = config.go_mod_cache.clone().or_else(go_info::default_module_cache);
+            let go_binaries_licenses =
+                go_info::get_license_info(&package_binaries, go_mod_cache.as_deref(), ctx.overrides, ctx.detector)?;
+            if !go_binaries_licenses.is_empty() {
+                ctx.report(ReportKind::GoBinaries, go_binaries_licenses);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn scan_python(ctx: &mut ScanContext, python_packages: &[PythonPackage], site_packages: usize) -> Result<()> {
+    if python_packages.is_empty() {
+        return Ok(());
+    }
+    info!(
+        "Found {} Python packages in {site_packages} site-packages directories",
+        python_packages.len()
+    );
+    let python_licenses = python_info::get_license_info(python_packages, ctx.overrides, ctx.detector)?;
+    ctx.report(ReportKind::Python, python_licenses);
+    Ok(())
+}
+
+fn scan_node(ctx: &mut ScanContext) -> Result<()> {
+    let node_modules = node_info::find_node_modules(ctx.package_path(), ctx.ignore_list.as_slice())?;
+    let node_packages = node_info::find_node_packages(&node_modules)?;
+    if node_packages.is_empty() {
+        return Ok(());
+    }
+    info!(
+        "Found {} Node packages in {} node_modules directories",
+        node_packages.len(),
+        node_modules.len()
+    );
+    let node_licenses = node_info::get_license_info(&node_packages, ctx.overrides, ctx.detector)?;
+    ctx.report(ReportKind::Node, node_licenses);
+    Ok(())
+}
+
+fn scan_java(ctx: &mut ScanContext) -> Result<()> {
+    let package_jars = java_info::find_jars_recurse(ctx.package_path(), ctx.ignore_list.as_slice())
+        .map_err(Error::io(ctx.package_path()))?;
+    let java_licenses = java_info::get_license_info(&package_jars, ctx.overrides, ctx.detector)?;
+    if !java_licenses.is_empty() {
+        ctx.report(ReportKind::Java, java_licenses);
+    }
+    Ok(())
+}
+
+/// Reports the fonts, icon themes and data files, they are attributed to the packages installing them.
+fn scan_assets(
+    ctx: &mut ScanContext,
+    vcpkg_status: &VcpkgStatus,
+    vcpkg_licenses: &[LicenseInfo],
+    installed_system_packages: Option<&SystemPackages>,
+) -> Result<()> {
+    let assets = asset_info::find_assets(ctx.package_path(), ctx.ignore_list.as_slice())
+        .map_err(Error::io(ctx.package_path()))?;
+    if assets.is_empty() {
+        return Ok(());
+    }
+    info!("Found {} assets", assets.len());
+    // The payload of a package file is not installed, its assets are not owned by system packages:
+    let assets_licenses = asset_info::get_license_info(
+        &assets,
+        vcpkg_status,
+        vcpkg_licenses,
+        installed_system_packages,
+        ctx.overrides,
+        ctx.detector,
+    )?;
+    ctx.report(ReportKind::Assets, assets_licenses);
+    Ok(())
+}
@@ -8,45 +8,128 @@
 //
 // SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
 
-use anyhow::{Context, Result};
-use serde::{Deserialize, Serialize};
-use std::path::Path;
+use log::*;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize, de::IgnoredAny};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
 
-#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+use crate::{
+    error::{Error, Result},
+    target_os::TargetOs,
+    version::VersionConstraint,
+};
+
+/// Prefix of package selectors that are regular expressions, e.g. `regex:^qt5-(base|svg)$`.
+const REGEX_PREFIX: &str = "regex:";
+/// Environment variable of the directory containing shared settings files, e.g. an organisation wide policy.
+pub const SHARED_SETTINGS_DIR_ENV: &str = "SANCUS_SHARED_SETTINGS_DIR";
+/// Environment variable of the settings file, used if no settings file is given on the command line.
+pub const SETTINGS_FILE_ENV: &str = "SANCUS_CONFIG";
+/// Names of the settings files searched in the project directory and its parent directories.
+pub const SETTINGS_FILE_NAMES: [&str; 4] = ["sancus.yaml", "sancus.yml", "sancus.toml", "sancus.json"];
+
+/// File format of a settings file, determined by its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SettingsFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl SettingsFormat {
+    pub(crate) fn of(file: &Path) -> Result<Self> {
+        match file
+            .extension()
+            .and_then(|x| x.to_str())
+            .map(str::to_lowercase)
+            .as_deref()
+        {
+            Some("yaml" | "yml") => Ok(Self::Yaml),
+            Some("toml") => Ok(Self::Toml),
+            Some("json") => Ok(Self::Json),
+            _ => Err(Error::Settings {
+                path: file.to_path_buf(),
+                message: "Unknown format, supported are YAML, TOML and JSON".to_owned(),
+            }),
+        }
+    }
+
+    pub(crate) fn parse<T: serde::de::DeserializeOwned>(
+        &self,
+        str: &str,
+    ) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(match self {
+            Self::Yaml => serde_yaml_bw::from_str(str)?,
+            Self::Toml => toml::from_str(str)?,
+            Self::Json => serde_json::from_str(str)?,
+        })
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema, Clone)]
 #[serde(rename_all = "snake_case")]
 pub struct LicenseFileOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<String>,
     pub file: String,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema, Clone)]
 #[serde(default, rename_all = "snake_case")]
 pub struct Override {
+    /// Package name, a glob pattern with `*` and `?` or a regular expression with the prefix `regex:`.
+    /// Required features are appended in brackets, e.g. `ffmpeg[gpl]`.
     pub package: String,
+    /// Version requirement, e.g. `>=5.15, <6` or `^1.2`, versions are compared like rpm compares them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Source of the package the override applies to, e.g. `vcpkg`, `rpm` or `crates`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
     pub comment: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub license_id: Option<String>,
     pub overwrite_all_license_ids: bool,
     pub license_files: Vec<LicenseFileOverride>,
+    /// Settings file the override is defined in
+    #[serde(skip)]
+    pub origin: Option<PathBuf>,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema, Clone)]
 #[serde(rename_all = "snake_case")]
 pub struct VcpkgIgnore {
     pub directory: String,
     pub comment: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub os: Option<String>,
+    /// Settings file the ignore is defined in
+    #[serde(skip)]
+    pub origin: Option<PathBuf>,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema, Clone)]
 #[serde(rename_all = "snake_case")]
 pub struct LibIgnore {
     pub lib: String,
     pub comment: String,
+    /// Settings file the ignore is defined in
+    #[serde(skip)]
+    pub origin: Option<PathBuf>,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema, Clone)]
 #[serde(rename_all = "snake_case")]
 pub struct Settings {
+    /// Settings files these settings extend, relative paths are relative to the settings file or, if not found
+    /// there, to the shared settings directory. Entries of this file are merged on top of the included ones.
+    #[serde(default, alias = "extends", skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
     #[serde(default = "default_overrides")]
     pub overrides: Vec<Override>,
     #[serde(default = "default_use_vcpkg_default_ignores")]
@@ -55,8 +138,22 @@ pub struct Settings {
     pub vcpkg_ignores: Vec<VcpkgIgnore>,
     #[serde(default = "default_lib_ignores")]
     pub lib_ignores: Vec<LibIgnore>,
+    /// Path to the vcpkg installation directory, relative paths are relative to the settings file
+    pub vcpkg_root: Option<String>,
+    /// vcpkg triplet of the shipped libraries
+    pub vcpkg_triplet: Option<String>,
+    /// vcpkg host triplet, its ports are reported as build tools that are not shipped
+    pub vcpkg_host_triplet: Option<String>,
+    /// Operating system the package is built for (default: the operating system sancus is running on)
+    pub target_os: Option<TargetOs>,
+    /// Settings file each of the other settings is taken from
+    #[serde(skip)]
+    pub origins: BTreeMap<String, PathBuf>,
 }
 
+/// Keys of the settings that are lists merged entry by entry, all other settings are replaced.
+const LIST_KEYS: [&str; 3] = ["overrides", "vcpkg_ignores", "lib_ignores"];
+
 fn default_overrides() -> Vec<Override> {
     vec![]
 }
@@ -73,27 +170,339 @@ fn default_lib_ignores() -> Vec<LibIgnore> {
     vec![]
 }
 
+/// Specificity of a matching override: exact name, number of required features, version and source.
+type Specificity = (bool, usize, bool, bool);
+
+/// Packages matched by the overrides of a scan, keyed by the index of the override in the settings. Used to report
+/// overrides that match nothing or several packages.
+pub type OverrideMatches = BTreeMap<usize, BTreeSet<String>>;
+
+/// Overrides of a scan, the packages matched by each override are recorded.
+#[derive(Debug, Default)]
+pub struct Overrides {
+    overrides: Vec<Override>,
+    matches: Mutex<OverrideMatches>,
+}
+
+impl Overrides {
+    pub fn new(overrides: Vec<Override>) -> Self {
+        Self {
+            overrides,
+            matches: Mutex::default(),
+        }
+    }
+
+    pub fn as_slice(&self) -> &[Override] {
+        &self.overrides
+    }
+
+    /// Packages matched by the overrides so far.
+    pub fn matches(&self) -> OverrideMatches {
+        self.matches.lock().map(|x| x.clone()).unwrap_or_default()
+    }
+}
+
+impl From<Vec<Override>> for Overrides {
+    fn from(overrides: Vec<Override>) -> Self {
+        Self::new(overrides)
+    }
+}
+
+/// Matches a glob pattern with `*` (any characters) and `?` (one character).
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let regex = regex::escape(pattern).replace(r"\*", ".*").replace(r"\?", ".");
+    Regex::new(&format!("^{regex}$")).is_ok_and(|x| x.is_match(name))
+}
+
 impl Override {
-    pub fn find_override<'a>(package: &str, overrides: &'a [Self]) -> Option<&'a Self> {
-        overrides.iter().find(|&x| x.package == package)
+    /// Splits the package selector into the package name and the required features,
+    /// e.g. `ffmpeg[gpl,nonfree]` selects the package `ffmpeg` if the features `gpl` and `nonfree` are enabled.
+    /// Regular expressions have no feature selector.
+    pub fn package_selector(&self) -> (&str, Vec<&str>) {
+        if self.package.trim_start().starts_with(REGEX_PREFIX) {
+            return (self.package.trim(), vec![]);
+        }
+        match self.package.split_once('[') {
+            Some((name, features)) => (
+                name.trim(),
+                features
+                    .trim_end()
+                    .trim_end_matches(']')
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|x| !x.is_empty())
+                    .collect(),
+            ),
+            None => (self.package.trim(), vec![]),
+        }
+    }
+
+    /// Returns `true` if the package selector is a glob pattern or a regular expression.
+    pub fn is_pattern(&self) -> bool {
+        let (name, _) = self.package_selector();
+        name.starts_with(REGEX_PREFIX) || name.contains(['*', '?'])
+    }
+
+    /// Checks that a regular expression selector and the version requirement are valid, returns the problem
+    /// otherwise.
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        let (name, _) = self.package_selector();
+        if let Some(regex) = name.strip_prefix(REGEX_PREFIX)
+            && let Err(error) = Regex::new(regex)
+        {
+            return Err(format!(
+                "Override '{}' has an invalid regular expression: {error}",
+                self.package
+            ));
+        }
+        if let Some(version) = &self.version
+            && let Err(error) = VersionConstraint::parse(version)
+        {
+            return Err(format!(
+                "Override '{}' has an invalid version requirement '{version}': {error}",
+                self.package
+            ));
+        }
+        Ok(())
+    }
+
+    fn matches_name(&self, package: &str) -> bool {
+        let (name, _) = self.package_selector();
+        if let Some(regex) = name.strip_prefix(REGEX_PREFIX) {
+            match Regex::new(regex) {
+                Ok(regex) => regex.is_match(package),
+                Err(error) => {
+                    warn!("Invalid regular expression of override '{}': {error}", self.package);
+                    false
+                }
+            }
+        } else if name.contains(['*', '?']) {
+            glob_matches(name, package)
+        } else {
+            name == package
+        }
+    }
+
+    fn matches_version(&self, version: Option<&str>) -> bool {
+        let Some(constraint) = &self.version else {
+            return true;
+        };
+        match (VersionConstraint::parse(constraint), version) {
+            (Ok(constraint), Some(version)) => constraint.matches(version),
+            (Ok(_), None) => false,
+            (Err(error), _) => {
+                warn!("Invalid version requirement of override '{}': {error:?}", self.package);
+                false
+            }
+        }
+    }
+
+    /// Identifies the override in warnings: the package selector, the version and the source.
+    pub fn description(&self) -> String {
+        let mut description = format!("'{}'", self.package);
+        if let Some(version) = &self.version {
+            description.push_str(&format!(" version '{version}'"));
+        }
+        if let Some(source) = &self.source {
+            description.push_str(&format!(" source '{source}'"));
+        }
+        description
+    }
+
+    /// Finds the override for a package of a source (e.g. `vcpkg`) with the given version and enabled features.
+    /// If several overrides match, the most specific one wins: exact names before patterns, then the one
+    /// requiring the most features, a version or a source. The package is recorded as match of the override.
+    pub fn find_override<'a>(
+        source: &str,
+        package: &str,
+        version: Option<&str>,
+        features: &[String],
+        overrides: &'a Overrides,
+    ) -> Option<&'a Self> {
+        let mut found: Option<(usize, &Self, Specificity)> = None;
+        let mut is_ambiguous = false;
+        for (index, x) in overrides.overrides.iter().enumerate() {
+            let (_, required_features) = x.package_selector();
+            if !x.matches_name(package)
+                || !required_features.iter().all(|f| features.iter().any(|x| x == f))
+                || x.source.as_ref().is_some_and(|x| !x.eq_ignore_ascii_case(source))
+                || !x.matches_version(version)
+            {
+                continue;
+            }
+            let specificity = (
+                !x.is_pattern(),
+                required_features.len(),
+                x.version.is_some(),
+                x.source.is_some(),
+            );
+            match found {
+                Some((_, _, best)) if specificity == best => is_ambiguous = true,
+                Some((_, _, best)) if specificity < best => {}
+                _ => {
+                    found = Some((index, x, specificity));
+                    is_ambiguous = false;
+                }
+            }
+        }
+        let (index, found, _) = found?;
+        if is_ambiguous {
+            warn!(
+                "Several overrides match {source} package '{package}', the first one {} is used",
+                found.description()
+            );
+        }
+        if let Ok(mut matches) = overrides.matches.lock() {
+            matches
+                .entry(index)
+                .or_default()
+                .insert(format!("{source} package '{package}'"));
+        }
+        Some(found)
+    }
+
+    /// Warns about overrides that did not match any package and about overrides of a single package that
+    /// matched several packages, e.g. packages of the same name from different sources.
+    pub fn report_matches(overrides: &[Self], matches: &OverrideMatches) {
+        for (index, x) in overrides.iter().enumerate() {
+            match matches.get(&index) {
+                None => warn!("Override {} does not match any package", x.description()),
+                Some(packages) if packages.len() > 1 && !x.is_pattern() => warn!(
+                    "Override {} matches several packages, please add a 'source' or 'version': {}",
+                    x.description(),
+                    packages.iter().cloned().collect::<Vec<_>>().join(", ")
+                ),
+                Some(packages) => debug!(
+                    "Override {} matches: {}",
+                    x.description(),
+                    packages.iter().cloned().collect::<Vec<_>>().join(", ")
+                ),
+            }
+        }
+    }
+}
+
+/// Replaces the entry with the same key or appends it, duplicates within `other` are kept.
+fn merge_entries<T, K: PartialEq>(entries: &mut Vec<T>, other: Vec<T>, key: impl Fn(&T) -> K) {
+    let existing_count = entries.len();
+    for entry in other {
+        match entries[..existing_count].iter_mut().find(|x| key(x) == key(&entry)) {
+            Some(existing) => *existing = entry,
+            None => entries.push(entry),
+        }
     }
 }
 
+/// Serializes settings to YAML.
+fn to_yaml(value: &impl Serialize) -> Result<String> {
+    serde_yaml_bw::to_string(value).map_err(|source| Error::Serialize {
+        what: "settings".to_owned(),
+        source: source.into(),
+    })
+}
+
+/// Formats a settings list as YAML sequence, each entry is preceded by its origin.
+fn write_entries<T: Serialize>(
+    out: &mut String,
+    key: &str,
+    entries: &[T],
+    origin: impl Fn(&T) -> Option<&PathBuf>,
+) -> Result<()> {
+    if entries.is_empty() {
+        out.push_str(&format!("{key}: []\n"));
+        return Ok(());
+    }
+    out.push_str(&format!("{key}:\n"));
+    for entry in entries {
+        if let Some(origin) = origin(entry) {
+            out.push_str(&format!("  # {}\n", origin.to_string_lossy()));
+        }
+        for (index, line) in to_yaml(entry)?.lines().enumerate() {
+            out.push_str(&format!("{}{line}\n", if index == 0 { "  - " } else { "    " }));
+        }
+    }
+    Ok(())
+}
+
 impl Settings {
     pub fn default_settings_file() -> std::path::PathBuf {
         std::path::Path::new("sancus.yaml").to_path_buf()
     }
 
-    pub fn load(file: &std::path::Path) -> Result<Self> {
-        let str = std::fs::read_to_string(file)
-            .with_context(|| format!("Cannot read settings file {}", file.to_str().unwrap_or("empty")).clone())?;
-        let mut settings = serde_yaml_bw::from_str::<Self>(str.as_str())
-            .with_context(|| format!("Cannot parse settings {}", file.to_str().unwrap_or("empty")).clone())?;
+    /// Directory of the shared settings files, taken from `SANCUS_SHARED_SETTINGS_DIR` if not given.
+    pub fn shared_settings_dir(shared_dir: Option<&Path>) -> Option<PathBuf> {
+        shared_dir
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::var_os(SHARED_SETTINGS_DIR_ENV).map(PathBuf::from))
+    }
+
+    /// Finds the settings file of a project: the given file, the file set by `SANCUS_CONFIG` or the first
+    /// `sancus.yaml`, `sancus.yml`, `sancus.toml` or `sancus.json` in the project directory or its parent
+    /// directories. The search stops at the root of a git repository.
+    pub fn find_settings_file(project_path: &Path, settings_file: Option<&Path>) -> Result<Option<PathBuf>> {
+        if let Some(file) = settings_file
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::var_os(SETTINGS_FILE_ENV).map(PathBuf::from))
+        {
+            if !file.is_file() {
+                return Err(Error::Io {
+                    path: file,
+                    source: std::io::ErrorKind::NotFound.into(),
+                });
+            }
+            return Ok(Some(file));
+        }
+        let start = project_path
+            .canonicalize()
+            .unwrap_or_else(|_| project_path.to_path_buf());
+        for dir in start.ancestors() {
+            let mut found = SETTINGS_FILE_NAMES
+                .iter()
+                .map(|name| dir.join(name))
+                .filter(|x| x.is_file());
+            if let Some(file) = found.next() {
+                let ignored = found.collect::<Vec<_>>();
+                if !ignored.is_empty() {
+                    warn!("Using settings file {file:?}, ignoring {ignored:?}");
+                }
+                return Ok(Some(file));
+            }
+            if dir.join(".git").exists() {
+                break;
+            }
+        }
+        Ok(None)
+    }
+
+    /// Loads a settings file and the settings files it includes, see [`Settings::include`].
+    pub fn load(file: &std::path::Path, shared_dir: Option<&Path>) -> Result<Self> {
+        let shared_dir = Self::shared_settings_dir(shared_dir);
+        let mut settings = Self::load_file(file, shared_dir.as_deref(), &mut vec![])?;
+        settings.include.clear();
+        Ok(settings)
+    }
+
+    fn load_file(file: &Path, shared_dir: Option<&Path>, included_by: &mut Vec<PathBuf>) -> Result<Self> {
+        let str = std::fs::read_to_string(file).map_err(Error::io(file))?;
+        let format = SettingsFormat::of(file)?;
+        let mut settings = format
+            .parse::<Self>(str.as_str())
+            .map_err(|source| Error::SettingsParse {
+                path: file.to_path_buf(),
+                source,
+            })?;
+        let keys = format
+            .parse::<BTreeMap<String, IgnoredAny>>(str.as_str())
+            .map(|x| x.into_keys().collect::<Vec<_>>())
+            .unwrap_or_default();
 
-        // Map relative license file overwrites to settings file:
-        let settings_path = file
-            .parent()
-            .unwrap_or_else(|| panic!("Cannot get directory of settings file {}", file.to_string_lossy()));
+        // Map relative paths to settings file:
+        let settings_path = file.parent().unwrap_or(Path::new(""));
+        if let Some(vcpkg_root) = settings.vcpkg_root.as_mut()
+            && Path::new(vcpkg_root).is_relative()
+        {
+            *vcpkg_root = settings_path.join(&vcpkg_root).to_string_lossy().into_owned();
+        }
         settings.overrides.iter_mut().for_each(|or| {
             or.license_files.iter_mut().for_each(|lf| {
                 let file_path = Path::new(&lf.file);
@@ -102,6 +511,147 @@ impl Settings {
                 }
             });
         });
-        Ok(settings)
+
+        for x in &settings.overrides {
+            x.validate().map_err(|message| Error::Settings {
+                path: file.to_path_buf(),
+                message,
+            })?;
+        }
+
+        // Remember where each setting comes from:
+        let origin = file.to_path_buf();
+        settings
+            .overrides
+            .iter_mut()
+            .for_each(|x| x.origin = Some(origin.clone()));
+        settings
+            .vcpkg_ignores
+            .iter_mut()
+            .for_each(|x| x.origin = Some(origin.clone()));
+        settings
+            .lib_ignores
+            .iter_mut()
+            .for_each(|x| x.origin = Some(origin.clone()));
+        for key in keys
+            .iter()
+            .filter(|x| !LIST_KEYS.contains(&x.as_str()) && *x != "include" && *x != "extends")
+        {
+            settings.origins.insert(key.clone(), origin.clone());
+        }
+
+        if settings.include.is_empty() {
+            return Ok(settings);
+        }
+        let canonical_file = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+        if included_by.contains(&canonical_file) {
+            return Err(Error::Settings {
+                path: file.to_path_buf(),
+                message: "The settings file is included recursively".to_owned(),
+            });
+        }
+        included_by.push(canonical_file);
+        let mut merged = Self::default();
+        for include in &settings.include {
+            let include_file = Self::find_include(include, settings_path, shared_dir).ok_or_else(|| Error::Settings {
+                path: file.to_path_buf(),
+                message: match shared_dir {
+                    Some(shared_dir) => format!(
+                        "Cannot find included settings file '{include}' in {settings_path:?} or {shared_dir:?}"
+                    ),
+                    None => format!(
+                        "Cannot find included settings file '{include}' in {settings_path:?}, no shared settings directory is set"
+                    ),
+                },
+            })?;
+            debug!("Settings file {file:?} includes {include_file:?}");
+            merged.merge(Self::load_file(&include_file, shared_dir, included_by)?);
+        }
+        included_by.pop();
+        merged.merge(settings);
+        Ok(merged)
+    }
+
+    fn find_include(include: &str, settings_path: &Path, shared_dir: Option<&Path>) -> Option<PathBuf> {
+        let path = Path::new(include);
+        if path.is_absolute() {
+            return Some(path.to_path_buf());
+        }
+        std::iter::once(settings_path.join(path))
+            .chain(shared_dir.map(|x| x.join(path)))
+            .find(|x| x.is_file())
+    }
+
+    /// Merges the settings of a file on top of these settings: list entries with the same key (the package,
+    /// version and source of overrides, the library of library ignores and the directory and OS of vcpkg
+    /// ignores) are replaced, other entries are appended. Other settings are replaced if they are set in the file.
+    fn merge(&mut self, other: Self) {
+        let is_set = |key: &str| other.origins.contains_key(key);
+        if is_set("use_vcpkg_default_ignores") {
+            self.use_vcpkg_default_ignores = other.use_vcpkg_default_ignores;
+        }
+        if is_set("vcpkg_root") {
+            self.vcpkg_root = other.vcpkg_root.clone();
+        }
+        if is_set("vcpkg_triplet") {
+            self.vcpkg_triplet = other.vcpkg_triplet.clone();
+        }
+        if is_set("vcpkg_host_triplet") {
+            self.vcpkg_host_triplet = other.vcpkg_host_triplet.clone();
+        }
+        if is_set("target_os") {
+            self.target_os = other.target_os;
+        }
+        self.origins.extend(other.origins);
+        merge_entries(&mut self.overrides, other.overrides, |x| {
+            (x.package.clone(), x.version.clone(), x.source.clone())
+        });
+        merge_entries(&mut self.vcpkg_ignores, other.vcpkg_ignores, |x| {
+            (x.directory.clone(), x.os.clone())
+        });
+        merge_entries(&mut self.lib_ignores, other.lib_ignores, |x| x.lib.clone());
+    }
+
+    /// JSON Schema of the settings file, for editors to validate and complete settings files.
+    pub fn json_schema() -> Result<String> {
+        serde_json::to_string_pretty(&schemars::schema_for!(Self)).map_err(|source| Error::Serialize {
+            what: "settings schema".to_owned(),
+            source: source.into(),
+        })
+    }
+
+    /// Formats the effective settings as YAML, annotated with the settings file each entry is taken from.
+    pub fn describe(&self) -> Result<String> {
+        let mut out = String::new();
+        let value = serde_yaml_bw::to_value(self).map_err(|source| Error::Serialize {
+            what: "settings".to_owned(),
+            source: source.into(),
+        })?;
+        let serde_yaml_bw::Value::Mapping(mapping) = value else {
+            return Err(Error::Serialize {
+                what: "settings".to_owned(),
+                source: "The settings are no mapping".into(),
+            });
+        };
+        for (key, value) in mapping.iter() {
+            let Some(key) = key.as_str() else {
+                continue;
+            };
+            match key {
+                "overrides" => write_entries(&mut out, key, &self.overrides, |x| x.origin.as_ref())?,
+                "vcpkg_ignores" => write_entries(&mut out, key, &self.vcpkg_ignores, |x| x.origin.as_ref())?,
+                "lib_ignores" => write_entries(&mut out, key, &self.lib_ignores, |x| x.origin.as_ref())?,
+                _ if value.is_null() => {}
+                _ => {
+                    let yaml = to_yaml(value)?;
+                    let origin = match self.origins.get(key) {
+                        Some(origin) => origin.to_string_lossy().into_owned(),
+                        None => "default".to_owned(),
+                    };
+                    out.push_str(&format!("{key}: {} # {origin}\n", yaml.trim_end()));
+                }
+            }
+        }
+        Ok(out)
     }
 }
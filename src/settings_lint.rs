@@ -0,0 +1,215 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::settings::{OverrideMatches, Settings};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Error => f.write_str("error"),
+            Self::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+/// A problem found in the settings, `origin` is the settings file of the entry.
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub severity: Severity,
+    pub origin: Option<PathBuf>,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.origin {
+            Some(origin) => write!(f, "{}: {}: {}", self.severity, origin.to_string_lossy(), self.message),
+            None => write!(f, "{}: {}", self.severity, self.message),
+        }
+    }
+}
+
+fn is_valid_license_expression(license: &str) -> bool {
+    spdx::Expression::parse(license).is_ok()
+}
+
+fn is_valid_license_id(id: &str) -> bool {
+    spdx::license_id(id).is_some() || id.starts_with("LicenseRef-")
+}
+
+/// Searches a file or directory with the given name, like the ignore list of the package scan matches it.
+fn contains_file_name(dir: &Path, name: &str) -> bool {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        let path = entry.path();
+        entry.file_name().to_string_lossy() == name
+            || (path.is_dir() && !path.is_symlink() && contains_file_name(&path, name))
+    })
+}
+
+/// Reports entries whose key occurs more than once.
+fn duplicates<T>(
+    problems: &mut Vec<Problem>,
+    what: &str,
+    entries: &[T],
+    key: impl Fn(&T) -> String,
+    origin: impl Fn(&T) -> Option<&PathBuf>,
+) {
+    for (index, entry) in entries.iter().enumerate() {
+        if entries[..index].iter().any(|x| key(x) == key(entry)) {
+            problems.push(Problem {
+                severity: Severity::Error,
+                origin: origin(entry).cloned(),
+                message: format!("Duplicate {what} {}", key(entry)),
+            });
+        }
+    }
+}
+
+/// Checks the settings after a scan of the package in `package_root`: overrides that did not match any package
+/// of the scan, library ignores that do not match any file of the package, invalid package selectors, version
+/// requirements and SPDX license ids, missing license files, missing comments and duplicate entries. Unused overrides are only reported if the matches of
+/// the scan are known.
+pub fn lint(settings: &Settings, package_root: &Path, override_matches: Option<&OverrideMatches>) -> Vec<Problem> {
+    let mut problems = vec![];
+    let mut problem = |severity: Severity, origin: Option<&PathBuf>, message: String| {
+        problems.push(Problem {
+            severity,
+            origin: origin.cloned(),
+            message,
+        })
+    };
+
+    for (index, x) in settings.overrides.iter().enumerate() {
+        let origin = x.origin.as_ref();
+        if override_matches.is_some_and(|matches| !matches.contains_key(&index)) {
+            problem(
+                Severity::Warning,
+                origin,
+                format!("Override '{}' does not match any package", x.package),
+            );
+        }
+        if let Err(message) = x.validate() {
+            problem(Severity::Error, origin, message);
+        }
+        if let Some(license_id) = &x.license_id
+            && !is_valid_license_expression(license_id)
+        {
+            problem(
+                Severity::Error,
+                origin,
+                format!(
+                    "Override '{}' has an invalid SPDX license expression '{license_id}'",
+                    x.package
+                ),
+            );
+        }
+        for license_file in &x.license_files {
+            if let Some(id) = &license_file.id
+                && !is_valid_license_id(id)
+            {
+                problem(
+                    Severity::Error,
+                    origin,
+                    format!(
+                        "License file {} of override '{}' has an invalid SPDX license id '{id}'",
+                        license_file.file, x.package
+                    ),
+                );
+            }
+            if !Path::new(&license_file.file).is_file() {
+                problem(
+                    Severity::Error,
+                    origin,
+                    format!(
+                        "License file {} of override '{}' does not exist",
+                        license_file.file, x.package
+                    ),
+                );
+            }
+        }
+        if x.comment.trim().is_empty() {
+            problem(
+                Severity::Warning,
+                origin,
+                format!("Override '{}' has no comment", x.package),
+            );
+        }
+    }
+
+    for x in &settings.lib_ignores {
+        let origin = x.origin.as_ref();
+        if !contains_file_name(package_root, &x.lib) {
+            problem(
+                Severity::Warning,
+                origin,
+                format!("Ignored library '{}' does not exist in the package", x.lib),
+            );
+        }
+        if x.comment.trim().is_empty() {
+            problem(
+                Severity::Warning,
+                origin,
+                format!("Ignored library '{}' has no comment", x.lib),
+            );
+        }
+    }
+
+    for x in &settings.vcpkg_ignores {
+        if x.comment.trim().is_empty() {
+            problem(
+                Severity::Warning,
+                x.origin.as_ref(),
+                format!("Ignored vcpkg directory '{}' has no comment", x.directory),
+            );
+        }
+    }
+
+    duplicates(
+        &mut problems,
+        "override",
+        &settings.overrides,
+        |x| x.description(),
+        |x| x.origin.as_ref(),
+    );
+    duplicates(
+        &mut problems,
+        "library ignore",
+        &settings.lib_ignores,
+        |x| format!("'{}'", x.lib),
+        |x| x.origin.as_ref(),
+    );
+    duplicates(
+        &mut problems,
+        "vcpkg ignore",
+        &settings.vcpkg_ignores,
+        |x| match &x.os {
+            Some(os) => format!("'{}' os '{os}'", x.directory),
+            None => format!("'{}'", x.directory),
+        },
+        |x| x.origin.as_ref(),
+    );
+
+    problems.sort_by_key(|x| x.severity);
+    problems
+}
@@ -0,0 +1,339 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use log::*;
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, OnceLock},
+};
+
+use crate::{
+    apk_info,
+    binary_info::BinaryInfo,
+    error::{Error, Result},
+    file_info::FileInfo,
+    ld_so::LibraryResolver,
+    license_detector::{LicenseDetector, LicenseFile},
+    license_info::{self, LicenseInfo},
+    package_source::PackageSource,
+    pacman_info,
+    rpm_info::{PackageDB, RpmCommand, RpmDatabase},
+    settings,
+};
+
+/// Directory containing the license texts of installed packages, one subdirectory per package.
+const LICENSES_DIR: &str = "usr/share/licenses";
+const LICENSE_FILE_PATTERNS: [&str; 3] = ["COPY", "LICENSE", "License"];
+
+/// Package manager of the system the libraries are installed from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    Rpm,
+    /// Alpine Linux package manager
+    Apk,
+    /// Arch Linux package manager
+    Pacman,
+}
+
+impl PackageManager {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Rpm => "rpm",
+            Self::Apk => "apk",
+            Self::Pacman => "pacman",
+        }
+    }
+
+    /// Detects the package manager by its database in `root`, rpm is used if no other database is found.
+    pub fn detect(root: &Path) -> Self {
+        if root.join(apk_info::INSTALLED_DB_FILE).is_file() {
+            Self::Apk
+        } else if root.join(pacman_info::LOCAL_DB_DIR).is_dir() {
+            Self::Pacman
+        } else {
+            Self::Rpm
+        }
+    }
+}
+
+impl FromStr for PackageManager {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "rpm" => Ok(Self::Rpm),
+            "apk" => Ok(Self::Apk),
+            "pacman" => Ok(Self::Pacman),
+            _ => Err(anyhow::anyhow!(
+                "Unknown package manager '{s}', supported are 'rpm', 'apk' and 'pacman'"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PackageManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A package of the apk or pacman database, the files are relative to the root directory of the system.
+#[derive(Debug, Clone)]
+pub struct SystemPackage {
+    pub name: String,
+    pub version: Option<String>,
+    pub license: Option<String>,
+    pub url: Option<String>,
+    pub files: Vec<String>,
+}
+
+impl SystemPackage {
+    /// Collects the license texts in `usr/share/licenses/<package>` and the license files of the package.
+    fn license_files(&self, root: &Path) -> Vec<LicenseFile> {
+        let mut files: Vec<PathBuf> = vec![];
+        if let Ok(entries) = fs::read_dir(root.join(LICENSES_DIR).join(&self.name)) {
+            files.extend(
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.is_file()),
+            );
+        }
+        for file in &self.files {
+            let path = root.join(file);
+            let is_license_file = Path::new(file)
+                .file_name()
+                .is_some_and(|name| LICENSE_FILE_PATTERNS.iter().any(|p| name.to_string_lossy().contains(p)));
+            if is_license_file && path.is_file() && !files.contains(&path) {
+                files.push(path);
+            }
+        }
+        files.sort();
+        files
+            .iter()
+            .map(|file| LicenseFile {
+                id: None,
+                file: file.to_string_lossy().into_owned(),
+            })
+            .collect()
+    }
+
+    pub fn license_info(
+        &self,
+        root: &Path,
+        manager: PackageManager,
+        lib_info: FileInfo,
+        overrides: &settings::Overrides,
+        detector: &LicenseDetector,
+    ) -> Result<LicenseInfo> {
+        let override_info =
+            settings::Override::find_override(manager.name(), &self.name, self.version.as_deref(), &[], overrides);
+        let (license, license_files) =
+            license_info::apply_override(override_info, self.license.clone(), self.license_files(root));
+
+        let license_expression = license
+            .as_deref()
+            .and_then(|license| license_info::parse_license_expression(&self.name, license));
+        let license_ids = license_info::license_ids(license_expression.as_ref());
+
+        let license_texts = detector.detect_licenses(&self.name, license_ids.as_slice(), license_files.as_slice())?;
+
+        let license = match license {
+            Some(license) => license,
+            None => {
+                warn!(
+                    "Missing license identifier for {manager} package '{}', please add an override",
+                    self.name
+                );
+                "NOASSERTION".to_owned()
+            }
+        };
+
+        Ok(LicenseInfo {
+            lib_info,
+            package_name: self.name.clone(),
+            license,
+            license_expression,
+            license_texts,
+            version: self.version.clone(),
+            url: self.url.clone(),
+            features: vec![],
+            dependencies: vec![],
+            binaries: vec![],
+        })
+    }
+}
+
+/// The installed packages of the system, rpm packages are queried from the rpm database when they are first
+/// needed, apk and pacman databases are read from the root directory.
+#[derive(Debug)]
+pub struct SystemPackages {
+    pub manager: PackageManager,
+    pub root: PathBuf,
+    packages: Vec<SystemPackage>,
+    /// Resolves the libraries of apk and pacman packages, rpm packages are resolved by their database
+    resolver: LibraryResolver,
+    rpm_database: Arc<dyn RpmDatabase>,
+    rpm_packages: OnceLock<PackageDB>,
+}
+
+fn unique_package(packages: Vec<&SystemPackage>, query: String) -> Result<Option<&SystemPackage>> {
+    if packages.len() > 1 {
+        return Err(Error::AmbiguousPackage {
+            query,
+            packages: packages.iter().map(|pkg| pkg.name.clone()).collect(),
+        });
+    }
+    Ok(packages.first().copied())
+}
+
+impl SystemPackages {
+    /// Opens the package database of `manager` in `root`, rpm packages are queried with `rpm --root`.
+    pub fn open(manager: PackageManager, root: &Path) -> anyhow::Result<Self> {
+        let packages = match manager {
+            PackageManager::Rpm => vec![],
+            PackageManager::Apk => apk_info::read_installed_packages(root)?,
+            PackageManager::Pacman => pacman_info::read_installed_packages(root)?,
+        };
+        if manager != PackageManager::Rpm {
+            info!("Found {} installed {manager} packages in {root:?}", packages.len());
+        }
+        Ok(Self {
+            manager,
+            root: root.to_path_buf(),
+            packages,
+            resolver: LibraryResolver::new(root),
+            rpm_database: Arc::new(RpmCommand::new(root)),
+            rpm_packages: OnceLock::new(),
+        })
+    }
+
+    /// The rpm packages of a database, e.g. an [`RpmFixture`](crate::rpm_info::RpmFixture) of a test.
+    pub fn rpm(database: Arc<dyn RpmDatabase>) -> Self {
+        Self {
+            manager: PackageManager::Rpm,
+            root: database.root(),
+            packages: vec![],
+            resolver: LibraryResolver::default(),
+            rpm_database: database,
+            rpm_packages: OnceLock::new(),
+        }
+    }
+
+    /// The installed rpm packages, the database is read on the first query. If reading fails, it is read
+    /// again on the next query.
+    pub fn rpm_packages(&self) -> Result<&PackageDB> {
+        if let Some(db) = self.rpm_packages.get() {
+            return Ok(db);
+        }
+        let db = PackageDB::load(self.rpm_database.as_ref())?;
+        info!("Found {} installed rpm packages in {:?}", db.len(), self.root);
+        Ok(self.rpm_packages.get_or_init(|| db))
+    }
+
+    /// Finds the package owning the library the dynamic loader would load for a library of the package, it is
+    /// looked up by its SONAME and its name. Distributions without multilib directories keep 64-bit libraries
+    /// in `lib`, the class of the library is not considered.
+    fn package_of_lib(&self, lib: &FileInfo) -> Result<Option<&SystemPackage>> {
+        let soname = BinaryInfo::read(&lib.path).ok().flatten().and_then(|x| x.name);
+        for name in soname.iter().chain(std::iter::once(&lib.name)) {
+            let Some(path) = self.resolver.resolve(name, None) else {
+                continue;
+            };
+            if let Some(package) = self.package_of_file(&path)? {
+                return Ok(Some(package));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Finds the package owning a file by its exact path, symbolic links are followed if no package owns the
+    /// link.
+    fn package_of_file(&self, path: &str) -> Result<Option<&SystemPackage>> {
+        for path in self
+            .resolver
+            .symlink_chain(&format!("/{}", path.trim_start_matches('/')))
+        {
+            let relative_path = path.trim_start_matches('/');
+            let packages = self
+                .packages
+                .iter()
+                .filter(|pkg| {
+                    pkg.files
+                        .iter()
+                        .any(|file| file.trim_start_matches('/') == relative_path)
+                })
+                .collect::<Vec<_>>();
+            if let Some(package) = unique_package(packages, format!("the file '{path}'"))? {
+                return Ok(Some(package));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Name of the package owning a file, `path` is relative to the root directory.
+    pub fn package_name_of_file(&self, path: &str) -> Result<Option<String>> {
+        match self.manager {
+            PackageManager::Rpm => Ok(self.rpm_packages()?.query_by_file(path)?.map(|x| x.name().to_owned())),
+            PackageManager::Apk | PackageManager::Pacman => Ok(self.package_of_file(path)?.map(|x| x.name.clone())),
+        }
+    }
+
+    pub fn license_info(
+        &self,
+        package_name: &str,
+        lib_info: FileInfo,
+        overrides: &settings::Overrides,
+        detector: &LicenseDetector,
+    ) -> Result<LicenseInfo> {
+        let missing_package = || Error::UnknownPackage {
+            kind: self.manager.name().to_owned(),
+            package: package_name.to_owned(),
+        };
+        match self.manager {
+            PackageManager::Rpm => self
+                .rpm_packages()?
+                .query_by_name(package_name)
+                .ok_or_else(missing_package)?
+                .license_info(lib_info, overrides, detector),
+            PackageManager::Apk | PackageManager::Pacman => self
+                .packages
+                .iter()
+                .find(|pkg| pkg.name == package_name)
+                .ok_or_else(missing_package)?
+                .license_info(&self.root, self.manager, lib_info, overrides, detector),
+        }
+    }
+}
+
+impl PackageSource for SystemPackages {
+    fn name(&self) -> &str {
+        self.manager.name()
+    }
+
+    fn package_of_file(&self, file: &FileInfo) -> Result<Option<String>> {
+        match self.manager {
+            PackageManager::Rpm => self.rpm_packages()?.package_of_file(file),
+            PackageManager::Apk | PackageManager::Pacman => Ok(self.package_of_lib(file)?.map(|x| x.name.clone())),
+        }
+    }
+
+    fn license_info(
+        &self,
+        package: &str,
+        file: &FileInfo,
+        overrides: &settings::Overrides,
+        detector: &LicenseDetector,
+    ) -> Result<LicenseInfo> {
+        SystemPackages::license_info(self, package, file.clone(), overrides, detector)
+    }
+}
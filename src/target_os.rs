@@ -0,0 +1,96 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{fmt, path::Path, str::FromStr};
+
+use crate::binary_info::BinaryFormat;
+
+/// Operating system the analyzed package is built for, independent of the host running sancus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetOs {
+    Linux,
+    Windows,
+    Macos,
+}
+
+impl TargetOs {
+    /// The operating system sancus is running on, Linux for all unsupported systems.
+    pub fn host() -> Self {
+        std::env::consts::OS.parse().unwrap_or(Self::Linux)
+    }
+
+    /// Name of the operating system as used by `std::env::consts::OS`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Linux => "linux",
+            Self::Windows => "windows",
+            Self::Macos => "macos",
+        }
+    }
+
+    /// Extension of the shared libraries of the operating system.
+    pub fn library_extension(&self) -> &'static str {
+        match self {
+            Self::Linux => "so",
+            Self::Windows => "dll",
+            Self::Macos => "dylib",
+        }
+    }
+
+    /// Whether the file name is a shared library of the operating system. Linux libraries end with `.so`
+    /// or a version like `.so.1.2`, the extensions of Windows and macOS libraries are case insensitive.
+    pub fn is_library(&self, name: &str) -> bool {
+        match self {
+            Self::Linux => {
+                name.ends_with(".so")
+                    || name.rsplit_once(".so.").is_some_and(|(_, version)| {
+                        version
+                            .split('.')
+                            .all(|x| !x.is_empty() && x.chars().all(|c| c.is_ascii_digit()))
+                    })
+            }
+            Self::Windows | Self::Macos => Path::new(name)
+                .extension()
+                .is_some_and(|x| x.eq_ignore_ascii_case(self.library_extension())),
+        }
+    }
+
+    pub fn binary_format(&self) -> BinaryFormat {
+        match self {
+            Self::Linux => BinaryFormat::Elf,
+            Self::Windows => BinaryFormat::Pe,
+            Self::Macos => BinaryFormat::MachO,
+        }
+    }
+}
+
+impl FromStr for TargetOs {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "linux" => Ok(Self::Linux),
+            "windows" => Ok(Self::Windows),
+            "macos" | "osx" | "darwin" => Ok(Self::Macos),
+            _ => Err(anyhow::anyhow!(
+                "Unknown target OS '{s}', supported are 'linux', 'windows' and 'macos'"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for TargetOs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
@@ -8,10 +8,13 @@
 //
 // SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
 
-use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 
+use crate::error::{Error, Result};
+
+#[cfg(feature = "scan")]
+use crate::file_info;
 #[cfg(feature = "scan")]
 use crate::license_info::LicenseInfo;
 #[cfg(feature = "scan")]
@@ -31,6 +34,12 @@ pub struct ThirdPartyLibrary {
     pub package_version: String,
     pub license: String,
     pub licenses: Vec<License>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub binaries: Vec<String>,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
@@ -42,45 +51,37 @@ pub struct ThirdPartyLicenses {
 
 impl ThirdPartyLicenses {
     pub fn load(file: &Path) -> Result<Self> {
-        let str = std::fs::read_to_string(file).with_context(|| {
-            format!(
-                "Cannot read third party licenses from file {}",
-                file.to_str().unwrap_or("empty")
-            )
-        })?;
-        serde_json::from_str::<Self>(str.as_str()).with_context(|| {
-            format!(
-                "Cannot parse third party licenses from file {}",
-                file.to_str().unwrap_or("empty")
-            )
+        let str = std::fs::read_to_string(file).map_err(Error::io(file))?;
+        serde_json::from_str::<Self>(str.as_str()).map_err(|source| Error::Json {
+            path: file.to_path_buf(),
+            source,
         })
     }
 
     #[cfg(feature = "scan")]
     pub fn save(&self, file_path: &Path) -> Result<()> {
         // Create all parent directories if the don't exist:
-        let parent_dir = file_path.parent().unwrap_or_else(|| {
-            panic!(
-                "Cannot get parent directory of third party file {}",
-                file_path.to_string_lossy().into_owned()
-            )
-        });
-        std::fs::create_dir_all(parent_dir)?;
+        if let Some(parent_dir) = file_path.parent() {
+            std::fs::create_dir_all(parent_dir).map_err(Error::io(parent_dir))?;
+        }
         // Write json file:
-        let str = serde_json::to_string_pretty(self).with_context(|| "Cannot serialize third party licenses")?;
-        std::fs::write(file_path, str).with_context(|| {
-            format!(
-                "Cannot serialize third party licenses to file {}",
-                file_path.to_str().unwrap_or("empty")
-            )
-        })
+        let str = serde_json::to_string_pretty(self).map_err(|source| Error::Json {
+            path: file_path.to_path_buf(),
+            source,
+        })?;
+        std::fs::write(file_path, str).map_err(Error::io(file_path))
     }
 
     #[cfg(feature = "scan")]
-    pub fn apply_overrides(&mut self, overrides: &[settings::Override]) -> Result<()> {
+    pub fn apply_overrides(&mut self, source: &str, overrides: &settings::Overrides) -> Result<()> {
         for package in &mut self.third_party_libraries {
-            if let Some(license_override) = settings::Override::find_override(&package.package_name, overrides)
-                && let Some(license_id) = &license_override.license_id
+            if let Some(license_override) = settings::Override::find_override(
+                source,
+                &package.package_name,
+                Some(&package.package_version),
+                &package.features,
+                overrides,
+            ) && let Some(license_id) = &license_override.license_id
             {
                 package.license = license_id.clone();
 
@@ -98,21 +99,16 @@ impl ThirdPartyLicenses {
     pub fn export(&self, result_dir: &Path) -> Result<()> {
         let base_path = result_dir.join(self.root_name.clone());
         for package in &self.third_party_libraries {
-            let pkg_dir = base_path.join(package.package_name.clone());
+            let pkg_dir = base_path.join(file_info::package_dir_name(&package.package_name));
 
-            std::fs::create_dir_all(pkg_dir.as_path())?;
+            std::fs::create_dir_all(pkg_dir.as_path()).map_err(Error::io(&pkg_dir))?;
 
             let pkg_desc_file = pkg_dir.join("README.txt");
             let pkg_desc = format!(
                 "Package: {}\nVersion: {}\nLicense: {}\n",
                 package.package_name, package.package_version, package.license
             );
-            std::fs::write(pkg_desc_file.as_path(), pkg_desc).with_context(|| {
-                format!(
-                    "Cannot write package description to file {}",
-                    pkg_desc_file.to_string_lossy()
-                )
-            })?;
+            std::fs::write(pkg_desc_file.as_path(), pkg_desc).map_err(Error::io(&pkg_desc_file))?;
 
             for license_text in &package.licenses {
                 // Workaround to fix unusual license ID: "License specified in file ($CARGO_HOME/registry/src/.../LICENSE)"
@@ -120,12 +116,8 @@ impl ThirdPartyLicenses {
                 let file_name = file_name.replace('$', "-");
                 let license_text_file = pkg_dir.join(format!("{file_name}.txt"));
 
-                std::fs::write(license_text_file.as_path(), license_text.text.clone()).with_context(|| {
-                    format!(
-                        "Cannot write license text to file {}",
-                        license_text_file.to_string_lossy()
-                    )
-                })?;
+                std::fs::write(license_text_file.as_path(), license_text.text.clone())
+                    .map_err(Error::io(&license_text_file))?;
             }
         }
         Ok(())
@@ -150,6 +142,9 @@ impl ThirdPartyLicenses {
                 package_version: info.version.clone().unwrap_or_default(),
                 license: info.license.clone(),
                 licenses,
+                features: info.features.clone(),
+                dependencies: info.dependencies.clone(),
+                binaries: info.binaries.clone(),
             });
         }
 
@@ -175,6 +170,20 @@ impl ThirdPartyLicenses {
             }
             pkg_tree.push(license_tree);
 
+            if !lib.features.is_empty() {
+                pkg_tree.push(termtree::Tree::new(format!("Features: {}", lib.features.join(", "))));
+            }
+            if !lib.dependencies.is_empty() {
+                let mut dependency_tree = termtree::Tree::new("Dependencies:".to_owned());
+                for dependency in &lib.dependencies {
+                    dependency_tree.push(termtree::Tree::new(dependency.clone()));
+                }
+                pkg_tree.push(dependency_tree);
+            }
+            if !lib.binaries.is_empty() {
+                pkg_tree.push(termtree::Tree::new(format!("Binaries: {}", lib.binaries.join(", "))));
+            }
+
             root_tree.push(pkg_tree);
         }
 
@@ -8,29 +8,37 @@
 //
 // SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
 
-use anyhow::{Context, Result};
 use log::*;
 use spdx_rs::models::PackageInformation;
 use std::{fs, path::Path};
 
+use crate::error::{Error, Result};
 use crate::file_info::FileInfo;
 use crate::license_detector::{LicenseDetector, LicenseFile};
-use crate::license_info::LicenseInfo;
+use crate::license_info::{self, LicenseInfo};
+use crate::package_source::PackageSource;
 use crate::settings;
+use crate::vcpkg_status::{self, VcpkgStatus};
+
+/// Name of the source of vcpkg ports, e.g. in overrides
+pub const VCPKG_SOURCE: &str = "vcpkg";
 
 const SPDX_ID_PORT: &str = "SPDXRef-port";
 const SPDX_ID_BINARY: &str = "SPDXRef-binary";
 const SPDX_ID_RESOURCE_1: &str = "SPDXRef-resource-1";
 
 fn parse_spdx_file(file: &Path) -> Result<spdx_rs::models::SPDX> {
-    let spdx_string = fs::read_to_string(file)?;
+    let spdx_string = fs::read_to_string(file).map_err(Error::io(file))?;
     let clean = spdx_string
         .lines()
         .filter(|x| !x.trim_start().starts_with("\"$schema\":"))
         .collect::<Vec<_>>();
     let spdx_string_clean = clean.join("\n");
 
-    serde_json::from_str(spdx_string_clean.as_ref()).context(format!("Failed to parse SPDX JSON file {file:?}"))
+    serde_json::from_str(spdx_string_clean.as_ref()).map_err(|source| Error::Json {
+        path: file.to_path_buf(),
+        source,
+    })
 }
 
 fn find_package_by_spdx_id<'a>(
@@ -70,14 +78,25 @@ fn find_license(packages: &[spdx_rs::models::PackageInformation]) -> Option<Stri
     None
 }
 
+/// Returns the triplet of a `vcpkg.spdx.json` file located in `<triplet>/share/<port>/`.
+fn triplet_of_port_dir(port_dir: &Path) -> Option<String> {
+    port_dir
+        .parent()
+        .and_then(Path::parent)
+        .and_then(Path::file_name)
+        .map(|x| x.to_string_lossy().into_owned())
+}
+
 pub fn get_license_info(
     vcpkg_spdx_files: &Vec<FileInfo>,
-    overrides: &[settings::Override],
+    status: &VcpkgStatus,
+    overrides: &settings::Overrides,
+    detector: &LicenseDetector,
 ) -> Result<Vec<LicenseInfo>> {
     let mut licenses = vec![];
 
     for file in vcpkg_spdx_files {
-        let directory = file.path.parent().unwrap();
+        let directory = file.path.parent().unwrap_or(Path::new(""));
         let spdx_result = parse_spdx_file(file.path.as_path())?;
         let packages = spdx_result.package_information.as_ref();
 
@@ -87,27 +106,24 @@ pub fn get_license_info(
             find_package_by_spdx_id(SPDX_ID_RESOURCE_1, packages).map(|pkg| pkg.package_download_location.clone());
 
         if let Some(pkg) = find_package_by_spdx_id(SPDX_ID_PORT, packages) {
-            let override_info = settings::Override::find_override(&pkg.package_name, overrides);
-
-            let license = if override_info.is_some_and(|x| x.license_id.is_some()) {
-                override_info.unwrap().license_id.clone()
-            } else {
-                found_license
+            let port = triplet_of_port_dir(directory)
+                .and_then(|triplet| status.find_port(&pkg.package_name, triplet.as_str()));
+            let (features, dependencies) = match port {
+                Some(port) => (port.enabled_features(), port.all_dependencies()),
+                None => (vcpkg_status::features_from_abi_info(directory), vec![]),
             };
 
+            let override_info = settings::Override::find_override(
+                VCPKG_SOURCE,
+                &pkg.package_name,
+                pkg.package_version.as_deref(),
+                &features,
+                overrides,
+            );
+
             // Find license file for package:
             let copyright_file = directory.join("copyright");
-            let license_files = if override_info.is_some_and(|x| !x.license_files.is_empty()) {
-                override_info
-                    .unwrap()
-                    .license_files
-                    .iter()
-                    .map(|license_file| LicenseFile {
-                        id: license_file.id.clone(),
-                        file: license_file.file.clone(),
-                    })
-                    .collect()
-            } else if copyright_file.exists() && copyright_file.is_file() {
+            let copyright_files = if copyright_file.is_file() {
                 vec![LicenseFile {
                     id: None,
                     file: copyright_file.to_string_lossy().into_owned(),
@@ -115,53 +131,20 @@ pub fn get_license_info(
             } else {
                 vec![]
             };
+            let (license, license_files) = license_info::apply_override(override_info, found_license, copyright_files);
 
-            let license = match license {
-                Some(license) => license,
-                None => {
-                    return Err(anyhow::anyhow!(
-                        "Missing license identifier for VCPKG package {}",
-                        pkg.package_name
-                    ));
-                }
-            };
-
-            let license_expression = if !license.is_empty() {
-                match spdx::Expression::parse(license.as_str()).context(format!(
-                    "Cannot parse license expression for package {}",
-                    pkg.package_name
-                )) {
-                    Ok(expr) => Some(expr),
-                    Err(error) => {
-                        warn!("{error:?}");
-                        None
-                    }
-                }
-            } else {
-                None
+            let Some(license) = license else {
+                return Err(Error::MissingLicense {
+                    kind: "vcpkg".to_owned(),
+                    package: pkg.package_name.clone(),
+                });
             };
 
-            let license_ids = if let Some(expr) = &license_expression {
-                let mut collection = vec![];
-                expr.iter()
-                    .map(|l| match l {
-                        spdx::expression::ExprNode::Req(req) => Some(req),
-                        spdx::expression::ExprNode::Op(_op) => None,
-                    })
-                    .for_each(|expr| {
-                        if let Some(expr) = expr
-                            && let Some(license) = &expr.req.license.id()
-                        {
-                            collection.push(*license);
-                        }
-                    });
-                collection
-            } else {
-                vec![]
-            };
+            let license_expression = license_info::parse_strict_license_expression(&pkg.package_name, &license);
+            let license_ids = license_info::license_ids(license_expression.as_ref());
 
             // Detect license ids of license texts:
-            let license_texts = LicenseDetector::instance().detect_licenses(
+            let license_texts = detector.detect_licenses(
                 pkg.package_name.as_str(),
                 license_ids.as_slice(),
                 license_files.as_slice(),
@@ -180,6 +163,9 @@ pub fn get_license_info(
                 license_texts,
                 version: pkg.package_version.clone(),
                 url,
+                features,
+                dependencies,
+                binaries: vec![],
             })
         }
     }
@@ -232,11 +218,81 @@ fn parse_package(pkg: spdx_rs::models::PackageInformation) -> termtree::Tree<Str
     {
         found_license = Some(format!("concluded license: {license}"));
     }
-    if found_license.is_none() {
-        found_license = Some("FOUND NO LICENSE!".to_owned());
+    sub_tree.push(termtree::Tree::new(
+        found_license.unwrap_or_else(|| "FOUND NO LICENSE!".to_owned()),
+    ));
+
+    sub_tree
+}
+
+/// The ports of a vcpkg installation as [`PackageSource`], libraries are attributed to the port that installed
+/// the library with the same name.
+#[derive(Debug, Clone)]
+pub struct VcpkgPorts {
+    status: VcpkgStatus,
+    /// Libraries of the vcpkg installation
+    libs: Vec<FileInfo>,
+    /// License information of the ports as collected by [`get_license_info`]
+    licenses: Vec<LicenseInfo>,
+}
+
+impl VcpkgPorts {
+    pub fn new(status: VcpkgStatus, libs: Vec<FileInfo>, licenses: Vec<LicenseInfo>) -> Self {
+        Self { status, libs, licenses }
+    }
+}
+
+impl PackageSource for VcpkgPorts {
+    fn name(&self) -> &str {
+        VCPKG_SOURCE
     }
 
-    sub_tree.push(termtree::Tree::new(found_license.unwrap()));
+    fn package_of_file(&self, file: &FileInfo) -> Result<Option<String>> {
+        let mut libs = self.libs.iter().filter(|lib| lib.name == file.name).peekable();
+        if libs.peek().is_none() {
+            return Ok(None);
+        }
+        // Without the file lists of the status database the port is unknown, the library is reported by its name:
+        match libs.find_map(|lib| self.status.find_port_of_file(&lib.path)) {
+            Some(port) => Ok(Some(port.name.clone())),
+            None => {
+                debug!("Cannot find the vcpkg port that installed '{}'", file.name);
+                Ok(Some(file.name.clone()))
+            }
+        }
+    }
 
-    sub_tree
+    fn license_info(
+        &self,
+        package: &str,
+        file: &FileInfo,
+        overrides: &settings::Overrides,
+        _detector: &LicenseDetector,
+    ) -> Result<LicenseInfo> {
+        match self.licenses.iter().find(|info| info.package_name == package) {
+            Some(info) => Ok(LicenseInfo {
+                lib_info: file.clone(),
+                ..info.clone()
+            }),
+            None => {
+                let override_info = settings::Override::find_override(VCPKG_SOURCE, package, None, &[], overrides);
+                let (license, _) = license_info::apply_override(override_info, None, vec![]);
+                let license_expression = license
+                    .as_deref()
+                    .and_then(|license| license_info::parse_strict_license_expression(package, license));
+                Ok(LicenseInfo {
+                    lib_info: file.clone(),
+                    package_name: package.to_owned(),
+                    license: license.unwrap_or_else(|| "NOASSERTION".to_owned()),
+                    license_expression,
+                    license_texts: vec![],
+                    version: None,
+                    url: None,
+                    features: vec![],
+                    dependencies: vec![],
+                    binaries: vec![file.name.clone()],
+                })
+            }
+        }
+    }
 }
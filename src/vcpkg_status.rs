@@ -0,0 +1,388 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use anyhow::{Context, Result};
+use log::*;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const VCPKG_STATUS_DIR: &str = "vcpkg";
+const VCPKG_STATUS_FILE: &str = "status";
+const VCPKG_UPDATES_DIR: &str = "updates";
+const VCPKG_INFO_DIR: &str = "info";
+const VCPKG_LIST_EXTENSION: &str = ".list";
+const VCPKG_ABI_INFO_FILE: &str = "vcpkg_abi_info.txt";
+const CORE_FEATURE: &str = "core";
+
+#[derive(Debug, Clone, Default)]
+pub struct VcpkgFeature {
+    pub name: String,
+    /// Ports of the same triplet the feature depends on
+    pub dependencies: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VcpkgPort {
+    pub name: String,
+    pub version: String,
+    pub triplet: String,
+    /// Ports of the same triplet the core of the port depends on
+    pub dependencies: Vec<String>,
+    pub default_features: Vec<String>,
+    pub features: Vec<VcpkgFeature>,
+    /// Files installed by the port relative to the vcpkg installation, e.g. `x64-linux/share/fonts/x.ttf`
+    pub files: Vec<String>,
+}
+
+/// Installed ports of a vcpkg installation as recorded in `vcpkg/status` and `vcpkg/updates/*`.
+#[derive(Debug, Clone, Default)]
+pub struct VcpkgStatus {
+    /// The vcpkg installation, the files of the ports are relative to it
+    root: PathBuf,
+    ports: Vec<VcpkgPort>,
+}
+
+#[derive(Debug, Default)]
+struct Paragraph {
+    fields: Vec<(String, String)>,
+}
+
+impl Paragraph {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.fields.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    fn is_installed(&self) -> bool {
+        self.get("Status").is_some_and(|status| status.ends_with(" installed"))
+    }
+}
+
+fn parse_paragraphs(content: &str) -> Vec<Paragraph> {
+    let mut paragraphs = vec![];
+    let mut current = Paragraph::default();
+    for line in content.lines() {
+        if line.trim().is_empty() {
+            if !current.fields.is_empty() {
+                paragraphs.push(std::mem::take(&mut current));
+            }
+        } else if line.starts_with([' ', '\t']) {
+            // Continuation line of a multi line field:
+            if let Some((_, value)) = current.fields.last_mut() {
+                value.push('\n');
+                value.push_str(line.trim());
+            }
+        } else if let Some((key, value)) = line.split_once(':') {
+            current.fields.push((key.trim().to_owned(), value.trim().to_owned()));
+        }
+    }
+    if !current.fields.is_empty() {
+        paragraphs.push(current);
+    }
+    paragraphs
+}
+
+/// Parses a vcpkg dependency list like `zlib, x264:x64-linux, ffmpeg[core,avcodec]`.
+/// Dependencies on a different triplet are host dependencies (build tools) and self references
+/// of feature paragraphs are skipped.
+fn parse_dependencies(depends: &str, port: &str, triplet: &str) -> Vec<String> {
+    let mut dependencies = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    let mut items = vec![];
+    for (index, c) in depends.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(&depends[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&depends[start..]);
+
+    for item in items {
+        let item = item.trim();
+        if item.is_empty() {
+            continue;
+        }
+        let (name, dep_triplet) = match item.split_once(':') {
+            Some((name, dep_triplet)) => (name, Some(dep_triplet)),
+            None => (item, None),
+        };
+        if dep_triplet.is_some_and(|dep_triplet| dep_triplet != triplet) {
+            continue;
+        }
+        let name = name.split('[').next().unwrap_or(name).trim();
+        if name != port && !dependencies.iter().any(|d| d == name) {
+            dependencies.push(name.to_owned());
+        }
+    }
+    dependencies
+}
+
+/// Finds the port of a file list named `<port>_<version>_<triplet>`. Port names do not contain `_`, but versions
+/// and custom triplets may, so the name is matched against the known ports and triplets. If several triplets
+/// match, the list belongs to the port whose version starts the remaining part.
+fn find_port_of_list<'a>(ports: &'a mut [VcpkgPort], stem: &str) -> Option<&'a mut VcpkgPort> {
+    let version_of = |port: &VcpkgPort| {
+        stem.strip_prefix(port.name.as_str())?
+            .strip_prefix('_')?
+            .strip_suffix(port.triplet.as_str())?
+            .strip_suffix('_')
+            .filter(|version| !version.is_empty())
+    };
+    let mut candidates = ports
+        .iter_mut()
+        .filter(|port| version_of(port).is_some())
+        .collect::<Vec<_>>();
+    let index = candidates.iter().position(|port| {
+        let version = port.version.split('#').next().unwrap_or_default();
+        version_of(port).is_some_and(|x| x.starts_with(version))
+    });
+    match index {
+        Some(index) => Some(candidates.swap_remove(index)),
+        None => candidates.into_iter().next(),
+    }
+}
+
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+impl VcpkgPort {
+    /// Names of all installed features including the implicit `core` feature.
+    pub fn enabled_features(&self) -> Vec<String> {
+        let mut features = vec![CORE_FEATURE.to_owned()];
+        features.extend(self.features.iter().map(|f| f.name.clone()));
+        features
+    }
+
+    /// Direct dependencies of the port including the dependencies of all installed features, the edges of the
+    /// port in the dependency tree of its triplet.
+    pub fn all_dependencies(&self) -> Vec<String> {
+        let mut dependencies = self.dependencies.clone();
+        for feature in &self.features {
+            for dependency in &feature.dependencies {
+                if !dependencies.contains(dependency) {
+                    dependencies.push(dependency.clone());
+                }
+            }
+        }
+        dependencies
+    }
+}
+
+impl VcpkgStatus {
+    pub fn status_dir(vcpkg_installation: &Path) -> PathBuf {
+        vcpkg_installation.join(VCPKG_STATUS_DIR)
+    }
+
+    /// Loads the status database of a `vcpkg_installed` directory.
+    /// An installation without status database results in an empty status.
+    pub fn load(vcpkg_installation: &Path) -> Result<Self> {
+        let status_dir = Self::status_dir(vcpkg_installation);
+        let status_file = status_dir.join(VCPKG_STATUS_FILE);
+        if !status_file.is_file() {
+            warn!("Cannot find vcpkg status database {status_file:?}, dependencies and features are not reported");
+            return Ok(Self {
+                root: vcpkg_installation.to_path_buf(),
+                ports: vec![],
+            });
+        }
+
+        let mut files = vec![status_file];
+        let updates_dir = status_dir.join(VCPKG_UPDATES_DIR);
+        if updates_dir.is_dir() {
+            let mut updates = fs::read_dir(&updates_dir)
+                .with_context(|| format!("Cannot read vcpkg status updates {updates_dir:?}"))?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_file())
+                .collect::<Vec<_>>();
+            updates.sort();
+            files.extend(updates);
+        }
+
+        let mut paragraphs: Vec<Paragraph> = vec![];
+        for file in files {
+            let content = fs::read_to_string(&file).with_context(|| format!("Cannot read vcpkg status {file:?}"))?;
+            for paragraph in parse_paragraphs(&content) {
+                // Later entries supersede earlier entries of the same package, feature and triplet:
+                let key = |p: &Paragraph| {
+                    [p.get("Package"), p.get("Feature"), p.get("Architecture")].map(|x| x.map(str::to_owned))
+                };
+                let new_key = key(&paragraph);
+                paragraphs.retain(|p| key(p) != new_key);
+                paragraphs.push(paragraph);
+            }
+        }
+
+        let mut ports: Vec<VcpkgPort> = vec![];
+        for paragraph in paragraphs
+            .iter()
+            .filter(|p| p.is_installed() && p.get("Feature").is_none())
+        {
+            let (Some(name), Some(triplet)) = (paragraph.get("Package"), paragraph.get("Architecture")) else {
+                continue;
+            };
+            let version = match paragraph.get("Port-Version") {
+                Some(port_version) if port_version != "0" => {
+                    format!("{}#{port_version}", paragraph.get("Version").unwrap_or_default())
+                }
+                _ => paragraph.get("Version").unwrap_or_default().to_owned(),
+            };
+            ports.push(VcpkgPort {
+                name: name.to_owned(),
+                version,
+                triplet: triplet.to_owned(),
+                dependencies: parse_dependencies(paragraph.get("Depends").unwrap_or_default(), name, triplet),
+                default_features: split_list(paragraph.get("Default-Features").unwrap_or_default()),
+                features: vec![],
+                files: vec![],
+            });
+        }
+        for paragraph in paragraphs.iter().filter(|p| p.is_installed()) {
+            let (Some(name), Some(feature), Some(triplet)) = (
+                paragraph.get("Package"),
+                paragraph.get("Feature"),
+                paragraph.get("Architecture"),
+            ) else {
+                continue;
+            };
+            if let Some(port) = ports.iter_mut().find(|p| p.name == name && p.triplet == triplet) {
+                port.features.push(VcpkgFeature {
+                    name: feature.to_owned(),
+                    dependencies: parse_dependencies(paragraph.get("Depends").unwrap_or_default(), name, triplet),
+                });
+            } else {
+                warn!("Found feature '{feature}' of port '{name}:{triplet}' without installed port");
+            }
+        }
+
+        // Files of the ports are listed in `info/<port>_<version>_<triplet>.list`:
+        let info_dir = status_dir.join(VCPKG_INFO_DIR);
+        if info_dir.is_dir() {
+            for entry in fs::read_dir(&info_dir).with_context(|| format!("Cannot read vcpkg info {info_dir:?}"))? {
+                let path = entry?.path();
+                let file_name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                let Some(stem) = file_name.strip_suffix(VCPKG_LIST_EXTENSION) else {
+                    continue;
+                };
+                if let Some(port) = find_port_of_list(&mut ports, stem) {
+                    port.files = fs::read_to_string(&path)
+                        .with_context(|| format!("Cannot read vcpkg file list {path:?}"))?
+                        .lines()
+                        .filter(|line| !line.is_empty() && !line.ends_with('/'))
+                        .map(str::to_owned)
+                        .collect();
+                }
+            }
+        }
+
+        Ok(Self {
+            root: vcpkg_installation.to_path_buf(),
+            ports,
+        })
+    }
+
+    pub fn ports(&self) -> &[VcpkgPort] {
+        &self.ports
+    }
+
+    pub fn find_port(&self, name: &str, triplet: &str) -> Option<&VcpkgPort> {
+        self.ports.iter().find(|p| p.name == name && p.triplet == triplet)
+    }
+
+    /// Finds the port that installed a file of the vcpkg installation, e.g. `<root>/x64-linux/lib/libz.so.1`.
+    /// Relative paths are relative to the vcpkg installation.
+    pub fn find_port_of_file(&self, path: &Path) -> Option<&VcpkgPort> {
+        let path = path.strip_prefix(&self.root).unwrap_or(path).to_string_lossy();
+        self.ports
+            .iter()
+            .find(|port| port.files.iter().any(|file| *file == path))
+    }
+
+    /// Finds the port that installed a file relative to the directory of its triplet, e.g. `share/fonts/x.ttf`
+    /// for `x64-linux/share/fonts/x.ttf`.
+    pub fn find_port_of_triplet_file(&self, path: &str) -> Option<&VcpkgPort> {
+        self.ports.iter().find(|port| {
+            port.files
+                .iter()
+                .any(|file| file.split_once('/').is_some_and(|(_, file)| file == path))
+        })
+    }
+
+    #[cfg(feature = "debug")]
+    pub fn print(&self) {
+        let mut root_tree = termtree::Tree::new("vcpkg dependency tree".to_owned());
+        let is_dependency = |port: &VcpkgPort| {
+            self.ports
+                .iter()
+                .any(|p| p.triplet == port.triplet && p.all_dependencies().contains(&port.name))
+        };
+        for port in self.ports.iter().filter(|p| !is_dependency(p)) {
+            root_tree.push(self.dependency_tree(port, &mut vec![]));
+        }
+        for line in root_tree.to_string().lines() {
+            debug!("{line}")
+        }
+    }
+
+    #[cfg(feature = "debug")]
+    fn dependency_tree(&self, port: &VcpkgPort, visited: &mut Vec<String>) -> termtree::Tree<String> {
+        let mut tree = termtree::Tree::new(format!(
+            "{}[{}]:{}@{}",
+            port.name,
+            port.enabled_features().join(","),
+            port.triplet,
+            port.version
+        ));
+        visited.push(port.name.clone());
+        for dependency in port.all_dependencies() {
+            if visited.contains(&dependency) {
+                continue;
+            }
+            match self.find_port(&dependency, &port.triplet) {
+                Some(dependency) => tree.push(self.dependency_tree(dependency, visited)),
+                None => tree.push(termtree::Tree::new(format!("{dependency} (not installed)"))),
+            };
+        }
+        visited.pop();
+        tree
+    }
+}
+
+/// Reads the enabled features from the `vcpkg_abi_info.txt` file of a port share directory.
+/// This is used as fallback if the port is missing in the status database.
+pub fn features_from_abi_info(port_share_dir: &Path) -> Vec<String> {
+    let abi_info_file = port_share_dir.join(VCPKG_ABI_INFO_FILE);
+    let Ok(content) = fs::read_to_string(&abi_info_file) else {
+        return vec![];
+    };
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("features "))
+        .map(|features| {
+            features
+                .split(';')
+                .map(str::trim)
+                .filter(|x| !x.is_empty())
+                .map(str::to_owned)
+                .collect()
+        })
+        .unwrap_or_default()
+}
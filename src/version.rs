@@ -0,0 +1,212 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+use anyhow::Result;
+use std::cmp::Ordering;
+
+/// Compares two version strings segment by segment like `rpmvercmp`: numeric segments are compared as
+/// numbers, alphabetic segments as strings and numeric segments are newer than alphabetic ones.
+/// `~` sorts before anything (`1.0~rc1 < 1.0`), `^` sorts after the version it follows (`1.0^git1 > 1.0`).
+fn compare_segments(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+    let is_separator = |c: char| !c.is_ascii_alphanumeric() && c != '~' && c != '^';
+    let (mut a, mut b) = (a, b);
+    loop {
+        a = a.trim_start_matches(is_separator);
+        b = b.trim_start_matches(is_separator);
+
+        match (a.strip_prefix('~'), b.strip_prefix('~')) {
+            (Some(rest_a), Some(rest_b)) => {
+                (a, b) = (rest_a, rest_b);
+                continue;
+            }
+            (Some(_), None) => return Ordering::Less,
+            (None, Some(_)) => return Ordering::Greater,
+            (None, None) => {}
+        }
+        match (a.strip_prefix('^'), b.strip_prefix('^')) {
+            (Some(rest_a), Some(rest_b)) => {
+                (a, b) = (rest_a, rest_b);
+                continue;
+            }
+            (Some(_), None) => {
+                return if b.is_empty() {
+                    Ordering::Greater
+                } else {
+                    Ordering::Less
+                };
+            }
+            (None, Some(_)) => {
+                return if a.is_empty() {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                };
+            }
+            (None, None) => {}
+        }
+        if a.is_empty() || b.is_empty() {
+            break;
+        }
+
+        let is_numeric = a.starts_with(|c: char| c.is_ascii_digit());
+        let segment_end = |s: &str| {
+            s.find(|c: char| {
+                if is_numeric {
+                    !c.is_ascii_digit()
+                } else {
+                    !c.is_ascii_alphabetic()
+                }
+            })
+            .unwrap_or(s.len())
+        };
+        let (segment_a, rest_a) = a.split_at(segment_end(a));
+        let (segment_b, rest_b) = b.split_at(segment_end(b));
+        // Segments of different types: numeric segments are newer
+        if segment_b.is_empty() {
+            return if is_numeric { Ordering::Greater } else { Ordering::Less };
+        }
+        let ordering = if is_numeric {
+            let segment_a = segment_a.trim_start_matches('0');
+            let segment_b = segment_b.trim_start_matches('0');
+            segment_a
+                .len()
+                .cmp(&segment_b.len())
+                .then_with(|| segment_a.cmp(segment_b))
+        } else {
+            segment_a.cmp(segment_b)
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+        (a, b) = (rest_a, rest_b);
+    }
+    match (a.is_empty(), b.is_empty()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, _) => Ordering::Greater,
+    }
+}
+
+/// Splits an RPM `[epoch:]version[-release]` string.
+fn split_evr(evr: &str) -> (u64, &str, Option<&str>) {
+    let (epoch, rest) = match evr.split_once(':') {
+        Some((epoch, rest)) if epoch.chars().all(|c| c.is_ascii_digit()) => (epoch.parse().unwrap_or(0), rest),
+        _ => (0, evr),
+    };
+    match rest.rsplit_once('-') {
+        Some((version, release)) => (epoch, version, Some(release)),
+        None => (epoch, rest, None),
+    }
+}
+
+/// Compares two versions like rpm compares `epoch:version-release`, plain and semantic versions are
+/// compared the same way. The release is only compared if both versions have one.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    let (epoch_a, version_a, release_a) = split_evr(a.trim());
+    let (epoch_b, version_b, release_b) = split_evr(b.trim());
+    epoch_a
+        .cmp(&epoch_b)
+        .then_with(|| compare_segments(version_a, version_b))
+        .then_with(|| match (release_a, release_b) {
+            (Some(release_a), Some(release_b)) => compare_segments(release_a, release_b),
+            _ => Ordering::Equal,
+        })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+/// A version requirement, a comma separated list of comparisons that all have to match, e.g. `>=5.15, <6`.
+/// `^1.2` and `~1.2` select compatible versions like Cargo: `>=1.2, <2` and `>=1.2, <1.3`.
+#[derive(Debug, Clone)]
+pub struct VersionConstraint {
+    comparisons: Vec<(Operator, String)>,
+}
+
+/// Upper bound of a caret (`^`) or tilde (`~`) requirement.
+fn next_version(version: &str, is_caret: bool) -> String {
+    let parts = version
+        .split(['.', '-', '+'])
+        .map(|x| x.parse::<u64>().unwrap_or(0))
+        .take(3)
+        .collect::<Vec<_>>();
+    let major = parts.first().copied().unwrap_or(0);
+    let minor = parts.get(1).copied();
+    match (is_caret, minor) {
+        (true, _) if major > 0 => format!("{}", major + 1),
+        (true, Some(minor)) if minor > 0 || parts.len() < 3 => format!("0.{}", minor + 1),
+        (true, Some(minor)) => format!("0.{minor}.{}", parts.get(2).copied().unwrap_or(0) + 1),
+        (false, Some(minor)) => format!("{major}.{}", minor + 1),
+        (_, None) => format!("{}", major + 1),
+    }
+}
+
+impl VersionConstraint {
+    pub fn parse(constraint: &str) -> Result<Self> {
+        let mut comparisons = vec![];
+        for comparison in constraint
+            .split(',')
+            .map(str::trim)
+            .filter(|x| !x.is_empty() && *x != "*")
+        {
+            let (operator, version) = [
+                (">=", Operator::GreaterEqual),
+                ("<=", Operator::LessEqual),
+                ("!=", Operator::NotEqual),
+                ("==", Operator::Equal),
+                (">", Operator::Greater),
+                ("<", Operator::Less),
+                ("=", Operator::Equal),
+            ]
+            .iter()
+            .find_map(|(prefix, operator)| comparison.strip_prefix(prefix).map(|x| (*operator, x.trim())))
+            .unwrap_or((Operator::Equal, comparison));
+
+            if let Some(version) = version.strip_prefix(['^', '~']) {
+                let version = version.trim();
+                if version.is_empty() {
+                    anyhow::bail!("Missing version in requirement '{comparison}'");
+                }
+                comparisons.push((Operator::GreaterEqual, version.to_owned()));
+                comparisons.push((Operator::Less, next_version(version, comparison.starts_with('^'))));
+                continue;
+            }
+            if version.is_empty() {
+                anyhow::bail!("Missing version in requirement '{comparison}'");
+            }
+            comparisons.push((operator, version.to_owned()));
+        }
+        Ok(Self { comparisons })
+    }
+
+    pub fn matches(&self, version: &str) -> bool {
+        self.comparisons.iter().all(|(operator, required)| {
+            let ordering = compare(version, required);
+            match operator {
+                Operator::Equal => ordering == Ordering::Equal,
+                Operator::NotEqual => ordering != Ordering::Equal,
+                Operator::Less => ordering == Ordering::Less,
+                Operator::LessEqual => ordering != Ordering::Greater,
+                Operator::Greater => ordering == Ordering::Greater,
+                Operator::GreaterEqual => ordering != Ordering::Less,
+            }
+        })
+    }
+}
@@ -0,0 +1,127 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+#![cfg(feature = "scan")]
+
+use std::path::{Path, PathBuf};
+
+use sancus_lib::{
+    asset_info::{self, Asset, AssetKind},
+    license_detector::{LicenseDetector, TemplateSet},
+    settings::{Override, Overrides},
+    vcpkg_status::VcpkgStatus,
+};
+
+fn package_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/assets/package")
+}
+
+fn assets() -> Vec<Asset> {
+    let mut assets = asset_info::find_assets(&package_dir(), &[]).unwrap();
+    assets.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+    assets
+}
+
+fn font_names(relative_path: &str) -> asset_info::FontNames {
+    asset_info::read_font_names(&std::fs::read(package_dir().join(relative_path)).unwrap()).unwrap()
+}
+
+#[test]
+fn fonts_icon_themes_and_icu_data_are_found() {
+    let assets = assets()
+        .into_iter()
+        .map(|x| (x.kind, x.name, x.relative_path))
+        .collect::<Vec<_>>();
+    // The index.theme outside of an icons directory is no icon theme:
+    assert_eq!(
+        assets,
+        [
+            (
+                AssetKind::Font,
+                "DejaVu Sans".to_owned(),
+                "usr/share/fonts/DejaVuSans.ttc".to_owned()
+            ),
+            (
+                AssetKind::Font,
+                "broken.otf".to_owned(),
+                "usr/share/fonts/broken.otf".to_owned()
+            ),
+            (
+                AssetKind::Font,
+                "Noto Sans".to_owned(),
+                "usr/share/fonts/noto/NotoSans-Regular.ttf".to_owned()
+            ),
+            (
+                AssetKind::IconTheme,
+                "Adwaita".to_owned(),
+                "usr/share/icons/Adwaita/index.theme".to_owned()
+            ),
+            (
+                AssetKind::Data,
+                "icudt74l.dat".to_owned(),
+                "usr/share/icu/74.2/icudt74l.dat".to_owned()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn english_windows_names_are_preferred() {
+    let names = font_names("usr/share/fonts/noto/NotoSans-Regular.ttf");
+    assert_eq!(names.family.as_deref(), Some("Noto Sans"));
+    assert_eq!(names.version.as_deref(), Some("Version 2.013"));
+    assert_eq!(
+        names.copyright.as_deref(),
+        Some("Copyright 2022 The Noto Project Authors")
+    );
+    assert_eq!(names.license_url.as_deref(), Some("https://openfontlicense.org"));
+    assert_eq!(names.license().as_deref(), Some("OFL-1.1"));
+}
+
+#[test]
+fn first_font_of_a_collection_is_read() {
+    let names = font_names("usr/share/fonts/DejaVuSans.ttc");
+    assert_eq!(names.family.as_deref(), Some("DejaVu Sans"));
+    assert_eq!(names.license_url, None);
+    // Without URL the license is taken from the description:
+    assert_eq!(names.license().as_deref(), Some("Bitstream-Vera"));
+
+    assert!(asset_info::read_font_names(b"not a font").is_none());
+}
+
+#[test]
+fn assets_without_owner_are_reported_with_their_metadata() {
+    let overrides = Overrides::new(vec![Override {
+        package: "Adwaita".to_owned(),
+        source: Some("assets".to_owned()),
+        license_id: Some("LGPL-3.0-only OR CC-BY-SA-3.0".to_owned()),
+        ..Default::default()
+    }]);
+    let licenses = asset_info::get_license_info(
+        &assets(),
+        &VcpkgStatus::default(),
+        &[],
+        None,
+        &overrides,
+        &LicenseDetector::builder().template_set(TemplateSet::None).build(),
+    )
+    .unwrap();
+
+    let license = |name: &str| {
+        let info = licenses.iter().find(|x| x.package_name == name).unwrap();
+        (info.license.as_str(), info.version.as_deref())
+    };
+    assert_eq!(license("Noto Sans"), ("OFL-1.1", Some("Version 2.013")));
+    assert_eq!(license("DejaVu Sans"), ("Bitstream-Vera", None));
+    assert_eq!(license("broken.otf"), ("NOASSERTION", None));
+    // The icon theme index has no license, it is taken from the override:
+    assert_eq!(license("Adwaita"), ("LGPL-3.0-only OR CC-BY-SA-3.0", None));
+    assert_eq!(licenses.len(), 5);
+}
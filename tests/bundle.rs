@@ -0,0 +1,114 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+#![cfg(feature = "scan")]
+
+use std::path::{Path, PathBuf};
+
+use sancus_lib::{
+    bundle::{Bundle, BundleKind},
+    file_info::FileInfo,
+    license_detector::{LicenseDetector, TemplateSet},
+    settings::Overrides,
+};
+
+fn fixture_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/bundle")
+}
+
+fn open(name: &str) -> Bundle {
+    Bundle::open(&fixture_dir().join(name), None).unwrap()
+}
+
+#[test]
+fn bundle_kinds_are_detected() {
+    assert_eq!(BundleKind::detect(&fixture_dir().join("flatpak")), BundleKind::Flatpak);
+    assert_eq!(BundleKind::detect(&fixture_dir().join("snap")), BundleKind::Snap);
+    assert_eq!(BundleKind::detect(&fixture_dir()), BundleKind::Directory);
+    assert_eq!("AppDir".parse::<BundleKind>().unwrap(), BundleKind::Directory);
+    assert!("deb".parse::<BundleKind>().is_err());
+}
+
+#[test]
+fn flatpak_metadata_is_read() {
+    let bundle = open("flatpak");
+    assert_eq!(bundle.name.as_deref(), Some("org.example.Viewer"));
+    assert_eq!(bundle.runtime.as_deref(), Some("org.freedesktop.Platform/x86_64/23.08"));
+    assert_eq!(bundle.root, fixture_dir().join("flatpak/files"));
+
+    // Runtimes are described by the `[Runtime]` section:
+    let runtime = open("flatpak-runtime");
+    assert_eq!(runtime.name.as_deref(), Some("org.example.Platform"));
+    assert_eq!(runtime.runtime.as_deref(), Some("org.example.Platform/x86_64/1.0"));
+}
+
+#[test]
+fn libraries_not_shipped_are_provided_by_the_runtime() {
+    let provided = ["libc.so.6".to_owned(), "libz.so.1".to_owned()];
+    let info = open("flatpak").runtime_license_info(&provided).unwrap();
+    assert_eq!(info.package_name, "org.freedesktop.Platform/x86_64/23.08");
+    assert_eq!(info.license, "NOASSERTION");
+    assert_eq!(info.binaries, provided);
+
+    let directory = Bundle::open(&fixture_dir(), None).unwrap();
+    assert!(directory.runtime_license_info(&provided).is_none());
+}
+
+#[test]
+fn snap_metadata_and_staged_packages_are_read() {
+    let bundle = open("snap");
+    assert_eq!(bundle.name.as_deref(), Some("viewer"));
+    assert_eq!(bundle.version.as_deref(), Some("2.1"));
+    assert_eq!(bundle.runtime.as_deref(), Some("core22"));
+    assert_eq!(bundle.root, fixture_dir().join("snap"));
+    // Packages staged by several parts are reported once:
+    let staged = bundle
+        .staged_packages
+        .iter()
+        .map(|x| (x.name.as_str(), x.version.as_deref()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        staged,
+        [
+            ("libzstd1", Some("1.4.8+dfsg-3build1")),
+            ("fonts-dejavu-core", Some("2.37-2build1")),
+            ("libexif12", None),
+        ]
+    );
+
+    // Without base the snap runs on core, without manifest no packages are staged:
+    let minimal = open("snap-minimal");
+    assert_eq!(minimal.runtime.as_deref(), Some("core"));
+    assert!(minimal.staged_packages.is_empty());
+}
+
+#[test]
+fn snap_libraries_are_attributed_to_staged_packages() {
+    let bundle = open("snap");
+    let lib_dir = fixture_dir().join("snap/usr/lib/x86_64-linux-gnu");
+    let libs =
+        ["libzstd.so.1", "libviewer-plugins.so.2"].map(|name| FileInfo::new(name.to_owned(), &lib_dir.join(name)));
+    let detector = LicenseDetector::builder().template_set(TemplateSet::None).build();
+    let licenses = bundle.license_info(&libs, &Overrides::default(), &detector).unwrap();
+
+    let names = licenses.iter().map(|x| x.package_name.as_str()).collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        ["libzstd1", "fonts-dejavu-core", "libexif12", "libviewer-plugins.so.2"]
+    );
+    // The license of the machine readable copyright file:
+    assert_eq!(licenses[0].license, "BSD-3-Clause OR GPL-2.0-only");
+    assert_eq!(licenses[0].binaries, ["libzstd.so.1"]);
+    assert_eq!(licenses[0].license_texts.len(), 1);
+    // Packages without copyright file and libraries without package are reported for review:
+    assert_eq!(licenses[1].license, "NOASSERTION");
+    assert_eq!(licenses[3].license, "NOASSERTION");
+    assert_eq!(licenses[3].binaries, ["libviewer-plugins.so.2"]);
+}
@@ -0,0 +1,81 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+#![cfg(feature = "scan")]
+
+use std::path::{Path, PathBuf};
+
+use sancus_lib::{
+    cargo_auditable::{self, DependencyKind},
+    file_info::FileInfo,
+    license_detector::{LicenseDetector, TemplateSet},
+    settings::Overrides,
+};
+
+fn fixture_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/cargo_auditable")
+}
+
+fn binary(name: &str) -> FileInfo {
+    FileInfo::new(name.to_owned(), &fixture_dir().join(name))
+}
+
+#[test]
+fn dependency_list_is_read_from_the_section() {
+    let packages = cargo_auditable::read_dependencies(&fixture_dir().join("app"))
+        .unwrap()
+        .unwrap();
+    let names = packages.iter().map(|x| x.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["app", "serde", "cc", "applib", "itoa"]);
+    assert!(packages[0].root);
+    assert_eq!(packages[1].dependencies, [4, 2]);
+    assert_eq!(packages[2].kind, DependencyKind::Build);
+    assert_eq!(packages[4].kind, DependencyKind::Runtime);
+
+    assert!(
+        cargo_auditable::read_dependencies(&fixture_dir().join("no-dependencies"))
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[test]
+fn too_large_dependency_lists_are_rejected() {
+    let error = cargo_auditable::read_dependencies(&fixture_dir().join("too-large")).unwrap_err();
+    assert!(error.to_string().contains("too large"), "{error:?}");
+}
+
+#[test]
+fn only_runtime_dependencies_of_registry_crates_are_reported() {
+    let licenses = cargo_auditable::get_license_info(
+        &[binary("app"), binary("too-large"), binary("no-dependencies")],
+        &[fixture_dir().join("registry")],
+        &Overrides::default(),
+        &LicenseDetector::builder().template_set(TemplateSet::None).build(),
+    )
+    .unwrap();
+
+    // The root and the local crate are first party code, cc is a build dependency:
+    let names = licenses.iter().map(|x| x.package_name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["serde", "itoa"]);
+
+    let serde = &licenses[0];
+    assert_eq!(serde.license, "MIT OR Apache-2.0");
+    assert_eq!(serde.url.as_deref(), Some("https://github.com/serde-rs/serde"));
+    // The indices of the dependencies are mapped to the crates, build dependencies are skipped:
+    assert_eq!(serde.dependencies, ["itoa"]);
+    assert_eq!(serde.binaries, ["app"]);
+
+    // Crates without sources are reported without license:
+    let itoa = &licenses[1];
+    assert_eq!(itoa.license, "NOASSERTION");
+    assert_eq!(itoa.version.as_deref(), Some("1.0.11"));
+    assert!(itoa.dependencies.is_empty());
+}
@@ -0,0 +1,115 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+#![cfg(feature = "scan")]
+
+use std::path::{Path, PathBuf};
+
+use sancus_lib::{
+    conan_info::{self, ConanPackage},
+    file_info::FileInfo,
+    license_detector::{LicenseDetector, TemplateSet},
+    license_info::LicenseInfo,
+    settings::Overrides,
+    target_os::TargetOs,
+};
+
+fn fixture_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/conan")
+}
+
+fn conan_home() -> PathBuf {
+    fixture_dir().join("home")
+}
+
+fn read_packages(name: &str) -> Vec<ConanPackage> {
+    conan_info::read_packages(&fixture_dir().join(name), Some(&conan_home())).unwrap()
+}
+
+fn license_info(packages: &[ConanPackage], detector: &LicenseDetector) -> Vec<LicenseInfo> {
+    let package_libs = ["libz.so.1", "libssl.so.3", "libapp.so.1"]
+        .map(|name| FileInfo::new(name.to_owned(), &fixture_dir().join(name)));
+    conan_info::get_license_info(
+        packages,
+        &package_libs,
+        TargetOs::Linux,
+        &Overrides::default(),
+        detector,
+    )
+    .unwrap()
+}
+
+#[test]
+fn graph_info_packages_are_read_with_their_recipe_metadata() {
+    let packages = read_packages("graph.json");
+    let names = packages.iter().map(|x| x.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["openssl", "zlib", "cmake"]);
+    assert!(packages[2].is_build_context);
+
+    let detector = LicenseDetector::builder().template_set(TemplateSet::None).build();
+    let infos = license_info(&packages, &detector);
+    // The build requirement is not shipped:
+    assert_eq!(infos.len(), 2);
+
+    let openssl = &infos[0];
+    assert_eq!(openssl.version.as_deref(), Some("3.0.13"));
+    assert_eq!(openssl.license, "Apache-2.0");
+    // The homepage is preferred over the URL of the recipe:
+    assert_eq!(openssl.url.as_deref(), Some("https://github.com/openssl/openssl"));
+    assert_eq!(openssl.features, ["no_asm=False", "shared=True"]);
+    assert_eq!(openssl.dependencies, ["zlib"]);
+    assert_eq!(openssl.binaries, ["libssl.so.3"]);
+    assert!(openssl.license_texts.is_empty());
+
+    // The package folder is found in the cache, the recipe license is a list:
+    let zlib = &infos[1];
+    assert_eq!(zlib.version.as_deref(), Some("1.3.1"));
+    assert_eq!(zlib.license, "Zlib");
+    assert_eq!(zlib.features, ["shared=true"]);
+    assert_eq!(zlib.binaries, ["libz.so.1"]);
+    assert_eq!(zlib.license_texts.len(), 1);
+    assert!(zlib.lib_info.path.ends_with("home/p/zlib1a2b3c/p/conaninfo.txt"));
+}
+
+#[test]
+fn lock_packages_are_completed_by_the_package_folders_of_the_cache() {
+    let packages = read_packages("conan.lock");
+    let names = packages.iter().map(|x| x.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["zlib", "openssl", "boost", "cmake"]);
+    // References with user and channel:
+    assert_eq!(packages[1].version.as_deref(), Some("3.0.13"));
+    assert!(packages[3].is_build_context);
+    // The package folder of the cache is only used if it is unique:
+    assert!(packages[0].package_folder.is_some());
+    assert!(packages[2].package_folder.is_none());
+
+    // The lock has no licenses, they are detected from the license files of the package:
+    let detector = LicenseDetector::builder()
+        .template_set(TemplateSet::Ids(vec!["Zlib".to_owned()]))
+        .build();
+    let infos = license_info(&packages, &detector);
+    assert_eq!(infos.len(), 3);
+
+    let zlib = &infos[0];
+    assert_eq!(zlib.license, "Zlib");
+    assert_eq!(zlib.features, ["shared=True"]);
+    assert!(zlib.dependencies.is_empty());
+
+    // Options and requirements are read from conaninfo.txt:
+    let openssl = &infos[1];
+    assert_eq!(openssl.license, "NOASSERTION");
+    assert_eq!(openssl.features, ["no_asm=False", "shared=True"]);
+    assert_eq!(openssl.dependencies, ["zlib"]);
+    assert_eq!(openssl.binaries, ["libssl.so.3"]);
+
+    let boost = &infos[2];
+    assert_eq!(boost.license, "NOASSERTION");
+    assert!(boost.binaries.is_empty());
+}
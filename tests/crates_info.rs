@@ -0,0 +1,55 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+#![cfg(feature = "scan")]
+
+use std::path::{Path, PathBuf};
+
+use sancus_lib::{
+    crates_info::{self, CargoTarget},
+    license_detector::{LicenseDetector, TemplateSet},
+    license_info::LicenseInfo,
+    settings::Overrides,
+    target_os::TargetOs,
+};
+
+fn fixture_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/crates")
+}
+
+fn license_info() -> Vec<LicenseInfo> {
+    crates_info::get_license_info(
+        &fixture_dir().join("project/Cargo.lock"),
+        &[fixture_dir().join("vendor")],
+        &CargoTarget::new(TargetOs::Linux, None),
+        &Overrides::default(),
+        &LicenseDetector::builder().template_set(TemplateSet::None).build(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn crate_is_described_by_its_manifest() {
+    let licenses = license_info();
+    let itoa = licenses.iter().find(|x| x.package_name == "itoa").unwrap();
+    assert_eq!(itoa.license, "MIT OR Apache-2.0");
+    assert_eq!(itoa.lib_info.name, "Cargo.toml");
+    assert_eq!(itoa.lib_info.path, fixture_dir().join("vendor/itoa-1.0.15/Cargo.toml"));
+}
+
+#[test]
+fn crate_without_sources_has_no_assertion() {
+    let licenses = license_info();
+    let missing = licenses.iter().find(|x| x.package_name == "missing").unwrap();
+    assert_eq!(missing.license, "NOASSERTION");
+    assert_eq!(missing.version.as_deref(), Some("2.0.0"));
+    assert_eq!(missing.lib_info.name, "Cargo.lock");
+    assert_eq!(missing.lib_info.path, fixture_dir().join("project/Cargo.lock"));
+}
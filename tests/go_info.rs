@@ -0,0 +1,119 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+#![cfg(feature = "scan")]
+
+use std::path::{Path, PathBuf};
+
+use sancus_lib::go_info::{GoBuildInfo, GoModule};
+
+fn fixture_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/go")
+}
+
+const MODINFO_START: &[u8] = b"\x30\x77\xaf\x0c\x92\x74\x08\x02\x41\xe1\xc1\x07\xe6\xd6\x18\xe6";
+const MODINFO_END: &[u8] = b"\xf9\x32\x43\x31\x86\x18\x20\x72\x00\x82\x42\x10\x41\x16\xd8\xf2";
+
+fn varint(mut value: usize) -> Vec<u8> {
+    let mut bytes = vec![];
+    while value >= 0x80 {
+        bytes.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+    bytes
+}
+
+/// Build information of Go 1.18 and later with the given flags, the strings follow the 32 byte header.
+fn buildinfo(flags: u8, modinfo: &str) -> Vec<u8> {
+    let mut data = b"\xff Go buildinf:".to_vec();
+    data.extend([8, flags]);
+    data.resize(32, 0);
+    data.extend(varint(8));
+    data.extend(b"go1.22.4");
+    let modinfo = [MODINFO_START, modinfo.as_bytes(), MODINFO_END].concat();
+    data.extend(varint(modinfo.len()));
+    data.extend(modinfo);
+    data
+}
+
+fn module(path: &str, version: &str) -> GoModule {
+    GoModule {
+        path: path.to_owned(),
+        version: version.to_owned(),
+    }
+}
+
+#[test]
+fn replacements_replace_the_preceding_module() {
+    let modinfo = "path\texample.com/app/cmd/app\n\
+                   mod\texample.com/app\tv1.2.0\th1:app=\n\
+                   =>\texample.com/fork\tv1.2.1\th1:fork=\n\
+                   dep\tgolang.org/x/text\tv0.14.0\th1:text=\n\
+                   =>\tgolang.org/x/text\tv0.15.0\th1:text15=\n\
+                   dep\tgithub.com/pkg/errors\tv0.9.1\th1:err=\n\
+                   =>\t../errors\t\n";
+    let info = GoBuildInfo::parse(&buildinfo(2, modinfo)).unwrap().unwrap();
+    assert_eq!(info.go_version, "go1.22.4");
+    assert_eq!(info.path.as_deref(), Some("example.com/app/cmd/app"));
+    assert_eq!(info.main, Some(module("example.com/fork", "v1.2.1")));
+    // A local replacement has no version:
+    assert_eq!(
+        info.dependencies,
+        [module("golang.org/x/text", "v0.15.0"), module("../errors", "")]
+    );
+}
+
+#[test]
+fn build_information_before_go_1_18_is_unsupported() {
+    let error = GoBuildInfo::parse(&buildinfo(0, "")).unwrap_err();
+    assert!(error.to_string().contains("before 1.18"), "{error}");
+}
+
+#[test]
+fn truncated_build_information_is_an_error() {
+    let data = buildinfo(2, "path\texample.com/app\n");
+    // The varint of the version length does not end:
+    let mut truncated = data[..32].to_vec();
+    truncated.extend([0x80; 11]);
+    assert!(
+        GoBuildInfo::parse(&truncated)
+            .unwrap_err()
+            .to_string()
+            .contains("Truncated")
+    );
+    // The module information is shorter than its length:
+    assert!(
+        GoBuildInfo::parse(&data[..data.len() - 1])
+            .unwrap_err()
+            .to_string()
+            .contains("Truncated")
+    );
+}
+
+#[test]
+fn binaries_without_sections_are_searched_entirely() {
+    let mut data = vec![0; 64];
+    data.extend(buildinfo(2, "mod\texample.com/app\tv1.0.0\t\n"));
+    let info = GoBuildInfo::parse(&data).unwrap().unwrap();
+    assert_eq!(info.main, Some(module("example.com/app", "v1.0.0")));
+
+    assert!(GoBuildInfo::parse(&[0; 64]).unwrap().is_none());
+}
+
+#[test]
+fn build_information_is_read_from_its_section() {
+    let info = GoBuildInfo::read(&fixture_dir().join("app")).unwrap().unwrap();
+    assert_eq!(info.main, Some(module("example.com/app", "v1.2.0")));
+    assert_eq!(info.dependencies, [module("github.com/pkg/errors", "v0.9.1")]);
+
+    // The build information of a Go binary embedded into another section is not the one of the binary:
+    assert!(GoBuildInfo::read(&fixture_dir().join("embedding")).unwrap().is_none());
+}
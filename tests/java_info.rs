@@ -0,0 +1,169 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+#![cfg(feature = "scan")]
+
+use std::io::{Cursor, Write};
+
+use sancus_lib::java_info::{JavaLibrary, read_jar};
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+fn jar(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut writer = ZipWriter::new(Cursor::new(vec![]));
+    for (name, content) in files {
+        // Stored files, the tests can find and change their content in the archive:
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        writer.start_file(*name, options).unwrap();
+        writer.write_all(content).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+fn pom(group_id: &str, artifact_id: &str, licenses: &[&str]) -> Vec<u8> {
+    let licenses = licenses
+        .iter()
+        .map(|name| format!("<license><name>{name}</name></license>"))
+        .collect::<String>();
+    format!(
+        "<project><groupId>{group_id}</groupId><artifactId>{artifact_id}</artifactId><version>2.0.1</version>\
+         <licenses>{licenses}</licenses></project>"
+    )
+    .into_bytes()
+}
+
+fn read(data: &[u8]) -> Vec<JavaLibrary> {
+    let mut libraries = vec![];
+    read_jar(data, "app.jar", &mut libraries).unwrap();
+    libraries
+}
+
+fn library<'a>(libraries: &'a [JavaLibrary], name: &str) -> &'a JavaLibrary {
+    libraries.iter().find(|x| x.name == name).unwrap()
+}
+
+#[test]
+fn text_files_with_invalid_utf8_are_read() {
+    let data = jar(&[
+        (
+            "META-INF/MANIFEST.MF",
+            b"Bundle-SymbolicName: org.example.lib\nBundle-Version: 1.2.0\nBundle-License: MIT\n",
+        ),
+        // Latin-1 encoded copyright holder:
+        ("META-INF/LICENSE", b"Copyright (c) J\xfcrgen M\xfcller\n"),
+        ("META-INF/COPYING", b"Permission is hereby granted\n"),
+    ]);
+    let mut libraries = vec![];
+    read_jar(&data, "lib.jar", &mut libraries).unwrap();
+
+    assert_eq!(libraries.len(), 1);
+    let library = &libraries[0];
+    assert_eq!(library.name, "org.example.lib");
+    assert_eq!(library.version.as_deref(), Some("1.2.0"));
+    assert_eq!(library.license.as_deref(), Some("MIT"));
+    assert_eq!(
+        library.license_texts,
+        [
+            "Copyright (c) J\u{fffd}rgen M\u{fffd}ller\n",
+            "Permission is hereby granted\n"
+        ]
+    );
+}
+
+#[test]
+fn pom_licenses_take_precedence_over_the_bundle_license() {
+    let manifest: &[u8] = b"Bundle-SymbolicName: org.example.lib\nBundle-License: MIT\n";
+    let with_licenses = pom("org.example", "lib", &["Apache License, Version 2.0"]);
+    let libraries = read(&jar(&[
+        ("META-INF/MANIFEST.MF", manifest),
+        ("META-INF/maven/org.example/lib/pom.xml", &with_licenses),
+    ]));
+    assert_eq!(libraries.len(), 1);
+    assert_eq!(libraries[0].name, "org.example:lib");
+    assert_eq!(libraries[0].license.as_deref(), Some("Apache-2.0"));
+
+    // Without licenses in the pom the manifest is used:
+    let without_licenses = pom("org.example", "lib", &[]);
+    let libraries = read(&jar(&[
+        ("META-INF/MANIFEST.MF", manifest),
+        ("META-INF/maven/org.example/lib/pom.xml", &without_licenses),
+    ]));
+    assert_eq!(libraries[0].license.as_deref(), Some("MIT"));
+    assert_eq!(libraries[0].version.as_deref(), Some("2.0.1"));
+}
+
+#[test]
+fn shaded_and_nested_jars_contain_several_libraries() {
+    let nested = jar(&[(
+        "META-INF/MANIFEST.MF",
+        b"Implementation-Title: nested\nImplementation-Version: 0.9\nBundle-License: BSD-3-Clause\n",
+    )]);
+    let app_pom = pom("org.example", "app", &[]);
+    let shaded_pom = pom(
+        "com.google.guava",
+        "guava",
+        &["The Apache Software License, Version 2.0"],
+    );
+    let libraries = read(&jar(&[
+        (
+            "META-INF/MANIFEST.MF",
+            b"Bundle-SymbolicName: app\nBundle-License: MIT\n",
+        ),
+        ("META-INF/LICENSE", b"MIT License\n"),
+        ("META-INF/maven/org.example/app/pom.xml", &app_pom),
+        ("META-INF/maven/com.google.guava/guava/pom.xml", &shaded_pom),
+        ("BOOT-INF/lib/nested.jar", &nested),
+    ]));
+
+    let names = libraries.iter().map(|x| x.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["org.example:app", "com.google.guava:guava", "nested"]);
+    // The manifest and the license files belong to the artifact the manifest describes:
+    let app = library(&libraries, "org.example:app");
+    assert_eq!(app.license.as_deref(), Some("MIT"));
+    assert_eq!(app.license_texts, ["MIT License\n"]);
+    let guava = library(&libraries, "com.google.guava:guava");
+    assert_eq!(guava.license.as_deref(), Some("Apache-2.0"));
+    assert!(guava.license_texts.is_empty());
+
+    let nested = library(&libraries, "nested");
+    assert_eq!(nested.jar, "app.jar!/BOOT-INF/lib/nested.jar");
+    assert_eq!(nested.version.as_deref(), Some("0.9"));
+    assert_eq!(nested.license.as_deref(), Some("BSD-3-Clause"));
+}
+
+#[test]
+fn metadata_with_invalid_utf8_is_read() {
+    let mut latin1_pom = pom("org.example", "lib", &["MIT License"]);
+    latin1_pom.extend(b"<!-- J\xfcrgen -->");
+    let libraries = read(&jar(&[("META-INF/maven/org.example/lib/pom.xml", &latin1_pom)]));
+    assert_eq!(libraries[0].name, "org.example:lib");
+    assert_eq!(libraries[0].license.as_deref(), Some("MIT"));
+}
+
+#[test]
+fn unreadable_entries_are_skipped() {
+    let mut data = jar(&[
+        (
+            "META-INF/MANIFEST.MF",
+            b"Bundle-SymbolicName: org.example.lib\nBundle-License: MIT\n",
+        ),
+        ("META-INF/LICENSE", b"Unreadable license\n"),
+        ("META-INF/COPYING", b"Readable license\n"),
+        ("lib/broken.jar", b"no zip archive"),
+    ]);
+    // Change the stored content of the license, its checksum does not match anymore:
+    let position = data.windows(10).position(|x| x == b"Unreadable").unwrap();
+    data[position] = b'u';
+
+    let libraries = read(&data);
+    assert_eq!(libraries.len(), 1);
+    assert_eq!(libraries[0].name, "org.example.lib");
+    assert_eq!(libraries[0].license.as_deref(), Some("MIT"));
+    assert_eq!(libraries[0].license_texts, ["Readable license\n"]);
+}
@@ -0,0 +1,106 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+#![cfg(feature = "scan")]
+
+use std::path::{Path, PathBuf};
+
+use sancus_lib::{
+    file_info::FileInfo,
+    license_detector::{LicenseDetector, TemplateSet},
+    nix_info::{self, NixMetadata, StorePath},
+    settings::Overrides,
+};
+
+fn fixture_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/nix")
+}
+
+fn store_dir() -> PathBuf {
+    fixture_dir().join("nix/store")
+}
+
+/// Name and version of a store path below the fixture store.
+fn split(entry: &str) -> Option<(String, Option<String>)> {
+    let store_path = StorePath::of(&store_dir().join(entry).join("lib/libfoo.so.1"))?;
+    assert_eq!(store_path.path, store_dir().join(entry));
+    Some((store_path.pname, store_path.version))
+}
+
+fn name_version(pname: &str, version: Option<&str>) -> Option<(String, Option<String>)> {
+    Some((pname.to_owned(), version.map(str::to_owned)))
+}
+
+#[test]
+fn store_path_names_are_split_like_nix() {
+    const HASH: &str = "0123456789abcdfghijklmnpqrsvwxyz";
+    assert_eq!(
+        split(&format!("{HASH}-zlib-1.3.1")),
+        name_version("zlib", Some("1.3.1"))
+    );
+    // The version starts at the first dash not followed by a letter:
+    assert_eq!(
+        split(&format!("{HASH}-python3.11-requests-2.31.0")),
+        name_version("python3.11-requests", Some("2.31.0"))
+    );
+    assert_eq!(
+        split(&format!("{HASH}-gtk+3-3.24.41")),
+        name_version("gtk+3", Some("3.24.41"))
+    );
+    assert_eq!(
+        split(&format!("{HASH}-openssl-3.0.13-dev")),
+        name_version("openssl", Some("3.0.13-dev"))
+    );
+    assert_eq!(split(&format!("{HASH}-source")), name_version("source", None));
+    assert_eq!(
+        split(&format!("{HASH}-xorg-server-21.1.11")),
+        name_version("xorg-server", Some("21.1.11"))
+    );
+
+    // Entries without a valid hash and paths outside of the store are no store paths:
+    assert_eq!(split("0123-zlib-1.3.1"), None);
+    assert_eq!(split(&format!("{HASH}-")), None);
+    assert!(StorePath::of(&fixture_dir().join("meta.json")).is_none());
+}
+
+#[test]
+fn store_paths_are_reported_with_metadata_or_their_name() {
+    let zlib_dir = store_dir().join("0123456789abcdfghijklmnpqrsvwxyz-zlib-1.3.1/lib");
+    let requests_dir = store_dir().join("2123456789abcdfghijklmnpqrsvwxyz-python3.11-requests-2.31.0/lib");
+    let libs = [
+        FileInfo::new("libz.so.1".to_owned(), &zlib_dir.join("libz.so.1")),
+        FileInfo::new(
+            "libpyrequests.so.2".to_owned(),
+            &requests_dir.join("libpyrequests.so.2"),
+        ),
+        FileInfo::new("libz.so.1.3.1".to_owned(), &zlib_dir.join("libz.so.1.3.1")),
+        FileInfo::new("libapp.so.1".to_owned(), &fixture_dir().join("libapp.so.1")),
+    ];
+    let metadata = NixMetadata::load(&fixture_dir().join("meta.json")).unwrap();
+    let detector = LicenseDetector::builder().template_set(TemplateSet::None).build();
+    let infos = nix_info::get_license_info(&libs, &metadata, &Overrides::default(), &detector).unwrap();
+    assert_eq!(infos.len(), 2);
+
+    // Matched by the name of the store path, the libraries of a store path are reported together:
+    let zlib = &infos[0];
+    assert_eq!(zlib.package_name, "zlib");
+    assert_eq!(zlib.version.as_deref(), Some("1.3.1"));
+    assert_eq!(zlib.license, "Zlib");
+    assert_eq!(zlib.url.as_deref(), Some("https://zlib.net/"));
+    assert_eq!(zlib.binaries, ["libz.so.1", "libz.so.1.3.1"]);
+    assert_eq!(zlib.license_texts.len(), 1);
+
+    // Without metadata, derivation and license files:
+    let requests = &infos[1];
+    assert_eq!(requests.package_name, "python3.11-requests");
+    assert_eq!(requests.version.as_deref(), Some("2.31.0"));
+    assert_eq!(requests.license, "NOASSERTION");
+    assert!(requests.license_texts.is_empty());
+}
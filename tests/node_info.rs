@@ -0,0 +1,106 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+#![cfg(feature = "scan")]
+
+use std::path::{Path, PathBuf};
+
+use sancus_lib::{
+    license_detector::{LicenseDetector, TemplateSet},
+    node_info::{self, NodePackage},
+    settings::Overrides,
+};
+
+fn fixture_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/node/project")
+}
+
+fn node_modules() -> PathBuf {
+    fixture_dir().join("node_modules")
+}
+
+fn load(package: &str) -> NodePackage {
+    NodePackage::load(&node_modules().join(package)).unwrap()
+}
+
+#[test]
+fn nested_and_scoped_packages_are_found() {
+    assert_eq!(
+        node_info::find_node_modules(&fixture_dir(), &[]).unwrap(),
+        [node_modules()]
+    );
+
+    // The package.json without name is skipped, left-pad is installed twice with the same version:
+    let packages = node_info::find_node_packages(&[node_modules()]).unwrap();
+    let names = packages
+        .iter()
+        .map(|x| format!("{}@{}", x.name, x.version.as_deref().unwrap_or_default()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            "@types/node@20.11.0",
+            "acme-sdk@3.0.0",
+            "debug@4.3.4",
+            "express@4.19.2",
+            "debug@2.6.9",
+            "left-pad@1.3.0",
+            "legacy@0.1.0",
+            "private@1.0.0",
+        ]
+    );
+    assert!(NodePackage::load(&node_modules().join("broken")).is_err());
+}
+
+#[test]
+fn package_metadata_is_read() {
+    let types = load("@types/node");
+    assert_eq!(types.license.as_deref(), Some("MIT"));
+    assert_eq!(
+        types.url.as_deref(),
+        Some("https://github.com/DefinitelyTyped/DefinitelyTyped.git")
+    );
+    assert_eq!(types.dependencies, ["undici-types"]);
+    // The homepage takes precedence over the repository:
+    assert_eq!(
+        load("left-pad").url.as_deref(),
+        Some("https://github.com/stevemao/left-pad#readme")
+    );
+}
+
+#[test]
+fn deprecated_licenses_array_is_a_choice() {
+    assert_eq!(load("legacy").license.as_deref(), Some("MIT OR Apache-2.0"));
+}
+
+#[test]
+fn see_license_in_refers_to_a_license_file() {
+    let package = load("acme-sdk");
+    assert_eq!(package.license, None);
+    assert_eq!(package.license_files, [node_modules().join("acme-sdk/EULA.txt")]);
+
+    let eula = std::fs::read_to_string(node_modules().join("acme-sdk/EULA.txt")).unwrap();
+    let detector = LicenseDetector::builder()
+        .template_set(TemplateSet::None)
+        .template("LicenseRef-Acme-EULA", eula)
+        .build();
+    let info = package.license_info(&Overrides::default(), &detector).unwrap();
+    assert_eq!(info.license, "NOASSERTION");
+    assert_eq!(info.license_texts[0].id, "LicenseRef-Acme-EULA");
+}
+
+#[test]
+fn unlicensed_packages_have_no_license() {
+    let package = load("private");
+    assert_eq!(package.license, None);
+    let detector = LicenseDetector::builder().template_set(TemplateSet::None).build();
+    let info = package.license_info(&Overrides::default(), &detector).unwrap();
+    assert_eq!(info.license, "NOASSERTION");
+}
@@ -0,0 +1,104 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+#![cfg(feature = "scan")]
+
+use std::path::{Path, PathBuf};
+
+use sancus_lib::{
+    license_detector::{LicenseDetector, TemplateSet},
+    package_file::{PackageFile, PackageFileKind},
+    settings::Overrides,
+};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/package_file")
+        .join(name)
+}
+
+/// Extracts a package file into an empty directory, the directory is nested to detect entries leaving it.
+fn extract(name: &str) -> (PackageFile, PathBuf) {
+    let base_dir = std::env::temp_dir().join(format!("sancus-{name}-{}", std::process::id()));
+    if base_dir.exists() {
+        std::fs::remove_dir_all(&base_dir).unwrap();
+    }
+    let target_dir = base_dir.join("payload");
+    let package = PackageFile::extract(&fixture(name), &target_dir).unwrap();
+    (package, base_dir)
+}
+
+#[test]
+fn package_files_are_detected_by_their_magic() {
+    assert_eq!(
+        PackageFile::detect(&fixture("app-1.0-1.fc40.x86_64.rpm")),
+        Some(PackageFileKind::Rpm)
+    );
+    assert_eq!(
+        PackageFile::detect(&fixture("app_1.0-1_amd64.deb")),
+        Some(PackageFileKind::Deb)
+    );
+    assert_eq!(
+        PackageFile::detect(&Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml")),
+        None
+    );
+}
+
+#[test]
+fn rpm_header_and_cpio_payload_are_read() {
+    let (package, base_dir) = extract("app-1.0-1.fc40.x86_64.rpm");
+    assert_eq!(package.kind, PackageFileKind::Rpm);
+    assert_eq!(package.name, "app");
+    assert_eq!(package.version.as_deref(), Some("1.0-1.fc40"));
+    assert_eq!(package.license.as_deref(), Some("MIT"));
+    assert_eq!(package.url.as_deref(), Some("https://example.com/app"));
+    // rpmlib capabilities are requirements of rpm itself, duplicates are removed:
+    assert_eq!(package.requires, ["/bin/sh", "libz.so.1()(64bit)", "bash"]);
+
+    let payload = base_dir.join("payload");
+    assert!(payload.join("opt/app/bin/app").is_file());
+    assert_eq!(
+        std::fs::read_to_string(payload.join("opt/app/LICENSE")).unwrap(),
+        "MIT License\n"
+    );
+    assert!(payload.join("opt/app/LICENSE").is_symlink());
+    // Device files and entries outside of the target directory are skipped:
+    assert!(!payload.join("dev/null").exists());
+    assert!(!base_dir.join("escape").exists());
+
+    // The dependencies of rpm package files are looked up in the rpm database:
+    let detector = LicenseDetector::builder().template_set(TemplateSet::None).build();
+    assert!(
+        package
+            .license_info(&[], None, &Overrides::default(), &detector)
+            .is_err()
+    );
+    std::fs::remove_dir_all(&base_dir).unwrap();
+}
+
+#[test]
+fn deb_control_and_data_archives_are_read() {
+    let (package, base_dir) = extract("app_1.0-1_amd64.deb");
+    assert_eq!(package.kind, PackageFileKind::Deb);
+    assert_eq!(package.name, "app");
+    assert_eq!(package.version.as_deref(), Some("1.0-1"));
+    assert_eq!(package.license, None);
+    assert_eq!(package.url.as_deref(), Some("https://example.com/app"));
+    // Versions and architectures are removed, alternatives are kept:
+    assert_eq!(package.requires, ["dpkg", "libc6", "zlib1g|libz-ng2", "libstdc++6"]);
+
+    let payload = base_dir.join("payload");
+    assert!(payload.join("opt/app/bin/app").is_file());
+    assert_eq!(
+        std::fs::read_to_string(payload.join("opt/app/copyright")).unwrap(),
+        "License: MIT\n"
+    );
+    std::fs::remove_dir_all(&base_dir).unwrap();
+}
@@ -0,0 +1,105 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+#![cfg(feature = "scan")]
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use sancus_lib::{
+    license_detector::{LicenseDetector, TemplateSet},
+    python_info::{self, PythonPackage},
+    scanner::{ReportKind, ScanBuilder, Source},
+    settings::Overrides,
+    target_os::TargetOs,
+};
+
+fn fixture_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/python")
+}
+
+fn site_packages() -> PathBuf {
+    fixture_dir().join("package/usr/lib/python3.12/site-packages")
+}
+
+fn detector() -> LicenseDetector {
+    LicenseDetector::builder().template_set(TemplateSet::None).build()
+}
+
+#[test]
+fn site_packages_are_found_in_the_package() {
+    let found = python_info::find_site_packages(&fixture_dir().join("package"), &[]).unwrap();
+    assert_eq!(found, [site_packages()]);
+    assert!(
+        python_info::find_site_packages(&fixture_dir().join("package"), &["python3.12".to_owned()])
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn packages_with_invalid_metadata_are_skipped() {
+    let packages = python_info::find_python_packages(&[site_packages()]).unwrap();
+    let names = packages.iter().map(|x| x.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["requests", "six"]);
+
+    assert!(PythonPackage::load(&site_packages().join("broken-1.0.dist-info")).is_err());
+    assert!(PythonPackage::load(&site_packages().join("unreadable-1.0.dist-info")).is_err());
+}
+
+#[test]
+fn dist_info_metadata_is_read() {
+    let package = PythonPackage::load(&site_packages().join("requests-2.32.3.dist-info")).unwrap();
+    assert_eq!(package.version.as_deref(), Some("2.32.3"));
+    assert_eq!(package.license.as_deref(), Some("Apache-2.0"));
+    assert_eq!(package.url.as_deref(), Some("https://requests.readthedocs.io"));
+    let modules = package
+        .extension_modules
+        .iter()
+        .map(|x| x.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(modules, ["md.cpython-312-x86_64-linux-gnu.so"]);
+
+    let info = package.license_info(&Overrides::default(), &detector()).unwrap();
+    assert_eq!(info.lib_info.name, "METADATA");
+    assert_eq!(info.binaries, ["md.cpython-312-x86_64-linux-gnu.so"]);
+}
+
+#[test]
+fn egg_info_metadata_is_named_after_its_file() {
+    let package = PythonPackage::load(&site_packages().join("six-1.16.0-py3.12.egg-info")).unwrap();
+    // The free text license is no SPDX expression, the classifier is used:
+    assert_eq!(package.license.as_deref(), Some("MIT"));
+
+    let info = package.license_info(&Overrides::default(), &detector()).unwrap();
+    assert_eq!(info.lib_info.name, "PKG-INFO");
+    assert_eq!(info.lib_info.path, package.metadata_file);
+}
+
+#[test]
+fn python_packages_are_only_scanned_if_enabled() {
+    let scan = |sources: &[Source]| {
+        ScanBuilder::new(fixture_dir(), fixture_dir().join("package"))
+            .sources(sources.iter().copied())
+            .target_os(TargetOs::Linux)
+            .license_detector(Arc::new(detector()))
+            .build()
+            .unwrap()
+            .scan()
+            .unwrap()
+    };
+    let report = scan(&[Source::Python]);
+    let packages = report.report(ReportKind::Python).unwrap().licenses.len();
+    assert_eq!(packages, 2);
+
+    assert!(scan(&[Source::Node]).report(ReportKind::Python).is_none());
+}
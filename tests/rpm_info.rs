@@ -0,0 +1,391 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+#![cfg(feature = "scan")]
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use sancus_lib::{
+    error::Error,
+    file_info::FileInfo,
+    license_detector::{LicenseDetector, TemplateSet},
+    package_source::{PackageSource, PackageSources},
+    rpm_info::{PackageDB, RpmFixture},
+    settings::{LicenseFileOverride, Override, Overrides},
+    system_packages::{PackageManager, SystemPackages},
+};
+
+fn fixture_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/rpm")
+}
+
+fn package_db(file: &str) -> PackageDB {
+    let fixture = RpmFixture::load(&fixture_dir().join(file)).unwrap();
+    PackageDB::load(&fixture).unwrap()
+}
+
+fn detector() -> LicenseDetector {
+    LicenseDetector::builder().template_set(TemplateSet::None).build()
+}
+
+fn lib(name: &str) -> FileInfo {
+    FileInfo::new(name.to_owned(), &fixture_dir().join("root/usr/lib64").join(name))
+}
+
+#[test]
+fn loads_yaml_and_json_fixtures() {
+    for file in ["packages.yaml", "packages.json"] {
+        let db = package_db(file);
+        let package = db.query_by_library_name("libz.so.1", Some(true)).unwrap();
+        assert_eq!(package.name(), "zlib");
+
+        let info = package
+            .license_info(lib("libz.so.1"), &Overrides::default(), &detector())
+            .unwrap();
+        assert_eq!(info.license, "Zlib");
+        assert_eq!(info.version.as_deref(), Some("1.3.1"));
+        assert_eq!(info.license_texts.len(), 1);
+        assert_eq!(info.license_texts[0].id, "Zlib");
+    }
+}
+
+#[test]
+fn queries_package_by_name_and_file() {
+    let db = package_db("packages.yaml");
+    assert_eq!(db.query_by_name("bzip2-libs").unwrap().name(), "bzip2-libs");
+    assert!(db.query_by_name("bzip2").is_none());
+
+    let package = db.query_by_file("/usr/lib64/libbz2.so.1.0.8").unwrap().unwrap();
+    assert_eq!(package.name(), "bzip2-libs");
+    // /lib64 is a link to usr/lib64:
+    let package = db.query_by_file("lib64/libbz2.so.1.0.8").unwrap().unwrap();
+    assert_eq!(package.name(), "bzip2-libs");
+    // Files are not matched by their name:
+    assert!(db.query_by_file("libbz2.so.1.0.8").unwrap().is_none());
+    assert!(db.query_by_file("/opt/lib64/libbz2.so.1.0.8").unwrap().is_none());
+}
+
+#[test]
+fn unknown_library_is_not_found() {
+    let db = package_db("packages.yaml");
+    let error = db.query_by_library_name("libunknown.so.1", None).unwrap_err();
+    assert!(matches!(error, Error::PackageNotFound(_)), "{error}");
+}
+
+#[test]
+fn library_of_several_packages_is_ambiguous() {
+    let db = package_db("packages.yaml");
+    let error = db.query_by_library_name("libssl.so.3", Some(true)).unwrap_err();
+    let Error::AmbiguousPackage { packages, .. } = error else {
+        panic!("Unexpected error: {error}");
+    };
+    assert_eq!(packages, ["openssl-libs", "openssl3-libs"]);
+}
+
+#[test]
+fn library_is_not_matched_by_substring() {
+    let db = package_db("packages.yaml");
+    // The debug information of zlib-debuginfo contains `libz.so.1` in its paths:
+    assert_eq!(
+        db.query_by_library_name("libz.so.1", Some(true)).unwrap().name(),
+        "zlib"
+    );
+    // Only `libssl.so.3` is installed:
+    let error = db.query_by_library_name("libssl.so", Some(true)).unwrap_err();
+    assert!(matches!(error, Error::PackageNotFound(_)), "{error}");
+}
+
+#[test]
+fn multilib_library_is_found_by_class() {
+    let db = package_db("packages.yaml");
+    assert_eq!(
+        db.query_by_library_name("libz.so.1", Some(true)).unwrap().name(),
+        "zlib"
+    );
+    assert_eq!(
+        db.query_by_library_name("libz.so.1", Some(false)).unwrap().name(),
+        "zlib-32bit"
+    );
+    // lib64 is searched first if the class is unknown:
+    assert_eq!(db.query_by_library_name("libz.so.1", None).unwrap().name(), "zlib");
+    // There is no 32-bit variant:
+    let error = db.query_by_library_name("libbz2.so.1", Some(false)).unwrap_err();
+    assert!(matches!(error, Error::PackageNotFound(_)), "{error}");
+}
+
+#[test]
+fn unowned_symlink_is_followed() {
+    let db = package_db("packages.yaml");
+    // libbz2.so.1 is a link to libbz2.so.1.0.8 in /lib64, a link to /usr/lib64:
+    assert_eq!(
+        db.query_by_library_name("libbz2.so.1", Some(true)).unwrap().name(),
+        "bzip2-libs"
+    );
+}
+
+#[test]
+fn configured_library_directory_is_searched() {
+    let db = package_db("packages.yaml");
+    // /opt/custom/lib64 is configured in ld.so.conf.d/custom.conf:
+    assert_eq!(
+        db.query_by_library_name("libcustom.so.2", Some(true)).unwrap().name(),
+        "custom"
+    );
+}
+
+#[test]
+fn loader_cache_determines_library() {
+    let db = package_db("cache.yaml");
+    // The first entry of the cache is not installed, the next one is preferred over the trusted directories:
+    assert_eq!(
+        db.query_by_library_name("libz.so.1", Some(true)).unwrap().name(),
+        "zlib-ng-compat"
+    );
+    assert_eq!(
+        db.query_by_library_name("libz.so.1", Some(false)).unwrap().name(),
+        "zlib-32bit"
+    );
+}
+
+#[test]
+fn library_file_is_looked_up_by_name() {
+    let db = package_db("packages.yaml");
+    // The file is no binary, its SONAME and class are unknown:
+    let file = FileInfo::new("libbz2.so.1".to_owned(), Path::new("/tmp/package/lib/libbz2.so.1"));
+    assert_eq!(db.query_by_library(&file).unwrap().name(), "bzip2-libs");
+}
+
+#[test]
+fn package_without_license_is_an_error() {
+    let db = package_db("packages.yaml");
+    let package = db.query_by_name("nolicense").unwrap();
+    let error = package
+        .license_info(lib("libnolicense.so.1"), &Overrides::default(), &detector())
+        .unwrap_err();
+    let Error::MissingLicense { kind, package } = error else {
+        panic!("Unexpected error: {error}");
+    };
+    assert_eq!(kind, "RPM");
+    assert_eq!(package, "nolicense");
+}
+
+#[test]
+fn override_provides_missing_license() {
+    let db = package_db("packages.yaml");
+    let package = db.query_by_name("nolicense").unwrap();
+    let overrides = Overrides::new(vec![Override {
+        package: "nolicense".to_owned(),
+        license_id: Some("MIT".to_owned()),
+        ..Default::default()
+    }]);
+    let info = package
+        .license_info(lib("libnolicense.so.1"), &overrides, &detector())
+        .unwrap();
+    assert_eq!(info.license, "MIT");
+    assert_eq!(info.license_texts.len(), 1);
+    assert_eq!(info.license_texts[0].id, "MIT");
+}
+
+#[test]
+fn specific_override_takes_precedence_over_pattern() {
+    let db = package_db("packages.yaml");
+    let package = db.query_by_name("custom").unwrap();
+    let replacement = fixture_dir().join("overrides/custom-LICENSE");
+    let overrides = Overrides::new(vec![
+        Override {
+            package: "cust*".to_owned(),
+            license_id: Some("BSD-3-Clause".to_owned()),
+            ..Default::default()
+        },
+        Override {
+            package: "custom".to_owned(),
+            version: Some(">=2".to_owned()),
+            source: Some("rpm".to_owned()),
+            license_id: Some("Apache-2.0".to_owned()),
+            license_files: vec![LicenseFileOverride {
+                id: Some("Apache-2.0".to_owned()),
+                file: replacement.to_string_lossy().into_owned(),
+            }],
+            ..Default::default()
+        },
+        Override {
+            package: "custom".to_owned(),
+            source: Some("vcpkg".to_owned()),
+            license_id: Some("GPL-2.0-only".to_owned()),
+            ..Default::default()
+        },
+    ]);
+    let info = package
+        .license_info(lib("libcustom.so.2"), &overrides, &detector())
+        .unwrap();
+    assert_eq!(info.license, "Apache-2.0");
+    assert_eq!(info.license_texts.len(), 1);
+    assert_eq!(info.license_texts[0].id, "Apache-2.0");
+    assert_eq!(
+        info.license_texts[0].text,
+        "Replacement license text of the custom package.\n"
+    );
+    // Only the applied override is recorded as match:
+    assert_eq!(overrides.matches().into_keys().collect::<Vec<_>>(), [1]);
+}
+
+#[test]
+fn pattern_override_applies_without_specific_override() {
+    let db = package_db("packages.yaml");
+    let package = db.query_by_name("custom").unwrap();
+    let overrides = Overrides::new(vec![
+        Override {
+            package: "cust*".to_owned(),
+            license_id: Some("BSD-3-Clause".to_owned()),
+            ..Default::default()
+        },
+        Override {
+            package: "custom".to_owned(),
+            version: Some("<2".to_owned()),
+            license_id: Some("Apache-2.0".to_owned()),
+            ..Default::default()
+        },
+    ]);
+    let info = package
+        .license_info(lib("libcustom.so.2"), &overrides, &detector())
+        .unwrap();
+    assert_eq!(info.license, "BSD-3-Clause");
+    assert_eq!(info.license_texts.len(), 1);
+    assert_eq!(info.license_texts[0].id, "BSD-3-Clause");
+}
+
+#[test]
+fn missing_installed_file_is_an_error() {
+    let db = package_db("packages.yaml");
+    let package = db.query_by_name("openssl-libs").unwrap();
+    let error = package
+        .license_info(lib("libssl.so.3"), &Overrides::default(), &detector())
+        .unwrap_err();
+    let Error::Io { path, source } = error else {
+        panic!("Unexpected error: {error}");
+    };
+    assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+    assert!(
+        path.ends_with("usr/share/licenses/openssl-libs/LICENSE.txt"),
+        "{}",
+        path.display()
+    );
+}
+
+#[test]
+fn override_license_files_replace_missing_installed_files() {
+    let db = package_db("packages.yaml");
+    let package = db.query_by_name("openssl-libs").unwrap();
+    let overrides = Overrides::new(vec![Override {
+        package: "openssl-libs".to_owned(),
+        license_files: vec![LicenseFileOverride {
+            id: Some("Apache-2.0".to_owned()),
+            file: fixture_dir()
+                .join("overrides/custom-LICENSE")
+                .to_string_lossy()
+                .into_owned(),
+        }],
+        ..Default::default()
+    }]);
+    let info = package
+        .license_info(lib("libssl.so.3"), &overrides, &detector())
+        .unwrap();
+    assert_eq!(info.license, "Apache-2.0");
+    assert_eq!(info.license_texts[0].id, "Apache-2.0");
+}
+
+#[test]
+fn attributes_libraries_through_package_sources() {
+    let db = Arc::new(package_db("packages.yaml"));
+    assert_eq!(db.name(), "rpm");
+
+    let mut sources = PackageSources::new();
+    sources.push(db);
+    let files = [
+        lib("libz.so.1.3.1"),
+        lib("libz.so.1"),
+        lib("libbz2.so.1"),
+        lib("libfoo.so.1"),
+    ];
+    let attribution = sources.attribute(&files, &Overrides::default(), &detector()).unwrap();
+
+    let packages = attribution.licenses[0]
+        .iter()
+        .map(|info| info.package_name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(packages, ["zlib", "bzip2-libs"]);
+    let unattributed = attribution
+        .unattributed
+        .iter()
+        .map(|file| file.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(unattributed, ["libfoo.so.1"]);
+}
+
+#[test]
+fn ambiguous_library_fails_the_attribution() {
+    let mut sources = PackageSources::new();
+    sources.push(Arc::new(package_db("packages.yaml")));
+    let files = [FileInfo::new(
+        "libssl.so.3".to_owned(),
+        Path::new("/usr/lib64/libssl.so.3"),
+    )];
+    let error = sources
+        .attribute(&files, &Overrides::default(), &detector())
+        .unwrap_err();
+    assert!(matches!(error, Error::AmbiguousPackage { .. }), "{error}");
+}
+
+#[test]
+fn system_packages_resolve_libraries_in_the_injected_database() {
+    for (file, name, expected) in [
+        ("packages.yaml", "libcustom.so.2", "custom"),
+        ("cache.yaml", "libz.so.1", "zlib-ng-compat"),
+    ] {
+        let fixture = RpmFixture::load(&fixture_dir().join(file)).unwrap();
+        let packages = SystemPackages::rpm(Arc::new(fixture));
+        assert_eq!(packages.manager, PackageManager::Rpm);
+
+        // The libraries are resolved in the root of the fixture, not of the host:
+        let file = FileInfo::new(name.to_owned(), Path::new("/tmp/package/lib").join(name).as_path());
+        assert_eq!(
+            packages.package_of_file(&file).unwrap().as_deref(),
+            Some(expected),
+            "{file:?}"
+        );
+    }
+}
+
+#[test]
+fn system_packages_find_owners_of_files_in_the_injected_database() {
+    let fixture = RpmFixture::load(&fixture_dir().join("packages.yaml")).unwrap();
+    let packages = SystemPackages::rpm(Arc::new(fixture));
+    assert_eq!(packages.root, fixture_dir().join("root"));
+    assert_eq!(
+        packages
+            .package_name_of_file("usr/share/licenses/custom/LICENSE")
+            .unwrap()
+            .as_deref(),
+        Some("custom")
+    );
+    assert!(packages.package_name_of_file("usr/share/doc/README").unwrap().is_none());
+
+    let info = packages
+        .license_info("bzip2-libs", lib("libbz2.so.1"), &Overrides::default(), &detector())
+        .unwrap();
+    assert_eq!(info.license, "bzip2-1.0.6");
+    let error = packages
+        .license_info("bzip2", lib("libbz2.so.1"), &Overrides::default(), &detector())
+        .unwrap_err();
+    assert!(matches!(error, Error::UnknownPackage { .. }), "{error}");
+}
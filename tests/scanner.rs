@@ -0,0 +1,145 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+#![cfg(feature = "scan")]
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use sancus_lib::{
+    error::Error,
+    license_detector::{LicenseDetector, TemplateSet},
+    rpm_info::RpmFixture,
+    scanner::{ReportKind, ScanBuilder, ScanReport, Source},
+    target_os::TargetOs,
+};
+
+fn fixture_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/vcpkg")
+}
+
+fn rpm_fixture_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/rpm")
+}
+
+fn detector() -> Arc<LicenseDetector> {
+    Arc::new(LicenseDetector::builder().template_set(TemplateSet::None).build())
+}
+
+fn scan_vcpkg(triplet: Option<&str>, host_triplet: Option<&str>) -> ScanReport {
+    ScanBuilder::new(fixture_dir().join("project"), fixture_dir().join("package"))
+        .sources([Source::Vcpkg])
+        .target_os(TargetOs::Windows)
+        .triplet(triplet.map(str::to_owned))
+        .host_triplet(host_triplet.map(str::to_owned))
+        .license_detector(detector())
+        .build()
+        .unwrap()
+        .scan()
+        .unwrap()
+}
+
+fn packages(report: &ScanReport, kind: ReportKind) -> Vec<&str> {
+    report
+        .report(kind)
+        .map(|report| report.licenses.iter().map(|x| x.package_name.as_str()).collect())
+        .unwrap_or_default()
+}
+
+#[test]
+fn host_triplet_ports_are_only_build_tools() {
+    for triplet in [Some("x64-windows"), None] {
+        let report = scan_vcpkg(triplet, Some("x64-mingw-static"));
+        assert_eq!(packages(&report, ReportKind::Vcpkg), ["zlib"], "triplet {triplet:?}");
+        assert_eq!(
+            packages(&report, ReportKind::VcpkgBuildTools),
+            ["pkgconf"],
+            "triplet {triplet:?}"
+        );
+    }
+}
+
+#[test]
+fn all_triplets_are_shipped_without_host_triplet() {
+    let report = scan_vcpkg(None, None);
+    let mut shipped = packages(&report, ReportKind::Vcpkg);
+    shipped.sort();
+    assert_eq!(shipped, ["pkgconf", "zlib"]);
+    assert!(report.report(ReportKind::VcpkgBuildTools).is_none());
+}
+
+#[test]
+fn libraries_are_attributed_to_the_packages_of_the_rpm_database() {
+    let fixture = RpmFixture::load(&rpm_fixture_dir().join("packages.yaml")).unwrap();
+    let report = ScanBuilder::new(rpm_fixture_dir(), rpm_fixture_dir().join("package"))
+        .sources([Source::System])
+        .target_os(TargetOs::Linux)
+        .rpm_database(Arc::new(fixture))
+        .license_detector(detector())
+        .build()
+        .unwrap()
+        .scan()
+        .unwrap();
+
+    let mut system = packages(&report, ReportKind::System);
+    system.sort();
+    assert_eq!(system, ["bzip2-libs", "zlib"]);
+    let zlib = &report.report(ReportKind::System).unwrap().licenses;
+    let zlib = zlib.iter().find(|x| x.package_name == "zlib").unwrap();
+    assert_eq!(zlib.license, "Zlib");
+    assert_eq!(zlib.version.as_deref(), Some("1.3.1"));
+    assert_eq!(zlib.license_texts.len(), 1);
+}
+
+fn scan_without_vcpkg(sources: &[Source]) -> sancus_lib::Result<ScanReport> {
+    let project = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/crates/project");
+    ScanBuilder::new(project, fixture_dir().join("package"))
+        .sources(sources.iter().copied())
+        .target_os(TargetOs::Windows)
+        .license_detector(detector())
+        .build()?
+        .scan()
+}
+
+#[test]
+fn missing_vcpkg_installation_is_skipped_by_default() {
+    let report = scan_without_vcpkg(&[]).unwrap();
+    assert!(report.report(ReportKind::Vcpkg).is_none());
+}
+
+#[test]
+fn missing_vcpkg_installation_is_an_error_if_requested() {
+    let error = scan_without_vcpkg(&[Source::Vcpkg, Source::Crates]).unwrap_err();
+    assert!(matches!(error, Error::VcpkgNotFound(_)), "{error}");
+}
+
+#[test]
+fn missing_package_path_is_an_error() {
+    let error = ScanBuilder::new(fixture_dir().join("project"), fixture_dir().join("missing"))
+        .build()
+        .unwrap_err();
+    assert!(
+        matches!(&error, Error::NotADirectory { path, .. } if path.ends_with("missing")),
+        "{error}"
+    );
+}
+
+#[test]
+fn sources_are_parsed_by_name() {
+    assert_eq!("Rust-Binaries".parse::<Source>().unwrap(), Source::RustBinaries);
+    let error = "cargo".parse::<Source>().unwrap_err();
+    assert!(
+        matches!(&error, Error::UnknownSource { name, .. } if name == "cargo"),
+        "{error}"
+    );
+    assert!(error.to_string().contains("'vcpkg', 'conan'"), "{error}");
+}
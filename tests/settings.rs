@@ -0,0 +1,291 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+#![cfg(feature = "scan")]
+
+use std::path::{Path, PathBuf};
+
+use sancus_lib::{
+    error::Error,
+    settings::{Override, Overrides, Settings},
+    target_os::TargetOs,
+};
+
+fn fixture_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/settings")
+}
+
+fn selector(package: &str) -> Override {
+    Override {
+        package: package.to_owned(),
+        license_id: Some("MIT".to_owned()),
+        ..Default::default()
+    }
+}
+
+/// Index of the override selected for a package, `None` if no override matches.
+fn selected(overrides: &[Override], source: &str, package: &str, version: Option<&str>) -> Option<usize> {
+    let overrides = Overrides::new(overrides.to_vec());
+    Override::find_override(source, package, version, &[], &overrides)?;
+    overrides.matches().into_keys().next()
+}
+
+fn zlib_override(source: &str) -> Override {
+    Override {
+        package: "zlib".to_owned(),
+        source: Some(source.to_owned()),
+        license_id: Some("Zlib".to_owned()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn matches_are_recorded_per_override_index() {
+    // Both overrides have the same description, they are recorded separately:
+    let overrides = Overrides::new(vec![zlib_override("rpm"), zlib_override("rpm"), zlib_override("vcpkg")]);
+    let found = Override::find_override("vcpkg", "zlib", Some("1.3.1"), &[], &overrides).unwrap();
+    assert_eq!(found.source.as_deref(), Some("vcpkg"));
+    Override::find_override("rpm", "zlib", None, &[], &overrides).unwrap();
+
+    let matches = overrides.matches();
+    assert_eq!(matches.keys().copied().collect::<Vec<_>>(), [0, 2]);
+    assert_eq!(matches[&0].iter().collect::<Vec<_>>(), ["rpm package 'zlib'"]);
+    assert_eq!(matches[&2].iter().collect::<Vec<_>>(), ["vcpkg package 'zlib'"]);
+}
+
+#[test]
+fn matches_do_not_leak_between_scans() {
+    let first = Overrides::new(vec![zlib_override("rpm")]);
+    Override::find_override("rpm", "zlib", None, &[], &first).unwrap();
+    assert_eq!(first.matches().len(), 1);
+
+    let second = Overrides::new(vec![zlib_override("rpm")]);
+    assert!(Override::find_override("rpm", "bzip2", None, &[], &second).is_none());
+    assert!(second.matches().is_empty());
+}
+
+#[test]
+fn glob_selectors_match_the_whole_name() {
+    let overrides = [selector("qt5-*")];
+    assert_eq!(selected(&overrides, "rpm", "qt5-qtbase", None), Some(0));
+    assert_eq!(selected(&overrides, "rpm", "qt5-", None), Some(0));
+    assert_eq!(selected(&overrides, "rpm", "libqt5-qtbase", None), None);
+
+    let overrides = [selector("libpng1?")];
+    assert_eq!(selected(&overrides, "rpm", "libpng16", None), Some(0));
+    assert_eq!(selected(&overrides, "rpm", "libpng1", None), None);
+    // Other characters of regular expressions are literal:
+    assert_eq!(selected(&[selector("gtk+3*")], "rpm", "gtk+3.0", None), Some(0));
+}
+
+#[test]
+fn regex_selectors_match_like_regular_expressions() {
+    let overrides = [selector("regex:^qt5-(base|svg)$")];
+    assert_eq!(selected(&overrides, "vcpkg", "qt5-base", None), Some(0));
+    assert_eq!(selected(&overrides, "vcpkg", "qt5-svg", None), Some(0));
+    assert_eq!(selected(&overrides, "vcpkg", "qt5-tools", None), None);
+    // The feature brackets are part of the expression:
+    assert_eq!(
+        selected(&[selector("regex:^lib[a-z]+$")], "rpm", "libpng", None),
+        Some(0)
+    );
+}
+
+#[test]
+fn exact_selectors_take_precedence_over_patterns() {
+    let overrides = [selector("regex:^zlib.*"), selector("zlib*"), selector("zlib")];
+    assert_eq!(selected(&overrides, "rpm", "zlib", None), Some(2));
+    assert_eq!(selected(&overrides, "rpm", "zlib-ng", None), Some(0));
+}
+
+#[test]
+fn version_constraints_select_overrides() {
+    let versioned = |version: &str| Override {
+        version: Some(version.to_owned()),
+        ..selector("qt5-base")
+    };
+    let overrides = [versioned(">=5.15, <6"), versioned("^6")];
+    assert_eq!(selected(&overrides, "vcpkg", "qt5-base", Some("5.15.2")), Some(0));
+    assert_eq!(selected(&overrides, "vcpkg", "qt5-base", Some("6.5.0")), Some(1));
+    assert_eq!(selected(&overrides, "vcpkg", "qt5-base", Some("5.12")), None);
+    // Versions are compared like rpm compares them:
+    assert_eq!(selected(&overrides, "vcpkg", "qt5-base", Some("5.15~rc1")), None);
+    assert_eq!(selected(&overrides, "vcpkg", "qt5-base", Some("1:5.15")), None);
+    // A version requirement never matches packages without a version:
+    assert_eq!(selected(&overrides, "vcpkg", "qt5-base", None), None);
+
+    // Overrides with a matching version are more specific:
+    let overrides = [selector("qt5-base"), versioned("<6")];
+    assert_eq!(selected(&overrides, "vcpkg", "qt5-base", Some("5.15")), Some(1));
+    assert_eq!(selected(&overrides, "vcpkg", "qt5-base", Some("6.5")), Some(0));
+}
+
+#[test]
+fn source_scoped_overrides_only_apply_to_their_source() {
+    let overrides = [zlib_override("rpm")];
+    assert_eq!(selected(&overrides, "rpm", "zlib", None), Some(0));
+    assert_eq!(selected(&overrides, "RPM", "zlib", None), Some(0));
+    assert_eq!(selected(&overrides, "vcpkg", "zlib", None), None);
+
+    // Overrides with a source are more specific than the ones for all sources:
+    let overrides = [selector("zlib"), zlib_override("vcpkg")];
+    assert_eq!(selected(&overrides, "vcpkg", "zlib", None), Some(1));
+    assert_eq!(selected(&overrides, "conan", "zlib", None), Some(0));
+}
+
+#[test]
+fn invalid_selectors_and_versions_are_rejected() {
+    assert!(selector("regex:^zlib$").validate().is_ok());
+    assert!(selector("regex:(zlib").validate().is_err());
+    let versioned = Override {
+        version: Some(">=".to_owned()),
+        ..selector("zlib")
+    };
+    assert!(versioned.validate().is_err());
+
+    for file in ["invalid-regex.yaml", "invalid-version.yaml"] {
+        let error = Settings::load(&fixture_dir().join(file), None).unwrap_err();
+        assert!(
+            matches!(&error, Error::Settings { path, .. } if path.ends_with(file)),
+            "{file}: {error}"
+        );
+    }
+}
+
+fn include_dir() -> PathBuf {
+    fixture_dir().join("include")
+}
+
+fn load_included() -> Settings {
+    let shared_dir = include_dir().join("shared");
+    Settings::load(&include_dir().join("project/sancus.yaml"), Some(&shared_dir)).unwrap()
+}
+
+#[test]
+fn included_settings_are_merged_in_order() {
+    let settings = load_included();
+    assert!(settings.include.is_empty());
+
+    // Entries with the same package replace the included entry in place, new entries are appended:
+    let overrides = settings
+        .overrides
+        .iter()
+        .map(|x| (x.package.as_str(), x.comment.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        overrides,
+        [
+            ("zlib", "Replaces the override of base.yaml"),
+            ("bzip2", "Replaces the override of base.yaml"),
+            ("curl", "License file relative to base.yaml"),
+            ("openssl", "Appended after the included overrides"),
+        ]
+    );
+    let lib_ignores = settings
+        .lib_ignores
+        .iter()
+        .map(|x| (x.lib.as_str(), x.comment.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lib_ignores,
+        [
+            ("libc.so.6", "Replaces the ignore of common.yaml"),
+            ("libm.so.6", "Defined in common.yaml"),
+        ]
+    );
+    assert!(
+        settings.overrides[1]
+            .origin
+            .as_ref()
+            .unwrap()
+            .ends_with("shared/shared.yaml")
+    );
+}
+
+#[test]
+fn including_settings_take_precedence_over_included_ones() {
+    let settings = load_included();
+    // Set in the including file:
+    assert_eq!(settings.vcpkg_triplet.as_deref(), Some("x64-linux-release"));
+    assert!(settings.origins["vcpkg_triplet"].ends_with("project/sancus.yaml"));
+    // Only set in an indirectly included file:
+    assert_eq!(settings.vcpkg_host_triplet.as_deref(), Some("x64-linux"));
+    assert!(settings.origins["vcpkg_host_triplet"].ends_with("common/common.yaml"));
+    // Later includes take precedence over earlier ones:
+    assert_eq!(settings.target_os, Some(TargetOs::Macos));
+    assert!(settings.origins["target_os"].ends_with("shared/shared.yaml"));
+}
+
+#[test]
+fn relative_paths_are_relative_to_the_including_file() {
+    let settings = load_included();
+    let curl = settings.overrides.iter().find(|x| x.package == "curl").unwrap();
+    assert_eq!(
+        Path::new(&curl.license_files[0].file),
+        include_dir().join("project/config/LICENSE.curl")
+    );
+    assert!(curl.origin.as_ref().unwrap().ends_with("project/config/base.yaml"));
+
+    // shared.yaml is only found in the shared settings directory:
+    let error = Settings::load(
+        &include_dir().join("project/sancus.yaml"),
+        Some(Path::new("/nonexistent")),
+    )
+    .unwrap_err();
+    assert!(
+        matches!(&error, Error::Settings { path, message } if path.ends_with("sancus.yaml") && message.contains("shared.yaml")),
+        "{error}"
+    );
+}
+
+#[test]
+fn recursive_includes_are_rejected() {
+    let error = Settings::load(&fixture_dir().join("cycle/a.yaml"), None).unwrap_err();
+    assert!(
+        matches!(&error, Error::Settings { path, message } if path.ends_with("a.yaml") && message.contains("recursively")),
+        "{error}"
+    );
+}
+
+/// Settings as JSON, to compare settings loaded from different files.
+fn to_json(settings: &Settings) -> serde_json::Value {
+    serde_json::to_value(settings).unwrap()
+}
+
+#[test]
+fn all_formats_load_the_same_settings() {
+    let load = |name: &str| Settings::load(&fixture_dir().join("formats").join(name), None).unwrap();
+    let yaml = load("sancus.yaml");
+    assert_eq!(yaml.target_os, Some(TargetOs::Linux));
+    assert_eq!(yaml.overrides[0].version.as_deref(), Some(">=5.15, <6"));
+    assert_eq!(yaml.vcpkg_ignores[0].os.as_deref(), Some("linux"));
+    assert!(!yaml.use_vcpkg_default_ignores);
+
+    for name in ["sancus.toml", "sancus.json"] {
+        let settings = load(name);
+        assert_eq!(to_json(&settings), to_json(&yaml), "{name}");
+        assert_eq!(
+            settings.origins.keys().collect::<Vec<_>>(),
+            yaml.origins.keys().collect::<Vec<_>>(),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn checked_in_schema_is_up_to_date() {
+    let schema_file = Path::new(env!("CARGO_MANIFEST_DIR")).join("sancus.schema.json");
+    let checked_in = serde_json::from_str::<serde_json::Value>(&std::fs::read_to_string(schema_file).unwrap()).unwrap();
+    let generated = serde_json::from_str::<serde_json::Value>(&Settings::json_schema().unwrap()).unwrap();
+    assert_eq!(
+        checked_in, generated,
+        "Run `just settings-schema` to update sancus.schema.json"
+    );
+}
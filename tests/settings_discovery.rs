@@ -0,0 +1,108 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+#![cfg(feature = "scan")]
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use sancus_lib::settings::{SETTINGS_FILE_ENV, Settings};
+
+/// Serializes the tests, they depend on `SANCUS_CONFIG`.
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+/// Creates an empty directory with the given files, a `.git` directory marks the root of a repository. git cannot
+/// check in `.git` directories, the tree is created in the temporary directory.
+fn create_tree(name: &str, files: &[&str]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("sancus-{name}-{}", std::process::id()));
+    if root.exists() {
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+    for file in files {
+        let path = root.join(file);
+        if file.ends_with(".git") {
+            std::fs::create_dir_all(&path).unwrap();
+        } else {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, "").unwrap();
+        }
+    }
+    std::fs::create_dir_all(root.join("repo/crates/app")).unwrap();
+    root.canonicalize().unwrap()
+}
+
+fn find(project_path: &Path, settings_file: Option<&Path>) -> Option<PathBuf> {
+    Settings::find_settings_file(project_path, settings_file).unwrap()
+}
+
+#[test]
+fn config_option_takes_precedence_over_the_environment_and_the_walk_up() {
+    let _lock = ENV_LOCK.lock().unwrap();
+    let root = create_tree(
+        "precedence",
+        &["repo/.git", "repo/sancus.yaml", "env.yaml", "option.yaml"],
+    );
+    let project = root.join("repo/crates/app");
+
+    // SAFETY: the tests of this file that read the environment are serialized by ENV_LOCK.
+    unsafe { std::env::remove_var(SETTINGS_FILE_ENV) };
+    assert_eq!(find(&project, None), Some(root.join("repo/sancus.yaml")));
+
+    // SAFETY: see above.
+    unsafe { std::env::set_var(SETTINGS_FILE_ENV, root.join("env.yaml")) };
+    assert_eq!(find(&project, None), Some(root.join("env.yaml")));
+    assert_eq!(
+        find(&project, Some(&root.join("option.yaml"))),
+        Some(root.join("option.yaml"))
+    );
+
+    // A missing file is an error instead of falling back to the walk-up:
+    // SAFETY: see above.
+    unsafe { std::env::set_var(SETTINGS_FILE_ENV, root.join("missing.yaml")) };
+    assert!(Settings::find_settings_file(&project, None).is_err());
+    assert!(Settings::find_settings_file(&project, Some(&root.join("missing.yaml"))).is_err());
+
+    // SAFETY: see above.
+    unsafe { std::env::remove_var(SETTINGS_FILE_ENV) };
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn walk_up_stops_at_the_git_root() {
+    let _lock = ENV_LOCK.lock().unwrap();
+    // SAFETY: the tests of this file that read the environment are serialized by ENV_LOCK.
+    unsafe { std::env::remove_var(SETTINGS_FILE_ENV) };
+
+    // The settings file outside of the repository is not used:
+    let outside = create_tree("git-root", &["sancus.yaml", "repo/.git"]);
+    assert_eq!(find(&outside.join("repo/crates/app"), None), None);
+
+    // The nearest settings file is used, of several formats the first of
+    // `SETTINGS_FILE_NAMES` is used:
+    let root = create_tree(
+        "nearest",
+        &[
+            "repo/.git",
+            "repo/sancus.yaml",
+            "repo/crates/sancus.json",
+            "repo/crates/sancus.toml",
+        ],
+    );
+    assert_eq!(
+        find(&root.join("repo/crates/app"), None),
+        Some(root.join("repo/crates/sancus.toml"))
+    );
+    assert_eq!(find(&root.join("repo"), None), Some(root.join("repo/sancus.yaml")));
+
+    std::fs::remove_dir_all(&outside).unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,282 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+#![cfg(feature = "scan")]
+
+use std::path::{Path, PathBuf};
+
+use sancus_lib::{
+    settings::{LibIgnore, LicenseFileOverride, Override, OverrideMatches, Settings, VcpkgIgnore},
+    settings_lint::{self, Problem, Severity},
+};
+
+fn package_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/crates/project")
+}
+
+fn existing_license_file() -> String {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("LICENSE-MIT")
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn zlib_override() -> Override {
+    Override {
+        package: "zlib".to_owned(),
+        comment: "Checked the sources".to_owned(),
+        license_id: Some("Zlib".to_owned()),
+        ..Default::default()
+    }
+}
+
+fn matched(indices: &[usize]) -> OverrideMatches {
+    indices
+        .iter()
+        .map(|index| (*index, ["rpm package 'zlib'".to_owned()].into()))
+        .collect()
+}
+
+fn messages(problems: &[Problem]) -> Vec<(Severity, &str)> {
+    problems.iter().map(|x| (x.severity, x.message.as_str())).collect()
+}
+
+#[test]
+fn valid_settings_have_no_problems() {
+    let settings = Settings {
+        overrides: vec![Override {
+            license_files: vec![LicenseFileOverride {
+                id: Some("MIT".to_owned()),
+                file: existing_license_file(),
+            }],
+            ..zlib_override()
+        }],
+        lib_ignores: vec![LibIgnore {
+            lib: "Cargo.toml".to_owned(),
+            comment: "Not a library".to_owned(),
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let problems = settings_lint::lint(&settings, &package_root(), Some(&matched(&[0])));
+    assert!(problems.is_empty(), "{problems:?}");
+}
+
+#[test]
+fn unused_overrides_are_reported_if_the_matches_are_known() {
+    let settings = Settings {
+        overrides: vec![
+            zlib_override(),
+            Override {
+                package: "bzip2".to_owned(),
+                ..zlib_override()
+            },
+        ],
+        ..Default::default()
+    };
+    let problems = settings_lint::lint(&settings, &package_root(), Some(&matched(&[0])));
+    assert_eq!(
+        messages(&problems),
+        [(Severity::Warning, "Override 'bzip2' does not match any package")]
+    );
+
+    assert!(settings_lint::lint(&settings, &package_root(), None).is_empty());
+}
+
+#[test]
+fn invalid_spdx_ids_are_errors() {
+    let settings = Settings {
+        overrides: vec![Override {
+            license_id: Some("MIT OR".to_owned()),
+            license_files: vec![LicenseFileOverride {
+                id: Some("NoSuchLicense".to_owned()),
+                file: existing_license_file(),
+            }],
+            ..zlib_override()
+        }],
+        ..Default::default()
+    };
+    let problems = settings_lint::lint(&settings, &package_root(), None);
+    assert_eq!(problems.len(), 2, "{problems:?}");
+    assert!(problems.iter().all(|x| x.severity == Severity::Error));
+    assert_eq!(
+        problems[0].message,
+        "Override 'zlib' has an invalid SPDX license expression 'MIT OR'"
+    );
+    assert!(
+        problems[1]
+            .message
+            .ends_with("of override 'zlib' has an invalid SPDX license id 'NoSuchLicense'")
+    );
+}
+
+#[test]
+fn invalid_selectors_and_versions_are_errors() {
+    let settings = Settings {
+        overrides: vec![
+            Override {
+                package: "regex:(zlib".to_owned(),
+                ..zlib_override()
+            },
+            Override {
+                version: Some("<".to_owned()),
+                ..zlib_override()
+            },
+        ],
+        ..Default::default()
+    };
+    let problems = settings_lint::lint(&settings, &package_root(), None);
+    assert_eq!(problems.len(), 2, "{problems:?}");
+    assert!(problems.iter().all(|x| x.severity == Severity::Error));
+    assert!(
+        problems[0]
+            .message
+            .starts_with("Override 'regex:(zlib' has an invalid regular expression")
+    );
+    assert!(
+        problems[1]
+            .message
+            .starts_with("Override 'zlib' has an invalid version requirement '<'")
+    );
+}
+
+#[test]
+fn license_ref_ids_are_valid() {
+    let settings = Settings {
+        overrides: vec![Override {
+            license_id: Some("LicenseRef-Proprietary".to_owned()),
+            license_files: vec![LicenseFileOverride {
+                id: Some("LicenseRef-Proprietary".to_owned()),
+                file: existing_license_file(),
+            }],
+            ..zlib_override()
+        }],
+        ..Default::default()
+    };
+    assert!(settings_lint::lint(&settings, &package_root(), None).is_empty());
+}
+
+#[test]
+fn missing_files_are_reported() {
+    let settings = Settings {
+        overrides: vec![Override {
+            license_files: vec![LicenseFileOverride {
+                id: None,
+                file: "does-not-exist/LICENSE".to_owned(),
+            }],
+            ..zlib_override()
+        }],
+        lib_ignores: vec![LibIgnore {
+            lib: "libmissing.so".to_owned(),
+            comment: "Removed".to_owned(),
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let problems = settings_lint::lint(&settings, &package_root(), None);
+    assert_eq!(
+        messages(&problems),
+        [
+            (
+                Severity::Error,
+                "License file does-not-exist/LICENSE of override 'zlib' does not exist"
+            ),
+            (
+                Severity::Warning,
+                "Ignored library 'libmissing.so' does not exist in the package"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn empty_comments_are_warnings() {
+    let settings = Settings {
+        overrides: vec![Override {
+            comment: " ".to_owned(),
+            ..zlib_override()
+        }],
+        lib_ignores: vec![LibIgnore {
+            lib: "Cargo.toml".to_owned(),
+            ..Default::default()
+        }],
+        vcpkg_ignores: vec![VcpkgIgnore {
+            directory: "tools".to_owned(),
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let problems = settings_lint::lint(&settings, &package_root(), None);
+    assert_eq!(
+        messages(&problems),
+        [
+            (Severity::Warning, "Override 'zlib' has no comment"),
+            (Severity::Warning, "Ignored library 'Cargo.toml' has no comment"),
+            (Severity::Warning, "Ignored vcpkg directory 'tools' has no comment"),
+        ]
+    );
+}
+
+#[test]
+fn duplicates_are_errors_with_their_origin() {
+    let origin = PathBuf::from("shared.yaml");
+    let ignore = VcpkgIgnore {
+        directory: "tools".to_owned(),
+        comment: "Build tools".to_owned(),
+        os: Some("linux".to_owned()),
+        ..Default::default()
+    };
+    let settings = Settings {
+        overrides: vec![
+            zlib_override(),
+            Override {
+                origin: Some(origin.clone()),
+                ..zlib_override()
+            },
+        ],
+        vcpkg_ignores: vec![
+            ignore.clone(),
+            // Another operating system is no duplicate:
+            VcpkgIgnore {
+                os: None,
+                ..ignore.clone()
+            },
+            VcpkgIgnore {
+                origin: Some(origin.clone()),
+                ..ignore
+            },
+        ],
+        ..Default::default()
+    };
+    let problems = settings_lint::lint(&settings, &package_root(), None);
+    assert_eq!(problems.len(), 2, "{problems:?}");
+    assert!(
+        problems
+            .iter()
+            .all(|x| x.severity == Severity::Error && x.origin.as_ref() == Some(&origin))
+    );
+    assert!(problems[0].message.starts_with("Duplicate override "));
+    assert_eq!(problems[1].message, "Duplicate vcpkg ignore 'tools' os 'linux'");
+}
+
+#[test]
+fn errors_are_sorted_before_warnings() {
+    let settings = Settings {
+        overrides: vec![Override {
+            comment: String::new(),
+            license_id: Some("invalid license".to_owned()),
+            ..zlib_override()
+        }],
+        ..Default::default()
+    };
+    let problems = settings_lint::lint(&settings, &package_root(), Some(&matched(&[])));
+    let severities = problems.iter().map(|x| x.severity).collect::<Vec<_>>();
+    assert_eq!(severities, [Severity::Error, Severity::Warning, Severity::Warning]);
+}
@@ -0,0 +1,104 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+#![cfg(feature = "scan")]
+
+use std::path::{Path, PathBuf};
+
+use sancus_lib::{
+    file_info::FileInfo,
+    license_detector::{LicenseDetector, TemplateSet},
+    package_source::PackageSource,
+    settings::Overrides,
+    system_packages::{PackageManager, SystemPackages},
+};
+
+fn fixture_root(manager: PackageManager) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(manager.name())
+        .join("root")
+}
+
+fn open(manager: PackageManager) -> SystemPackages {
+    let root = fixture_root(manager);
+    assert_eq!(PackageManager::detect(&root), manager);
+    SystemPackages::open(manager, &root).unwrap()
+}
+
+fn lib(name: &str) -> FileInfo {
+    // The library of the package is no binary, it is looked up by its name:
+    FileInfo::new(name.to_owned(), Path::new("/tmp/package/lib").join(name).as_path())
+}
+
+fn owner(packages: &SystemPackages, name: &str) -> Option<String> {
+    packages.package_of_file(&lib(name)).unwrap()
+}
+
+#[test]
+fn apk_libraries_are_attributed_by_their_resolved_path() {
+    let packages = open(PackageManager::Apk);
+    // zlib-doc installs a file of the same name, but not into a library directory:
+    assert_eq!(owner(&packages, "libz.so.1").as_deref(), Some("zlib"));
+    // The SONAME link is owned by no package, its target is:
+    assert_eq!(owner(&packages, "libbz2.so.1").as_deref(), Some("libbz2"));
+    assert_eq!(owner(&packages, "libssl.so.3"), None);
+}
+
+#[test]
+fn pacman_libraries_are_attributed_by_their_resolved_path() {
+    let packages = open(PackageManager::Pacman);
+    // /lib64 is a link to usr/lib:
+    assert_eq!(owner(&packages, "libz.so.1").as_deref(), Some("zlib"));
+    assert_eq!(owner(&packages, "libz.so").as_deref(), Some("zlib"));
+    assert_eq!(owner(&packages, "libbz2.so.1").as_deref(), Some("bzip2"));
+    assert_eq!(owner(&packages, "libssl.so.3"), None);
+}
+
+#[test]
+fn files_are_matched_by_their_exact_path() {
+    for manager in [PackageManager::Apk, PackageManager::Pacman] {
+        let packages = open(manager);
+        assert_eq!(
+            packages
+                .package_name_of_file("usr/share/doc/zlib/libz.so.1")
+                .unwrap()
+                .as_deref(),
+            Some("zlib-doc")
+        );
+        assert_eq!(
+            packages
+                .package_name_of_file("/usr/share/licenses/zlib/LICENSE")
+                .unwrap()
+                .as_deref(),
+            Some("zlib")
+        );
+        assert!(packages.package_name_of_file("libz.so.1").unwrap().is_none());
+    }
+}
+
+#[test]
+fn license_info_contains_the_installed_license_files() {
+    let detector = LicenseDetector::builder().template_set(TemplateSet::None).build();
+    for (manager, version) in [(PackageManager::Apk, "1.3.1-r2"), (PackageManager::Pacman, "1.3.1-2")] {
+        let packages = open(manager);
+        let info = packages
+            .license_info("zlib", lib("libz.so.1"), &Overrides::default(), &detector)
+            .unwrap();
+        assert_eq!(info.license, "Zlib");
+        assert_eq!(info.version.as_deref(), Some(version));
+        assert_eq!(info.license_texts.len(), 1, "{manager}");
+        assert!(
+            packages
+                .license_info("zlib-ng", lib("libz.so.1"), &Overrides::default(), &detector)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,54 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+#![cfg(feature = "scan")]
+
+use sancus_lib::target_os::TargetOs;
+
+#[test]
+fn linux_libraries_end_with_so_and_version() {
+    for name in [
+        "libz.so",
+        "libz.so.1",
+        "libz.so.1.3.1",
+        "_ssl.cpython-312-x86_64-linux-gnu.so",
+    ] {
+        assert!(TargetOs::Linux.is_library(name), "{name}");
+    }
+    for name in [
+        "libz.so.1.debug",
+        "libz.so.1.3.1-gdb.py",
+        "foo.socket",
+        "libz.a",
+        "libz.so.",
+    ] {
+        assert!(!TargetOs::Linux.is_library(name), "{name}");
+    }
+}
+
+#[test]
+fn windows_libraries_have_dll_extension() {
+    for name in ["zlib1.dll", "ZLIB1.DLL", "Qt6Core.Dll"] {
+        assert!(TargetOs::Windows.is_library(name), "{name}");
+    }
+    for name in ["libfoo.dll.a", "foo.dll.manifest", "foo.dylib", "dll"] {
+        assert!(!TargetOs::Windows.is_library(name), "{name}");
+    }
+}
+
+#[test]
+fn macos_libraries_have_dylib_extension() {
+    for name in ["libz.1.dylib", "Foo.DyLib"] {
+        assert!(TargetOs::Macos.is_library(name), "{name}");
+    }
+    for name in ["foo.dylib.dSYM", "libz.a", "zlib1.dll"] {
+        assert!(!TargetOs::Macos.is_library(name), "{name}");
+    }
+}
@@ -0,0 +1,85 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+#![cfg(feature = "scan")]
+
+use sancus_lib::{
+    error::Error,
+    file_info,
+    license_detector::{LicenseDetector, TemplateSet},
+    third_party_licenses::{License, ThirdPartyLibrary, ThirdPartyLicenses},
+};
+
+fn library(package_name: &str) -> ThirdPartyLibrary {
+    ThirdPartyLibrary {
+        package_name: package_name.to_owned(),
+        package_version: "1.0.0".to_owned(),
+        license: "MIT".to_owned(),
+        licenses: vec![License {
+            license: "MIT".to_owned(),
+            text: "MIT License".to_owned(),
+        }],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn package_dir_names_stay_inside_the_parent_directory() {
+    assert_eq!(file_info::package_dir_name("zlib"), "zlib");
+    assert_eq!(
+        file_info::package_dir_name("github.com/pkg/errors"),
+        "github.com_pkg_errors"
+    );
+    assert_eq!(file_info::package_dir_name("@types/node"), "@types_node");
+    assert_eq!(file_info::package_dir_name("C:\\lib"), "C__lib");
+    assert_eq!(file_info::package_dir_name(".."), "_..");
+    assert_eq!(file_info::package_dir_name("."), "_.");
+    assert_eq!(file_info::package_dir_name("../etc"), ".._etc");
+}
+
+#[test]
+fn packages_are_exported_into_sanitized_directories() {
+    let result_dir = std::env::temp_dir().join(format!("sancus-export-{}", std::process::id()));
+    let licenses = ThirdPartyLicenses {
+        root_name: "app".to_owned(),
+        third_party_libraries: vec![library("github.com/pkg/errors"), library("@scope/pkg"), library("..")],
+    };
+    licenses.export(&result_dir).unwrap();
+
+    let app_dir = result_dir.join("app");
+    let mut dirs = std::fs::read_dir(&app_dir)
+        .unwrap()
+        .map(|x| x.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    dirs.sort();
+    assert_eq!(dirs, ["@scope_pkg", "_..", "github.com_pkg_errors"]);
+    assert!(app_dir.join("github.com_pkg_errors/MIT.txt").is_file());
+    assert!(app_dir.join("_../README.txt").is_file());
+    assert!(!result_dir.join("README.txt").exists());
+
+    std::fs::remove_dir_all(&result_dir).unwrap();
+}
+
+#[test]
+fn detecting_without_templates_is_an_error() {
+    let detector = LicenseDetector::builder().template_set(TemplateSet::None).build();
+    let error = detector
+        .detect_license("zlib", &[], "Permission is granted")
+        .unwrap_err();
+    assert!(
+        matches!(&error, Error::NoLicenseTemplates(package) if package == "zlib"),
+        "{error}"
+    );
+
+    // The declared license is used as template:
+    let zlib = spdx::license_id("Zlib").unwrap();
+    let text = detector.detect_license("zlib", &[zlib], zlib.text()).unwrap();
+    assert_eq!(text.id, "Zlib");
+}
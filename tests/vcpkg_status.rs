@@ -0,0 +1,213 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+#![cfg(feature = "scan")]
+
+use std::path::{Path, PathBuf};
+
+use sancus_lib::{
+    asset_info::{self, Asset, AssetKind},
+    file_info::FileInfo,
+    license_detector::{LicenseDetector, TemplateSet},
+    license_info::LicenseInfo,
+    settings::Overrides,
+    vcpkg_status::VcpkgStatus,
+};
+
+fn fixture_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/vcpkg/status")
+}
+
+fn status() -> VcpkgStatus {
+    VcpkgStatus::load(&fixture_dir()).unwrap()
+}
+
+#[test]
+fn installed_ports_are_read() {
+    let status = status();
+    let mut ports = status
+        .ports()
+        .iter()
+        .map(|port| format!("{}:{}@{}", port.name, port.triplet, port.version))
+        .collect::<Vec<_>>();
+    ports.sort();
+    assert_eq!(
+        ports,
+        [
+            "bzip2:x64-linux_release@1.0.8#6",
+            "ffmpeg:x64-linux-dyn@7.0#2",
+            "icu:x64-linux-dyn@74.2",
+            "pkgconf:x64-linux@2.2.0",
+            "x264:x64-linux-dyn@0.164.3107",
+            "zlib:x64-linux-dyn@1.3.1#1",
+        ]
+    );
+}
+
+#[test]
+fn updates_supersede_the_status() {
+    let status = status();
+    // The update removes openssl and raises the port version of zlib:
+    assert!(status.find_port("openssl", "x64-linux-dyn").is_none());
+    assert_eq!(status.find_port("zlib", "x64-linux-dyn").unwrap().version, "1.3.1#1");
+}
+
+#[test]
+fn continuation_lines_extend_fields() {
+    let status = status();
+    let ffmpeg = status.find_port("ffmpeg", "x64-linux-dyn").unwrap();
+    assert_eq!(ffmpeg.default_features, ["avcodec", "swscale"]);
+}
+
+#[test]
+fn host_dependencies_and_self_references_are_skipped() {
+    let status = status();
+    let ffmpeg = status.find_port("ffmpeg", "x64-linux-dyn").unwrap();
+    assert_eq!(ffmpeg.dependencies, ["zlib"]);
+    assert_eq!(ffmpeg.enabled_features(), ["core", "avcodec", "gpl"]);
+    assert_eq!(ffmpeg.all_dependencies(), ["zlib", "x264"]);
+}
+
+#[test]
+fn file_lists_are_assigned_to_ports() {
+    let status = status();
+    let zlib = status.find_port("zlib", "x64-linux-dyn").unwrap();
+    assert_eq!(
+        zlib.files,
+        ["x64-linux-dyn/lib/libz.so.1", "x64-linux-dyn/share/zlib/copyright"]
+    );
+    assert!(status.find_port("x264", "x64-linux-dyn").unwrap().files.is_empty());
+}
+
+#[test]
+fn file_lists_of_triplets_with_underscores_are_assigned() {
+    let status = status();
+    let bzip2 = status.find_port("bzip2", "x64-linux_release").unwrap();
+    assert_eq!(
+        bzip2.files,
+        [
+            "x64-linux_release/lib/libbz2.so.1.0",
+            "x64-linux_release/share/bzip2/copyright"
+        ]
+    );
+    assert_eq!(
+        status
+            .find_port_of_file(Path::new("x64-linux_release/lib/libbz2.so.1.0"))
+            .map(|port| port.name.as_str()),
+        Some("bzip2")
+    );
+}
+
+/// Dependency tree of a port as `name -> dependency` edges, built from the direct dependencies of the ports.
+fn dependency_edges(status: &VcpkgStatus, name: &str, triplet: &str, edges: &mut Vec<String>) {
+    let port = status.find_port(name, triplet).unwrap();
+    for dependency in port.all_dependencies() {
+        edges.push(format!("{name} -> {dependency}"));
+        dependency_edges(status, &dependency, triplet, edges);
+    }
+}
+
+#[test]
+fn dependency_tree_is_reconstructed_from_the_edges_of_the_ports() {
+    let status = status();
+    let mut edges = vec![];
+    dependency_edges(&status, "ffmpeg", "x64-linux-dyn", &mut edges);
+    // x264 is a dependency of the gpl feature, pkgconf a build tool of the host triplet:
+    assert_eq!(edges, ["ffmpeg -> zlib", "ffmpeg -> x264"]);
+    let ffmpeg = status.find_port("ffmpeg", "x64-linux-dyn").unwrap();
+    assert_eq!(ffmpeg.features[1].name, "gpl");
+    assert_eq!(ffmpeg.features[1].dependencies, ["x264"]);
+
+    // The port depends on one of its own features, that is no edge:
+    assert!(
+        status
+            .find_port("bzip2", "x64-linux_release")
+            .unwrap()
+            .all_dependencies()
+            .is_empty()
+    );
+}
+
+#[test]
+fn missing_status_database_is_empty() {
+    let status = VcpkgStatus::load(&fixture_dir().join("vcpkg")).unwrap();
+    assert!(status.ports().is_empty());
+}
+
+#[test]
+fn port_of_file_is_found_by_its_path() {
+    let status = status();
+    let port = |path: &Path| status.find_port_of_file(path).map(|port| port.name.as_str());
+    assert_eq!(port(&fixture_dir().join("x64-linux-dyn/lib/libz.so.1")), Some("zlib"));
+    assert_eq!(port(Path::new("x64-linux-dyn/lib/libz.so.1")), Some("zlib"));
+    // Files with the same name in other directories are not installed by the port:
+    assert_eq!(port(&fixture_dir().join("x64-linux-dyn/lib/debug/libz.so.1")), None);
+    assert_eq!(port(Path::new("libz.so.1")), None);
+}
+
+#[test]
+fn port_of_triplet_file_is_found_by_its_path() {
+    let status = status();
+    let port = |path: &str| status.find_port_of_triplet_file(path).map(|port| port.name.as_str());
+    assert_eq!(port("share/icu/74.2/icudt74l.dat"), Some("icu"));
+    assert_eq!(port("icudt74l.dat"), None);
+    assert_eq!(port("data/share/icu/74.2/icudt74l.dat"), None);
+}
+
+fn data_asset(relative_path: &str) -> Asset {
+    let name = relative_path.rsplit('/').next().unwrap_or(relative_path);
+    Asset {
+        kind: AssetKind::Data,
+        name: name.to_owned(),
+        file: FileInfo::new(name.to_owned(), &Path::new("/tmp/package").join(relative_path)),
+        relative_path: relative_path.to_owned(),
+        font_names: None,
+    }
+}
+
+#[test]
+fn assets_are_attributed_by_their_path() {
+    let icu = LicenseInfo {
+        lib_info: FileInfo::new(
+            "libicuuc.so.74".to_owned(),
+            Path::new("/tmp/package/lib/libicuuc.so.74"),
+        ),
+        package_name: "icu".to_owned(),
+        license: "ICU".to_owned(),
+        license_expression: None,
+        license_texts: vec![],
+        version: Some("74.2".to_owned()),
+        url: None,
+        features: vec![],
+        dependencies: vec![],
+        binaries: vec![],
+    };
+    let assets = [
+        data_asset("share/icu/74.2/icudt74l.dat"),
+        data_asset("share/icu/74.2/icudt74l.dat"),
+        data_asset("lib/icudt74l.dat"),
+    ];
+    let licenses = asset_info::get_license_info(
+        &assets,
+        &status(),
+        &[icu],
+        None,
+        &Overrides::default(),
+        &LicenseDetector::builder().template_set(TemplateSet::None).build(),
+    )
+    .unwrap();
+
+    assert_eq!(licenses.len(), 2);
+    assert_eq!(licenses[0].package_name, "icu");
+    assert_eq!(licenses[0].binaries, ["share/icu/74.2/icudt74l.dat"]);
+    assert_eq!(licenses[1].package_name, "icudt74l.dat");
+    assert_eq!(licenses[1].license, "NOASSERTION");
+    assert_eq!(licenses[1].binaries, ["lib/icudt74l.dat"]);
+}
@@ -0,0 +1,103 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// SPDX-FileCopyrightText: 2024 X-Software GmbH <opensource@x-software.com>
+
+#![cfg(feature = "scan")]
+
+use std::cmp::Ordering;
+
+use sancus_lib::version::{self, VersionConstraint};
+
+fn assert_order(older: &str, newer: &str) {
+    assert_eq!(version::compare(older, newer), Ordering::Less, "{older} < {newer}");
+    assert_eq!(version::compare(newer, older), Ordering::Greater, "{newer} > {older}");
+}
+
+#[test]
+fn versions_are_compared_like_rpmvercmp() {
+    assert_order("1.0", "1.0.1");
+    assert_order("1.9", "1.10");
+    assert_order("2.0", "10.0");
+    // Alphabetic segments are older than numeric ones:
+    assert_order("1.0a", "1.0.1");
+    assert_order("1.0a", "1.0b");
+    assert_order("1.0", "1.0a");
+    // Leading zeros are ignored:
+    assert_eq!(version::compare("1.01", "1.1"), Ordering::Equal);
+    // Separators only separate segments:
+    assert_eq!(version::compare("1.0_1", "1.0.1"), Ordering::Equal);
+}
+
+#[test]
+fn tilde_sorts_before_and_caret_after_the_version() {
+    assert_order("1.0~rc1", "1.0");
+    assert_order("1.0~rc1", "1.0~rc2");
+    assert_order("1.0~~", "1.0~rc1");
+    assert_order("1.0", "1.0^git1");
+    assert_order("1.0^git1", "1.0.1");
+}
+
+#[test]
+fn epochs_take_precedence_over_versions() {
+    assert_order("2.0", "1:1.0");
+    assert_order("1:1.0", "2:0.1");
+    assert_eq!(version::compare("0:1.0", "1.0"), Ordering::Equal);
+}
+
+#[test]
+fn releases_are_only_compared_if_both_versions_have_one() {
+    assert_order("1.0-1", "1.0-2");
+    assert_order("1.0-9", "1.0-10");
+    assert_eq!(version::compare("1.0", "1.0-2"), Ordering::Equal);
+    assert_order("1.0-2", "1.0.1-1");
+}
+
+#[test]
+fn constraints_combine_comparisons() {
+    let constraint = VersionConstraint::parse(">=5.15, <6").unwrap();
+    assert!(constraint.matches("5.15.2"));
+    assert!(constraint.matches("5.15"));
+    assert!(!constraint.matches("5.9"));
+    assert!(!constraint.matches("6.0"));
+
+    let constraint = VersionConstraint::parse("!=1.2.3").unwrap();
+    assert!(constraint.matches("1.2.4"));
+    assert!(!constraint.matches("1.2.3"));
+
+    // A plain version requires the version:
+    let constraint = VersionConstraint::parse("1.3.1").unwrap();
+    assert!(constraint.matches("1.3.1"));
+    assert!(!constraint.matches("1.3.10"));
+
+    assert!(VersionConstraint::parse("*").unwrap().matches("0.1"));
+}
+
+#[test]
+fn caret_and_tilde_select_compatible_versions() {
+    let caret = VersionConstraint::parse("^1.2").unwrap();
+    assert!(caret.matches("1.2.0"));
+    assert!(caret.matches("1.9"));
+    assert!(!caret.matches("2.0"));
+    assert!(!caret.matches("1.1.9"));
+
+    let caret = VersionConstraint::parse("^0.2.3").unwrap();
+    assert!(caret.matches("0.2.9"));
+    assert!(!caret.matches("0.3.0"));
+
+    let tilde = VersionConstraint::parse("~1.2").unwrap();
+    assert!(tilde.matches("1.2.7"));
+    assert!(!tilde.matches("1.3"));
+}
+
+#[test]
+fn missing_versions_are_invalid() {
+    for constraint in [">=", ">=1.0, <", "^", "~ "] {
+        assert!(VersionConstraint::parse(constraint).is_err(), "{constraint}");
+    }
+}